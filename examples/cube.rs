@@ -0,0 +1,76 @@
+use std::time::Instant;
+
+use rug::*;
+
+const CORNERS: [Vec3<f32>; 8] = [
+    Vec3::new(-1.0, -1.0, -1.0),
+    Vec3::new(1.0, -1.0, -1.0),
+    Vec3::new(1.0, 1.0, -1.0),
+    Vec3::new(-1.0, 1.0, -1.0),
+    Vec3::new(-1.0, -1.0, 1.0),
+    Vec3::new(1.0, -1.0, 1.0),
+    Vec3::new(1.0, 1.0, 1.0),
+    Vec3::new(-1.0, 1.0, 1.0),
+];
+// each face goes anti-clockwise when looking at it from outside the cube
+const FACES: [([usize; 4], Color); 6] = [
+    ([4, 5, 6, 7], Color::RED),
+    ([1, 0, 3, 2], Color::GREEN),
+    ([5, 1, 2, 6], Color::BLUE),
+    ([0, 4, 7, 3], Color::YELLOW),
+    ([7, 6, 2, 3], Color::CYAN),
+    ([0, 1, 5, 4], Color::MAGENTA),
+];
+
+struct AppState {
+    start: Instant,
+}
+impl CanvasApp for AppState {
+    fn setup(&mut self, _window: &mut Window) {}
+
+    fn render(&mut self, g: &mut Graphics, window: &mut Window) {
+        g.fill(Color::BLACK);
+
+        let t = self.start.elapsed().as_secs_f32();
+        let size = g.size().map(|e| e as f32);
+        let camera = Renderer3d::new()
+            .with_view(Mat4::look_at(
+                Vec3::new(0.0, 1.0, 5.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ))
+            .with_projection(Mat4::perspective(1.0, size.x / size.y, 0.1, 100.0))
+            // the depth buffer decides which faces are in front, so none have to be culled
+            .with_cull(CullMode::None);
+
+        // two cubes spinning through each other
+        let spin = Mat4::rotate_y(t) * Mat4::rotate_x(t * 0.7);
+        let models = [
+            Mat4::translate(Vec3::new(-0.6, 0.0, 0.0)) * spin,
+            Mat4::translate(Vec3::new(0.6, 0.0, 0.0))
+                * Mat4::scale(Vec3::new_all(0.7))
+                * spin.transpose(),
+        ];
+        for model in models {
+            let renderer = camera.clone().with_model(model);
+            for (face, color) in FACES {
+                let [a, b, c, d] = face.map(|i| CORNERS[i]);
+                renderer.fill_tri(g, [a, b, c], color);
+                renderer.fill_tri(g, [a, c, d], color);
+            }
+        }
+
+        window.set_title(&format!("fps: {}", window.tracked_fps));
+    }
+}
+
+fn main() {
+    let app_state = AppState {
+        start: Instant::now(),
+    };
+    let config = CanvasConfig::new()
+        .with_title("Cube")
+        .with_size(Vec2::new(600, 600))
+        .with_depth_buffer(true);
+    run_canvas_app(app_state, config);
+}
@@ -1,20 +1,30 @@
+use rug::fonts::Font;
 use rug::*;
 
-struct AppState;
+struct AppState {
+    font: Font<'static>,
+}
 impl CanvasApp for AppState {
     fn setup(&mut self, _window: &mut Window) {}
 
     fn render(&mut self, g: &mut Graphics, window: &mut Window) {
         g.fill(Color::RED);
 
+        g.set_font(self.font.clone());
+        g.set_font_size(48.0);
+        g.draw_str("Hello, World!", Vec2::new(20, 20), Color::WHITE);
+
         window.set_title(&format!("fps: {}", window.tracked_fps));
     }
 }
 
 fn main() {
+    let app_state = AppState {
+        font: include_font!("menlo-regular.ttf").unwrap(),
+    };
     let config = CanvasConfig::new()
         .with_title("Hello, World!")
         .with_size(Vec2::new(400, 400));
 
-    run_canvas_app(AppState, config);
+    run_canvas_app(app_state, config);
 }
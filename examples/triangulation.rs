@@ -15,7 +15,10 @@ impl App {
         #[cfg(target_os = "windows")]
         let size = 600.0;
 
-        let polys = build_text("A", Vec2::new(40, 80), &font, size);
+        let polys: Vec<Poly> = build_text("A", Vec2::new(40.0, 80.0), &font, size)
+            .iter()
+            .map(|contour| contour.to_poly())
+            .collect();
 
         let tris = triangulate(&polys[1].points).unwrap();
 
@@ -0,0 +1,156 @@
+use crate::graphics::Color;
+
+/// How a color being drawn (the source) is combined with the color already in the buffer (the destination).
+///
+/// Apart from `Replace`, every mode computes a blended color channel-by-channel,
+/// then composites it over the destination using the source's alpha.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum BlendMode {
+    /// The source overwrites the destination, alpha included.
+    Replace,
+    /// The source is drawn on top of the destination.
+    #[default]
+    SourceOver,
+    /// `min(s + d, 255)`
+    Add,
+    /// `s * d / 255`
+    Multiply,
+    /// `255 - (255 - s) * (255 - d) / 255`
+    Screen,
+    /// `max(d - s, 0)`
+    Subtract,
+    /// `min(s, d)`
+    Min,
+    /// `max(s, d)`
+    Max,
+    /// `s ^ d`
+    Xor,
+}
+impl BlendMode {
+    /// Combines a single color channel of the source (`s`) and destination (`d`).
+    ///
+    /// `Replace` and `SourceOver` simply return `s`.
+    #[inline(always)]
+    pub fn blend_channel(self, s: u8, d: u8) -> u8 {
+        let (s32, d32) = (s as u32, d as u32);
+        match self {
+            Self::Replace | Self::SourceOver => s,
+            Self::Add => s.saturating_add(d),
+            Self::Multiply => ((s32 * d32 + 127) / 255) as u8,
+            Self::Screen => (255 - ((255 - s32) * (255 - d32) + 127) / 255) as u8,
+            Self::Subtract => d.saturating_sub(s),
+            Self::Min => s.min(d),
+            Self::Max => s.max(d),
+            Self::Xor => s ^ d,
+        }
+    }
+
+    /// Blends `src` onto `dst`, returning the resulting color.
+    pub fn blend(self, src: Color, dst: Color) -> Color {
+        match self {
+            Self::Replace => src,
+            Self::SourceOver => src.blend_over(dst),
+            _ => {
+                if src.a == 0 {
+                    return dst;
+                }
+                // where the destination is transparent, there is nothing to blend with,
+                // so the source color shows through unchanged
+                let da = dst.a as u32;
+                let mix = |s: u8, d: u8| {
+                    let b = self.blend_channel(s, d) as u32;
+                    ((s as u32 * (255 - da) + b * da + 127) / 255) as u8
+                };
+                let mixed = Color::rgba(
+                    mix(src.r, dst.r),
+                    mix(src.g, dst.g),
+                    mix(src.b, dst.b),
+                    src.a,
+                );
+                mixed.blend_over(dst)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: Color = Color::rgb(200, 100, 30);
+    const DST: Color = Color::rgb(50, 180, 255);
+
+    fn check(mode: BlendMode, f: impl Fn(u32, u32) -> u32) {
+        let expected = |s: u8, d: u8| f(s as u32, d as u32) as u8;
+        assert_eq!(
+            mode.blend(SRC, DST),
+            Color::rgb(
+                expected(SRC.r, DST.r),
+                expected(SRC.g, DST.g),
+                expected(SRC.b, DST.b),
+            ),
+            "{:?}",
+            mode
+        );
+    }
+
+    #[test]
+    fn opaque_formulas() {
+        check(BlendMode::Replace, |s, _| s);
+        check(BlendMode::SourceOver, |s, _| s);
+        check(BlendMode::Add, |s, d| (s + d).min(255));
+        check(BlendMode::Multiply, |s, d| (s * d + 127) / 255);
+        check(BlendMode::Screen, |s, d| {
+            255 - ((255 - s) * (255 - d) + 127) / 255
+        });
+        check(BlendMode::Subtract, |s, d| d.saturating_sub(s));
+        check(BlendMode::Min, |s, d| s.min(d));
+        check(BlendMode::Max, |s, d| s.max(d));
+        check(BlendMode::Xor, |s, d| s ^ d);
+    }
+
+    #[test]
+    fn translucent_source() {
+        // a half transparent source moves the destination halfway towards the blended color
+        let src = Color::rgba(200, 100, 30, 128);
+        let blended = BlendMode::Add.blend(src, DST);
+        let lerp = |b: u32, d: u32| ((b * 128 + d * 127 + 127) / 255) as u8;
+        assert_eq!(
+            blended,
+            Color::rgb(lerp(250, 50), lerp(255, 180), lerp(255, 255))
+        );
+
+        assert_eq!(BlendMode::Multiply.blend(Color::rgba(0, 0, 0, 0), DST), DST);
+        assert_eq!(
+            BlendMode::Replace.blend(Color::rgba(1, 2, 3, 4), DST),
+            Color::rgba(1, 2, 3, 4)
+        );
+    }
+
+    #[test]
+    fn source_over() {
+        let src = Color::rgba(255, 0, 0, 128);
+        assert_eq!(
+            BlendMode::SourceOver.blend(src, Color::rgb(0, 0, 255)),
+            Color::rgb(128, 0, 127)
+        );
+        // over a transparent destination, the source is unchanged
+        assert_eq!(
+            BlendMode::SourceOver.blend(src, Color::rgba(0, 0, 0, 0)),
+            src
+        );
+        assert_eq!(
+            BlendMode::SourceOver.blend(src, Color::rgba(0, 0, 255, 128)),
+            Color::rgba(170, 0, 85, 192)
+        );
+    }
+
+    #[test]
+    fn transparent_destination() {
+        // with nothing to blend with, every mode behaves like source-over
+        let dst = Color::rgba(0, 0, 0, 0);
+        for mode in [BlendMode::Multiply, BlendMode::Xor, BlendMode::Subtract] {
+            assert_eq!(mode.blend(SRC, dst), SRC);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::vectors::Vec2;
+
+/// How the depth of a new pixel is compared with the depth already in a `DepthBuffer`.
+/// The pixel is only drawn if the comparison passes.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum DepthCompare {
+    Never,
+    /// Drawn if the new depth is less than the stored one, so nearer pixels cover farther ones.
+    #[default]
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+    NotEqual,
+    Always,
+}
+impl DepthCompare {
+    /// If a pixel at `depth` passes over a pixel already at `stored`.
+    #[inline(always)]
+    pub fn passes(self, depth: f32, stored: f32) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Less => depth < stored,
+            Self::LessEqual => depth <= stored,
+            Self::Equal => depth == stored,
+            Self::GreaterEqual => depth >= stored,
+            Self::Greater => depth > stored,
+            Self::NotEqual => depth != stored,
+            Self::Always => true,
+        }
+    }
+}
+
+/// A depth for every pixel of a buffer, so that what's drawn doesn't depend on the order it's drawn in.
+///
+/// Attach it with `Graphics::set_depth_buffer`, and depth-tested drawing (`Graphics::fill_tri_depth` and `Renderer3d`)
+/// only draws pixels that pass `compare`, writing their depth if `write` is set.
+/// It should be the same size as the buffer being drawn to, and pixels outside of it are never depth tested.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthBuffer {
+    size: Vec2<u32>,
+    depths: Vec<f32>,
+    pub compare: DepthCompare,
+    /// If the depths of pixels that pass are written to the buffer.
+    pub write: bool,
+}
+impl DepthBuffer {
+    /// The depth that a new or cleared buffer is filled with, which is behind everything.
+    pub const FAR: f32 = f32::INFINITY;
+
+    pub fn new(size: Vec2<u32>) -> Self {
+        Self {
+            size,
+            depths: vec![Self::FAR; size.x as usize * size.y as usize],
+            compare: DepthCompare::default(),
+            write: true,
+        }
+    }
+    pub fn with_compare(mut self, compare: DepthCompare) -> Self {
+        self.compare = compare;
+        self
+    }
+    pub fn with_write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> Vec2<u32> {
+        self.size
+    }
+    /// The depth of each pixel, a row at a time.
+    #[inline(always)]
+    pub fn depths(&self) -> &[f32] {
+        &self.depths
+    }
+
+    /// Sets every pixel to `depth`, which is usually `DepthBuffer::FAR` (or `-DepthBuffer::FAR` when comparing with greater).
+    pub fn clear(&mut self, depth: f32) {
+        self.depths.fill(depth);
+    }
+    /// Changes the size of the buffer, clearing it to `DepthBuffer::FAR`.
+    pub fn resize(&mut self, size: Vec2<u32>) {
+        self.size = size;
+        self.depths.clear();
+        self.depths
+            .resize(size.x as usize * size.y as usize, Self::FAR);
+    }
+
+    pub fn get(&self, pos: Vec2<i32>) -> Option<f32> {
+        self.index(pos).map(|i| self.depths[i])
+    }
+
+    /// If a pixel at `depth` passes the depth test, in which case its depth is written (if `write` is set).
+    pub fn test(&mut self, pos: Vec2<i32>, depth: f32) -> bool {
+        let Some(i) = self.index(pos) else {
+            return true;
+        };
+        if !self.compare.passes(depth, self.depths[i]) {
+            return false;
+        }
+        if self.write {
+            self.depths[i] = depth;
+        }
+        true
+    }
+
+    #[inline(always)]
+    fn index(&self, pos: Vec2<i32>) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x as i32 || pos.y >= self.size.y as i32 {
+            return None;
+        }
+        Some(pos.y as usize * self.size.x as usize + pos.x as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_functions() {
+        use DepthCompare::*;
+        let expected = [
+            (Never, [false, false, false]),
+            (Less, [true, false, false]),
+            (LessEqual, [true, true, false]),
+            (Equal, [false, true, false]),
+            (GreaterEqual, [false, true, true]),
+            (Greater, [false, false, true]),
+            (NotEqual, [true, false, true]),
+            (Always, [true, true, true]),
+        ];
+        for (compare, passes) in expected {
+            assert_eq!(
+                [0.0, 0.5, 1.0].map(|d| compare.passes(d, 0.5)),
+                passes,
+                "{compare:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn testing_writes_depths() {
+        let mut depth = DepthBuffer::new(Vec2::new(4, 3));
+        let pos = Vec2::new(3, 2);
+        assert_eq!(depth.get(pos), Some(DepthBuffer::FAR));
+        assert!(depth.test(pos, 0.5));
+        assert!(!depth.test(pos, 0.7));
+        assert!(depth.test(pos, 0.2));
+        assert_eq!(depth.get(pos), Some(0.2));
+        // only the tested pixel is changed
+        assert_eq!(
+            depth
+                .depths()
+                .iter()
+                .filter(|&&d| d != DepthBuffer::FAR)
+                .count(),
+            1
+        );
+
+        // outside of the buffer always passes
+        assert!(depth.test(Vec2::new(4, 0), 1.0));
+        assert_eq!(depth.get(Vec2::new(-1, 0)), None);
+
+        let mut read_only = depth
+            .clone()
+            .with_write(false)
+            .with_compare(DepthCompare::Greater);
+        assert!(read_only.test(pos, 0.5));
+        assert_eq!(read_only.get(pos), Some(0.2));
+
+        depth.clear(-DepthBuffer::FAR);
+        assert_eq!(depth.get(pos), Some(-DepthBuffer::FAR));
+        depth.resize(Vec2::new(2, 2));
+        assert_eq!(depth.depths(), &[DepthBuffer::FAR; 4]);
+    }
+}
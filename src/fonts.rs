@@ -1,7 +1,15 @@
-use crate::shapes::{remove_dup_points, Poly};
+use crate::glyph_cache::FontId;
+use crate::graphics::{Color, Image};
+use crate::path;
+use crate::shapes::{Poly, Rect};
 use crate::vectors::Vec2;
 pub use rusttype::Font;
-use rusttype::{OutlineBuilder, Point, Scale};
+use rusttype::{
+    Glyph, GlyphId, OutlineBuilder, Point, PositionedGlyph, Scale, ScaledGlyph, VMetrics,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
 
 #[macro_export]
 macro_rules! include_font {
@@ -10,95 +18,1480 @@ macro_rules! include_font {
     }};
 }
 
-pub fn build_text(text: &str, pos: Vec2<i32>, font: &Font, size: f32) -> Vec<Poly> {
-    let layout = font.layout(
-        text,
-        Scale::uniform(size),
-        Point {
-            x: pos.x as f32,
-            y: pos.y as f32,
-        },
-    );
-    let mut builder = TextBuilder::new();
-    for glyph in layout {
-        let pos = glyph.position();
-        builder.glyph_pos = Vec2::new(pos.x as i32, pos.y as i32);
+/// Builds the outlines of `text` into contours, with the baseline of the first glyph starting at `pos`.
+///
+/// Curves are flattened to within `path::DEFAULT_TOLERANCE` pixels at the requested `size`,
+/// so larger text gets more points per curve. The contours can be filled with `Graphics::fill_polys_f32`.
+pub fn build_text(text: &str, pos: Vec2<f32>, font: &Font, size: f32) -> Vec<Contour> {
+    build_text_with_tolerance(text, pos, font, size, path::DEFAULT_TOLERANCE)
+}
+
+/// Like `build_text`, but curves are flattened to within `tolerance` pixels.
+pub fn build_text_with_tolerance(
+    text: &str,
+    pos: Vec2<f32>,
+    font: &Font,
+    size: f32,
+    tolerance: f32,
+) -> Vec<Contour> {
+    let ascent = font.v_metrics(Scale::uniform(size)).ascent;
+    let text = TextLayout::new(size).layout(text, font, Vec2::new(pos.x, pos.y - ascent));
+    build_laid_out_text(&text, tolerance)
+}
+
+/// Builds the outlines of text positioned by a `TextLayout` (such as rich text) into contours,
+/// with curves flattened to within `tolerance` pixels.
+pub fn build_laid_out_text(text: &LaidOutText, tolerance: f32) -> Vec<Contour> {
+    let mut builder = TextBuilder::new().with_tolerance(tolerance);
+    for glyph in &text.glyphs {
+        // the outline is built relative to the glyph's pixel bounds
+        let Some(bb) = glyph.pixel_bounding_box() else {
+            continue;
+        };
+        builder.glyph_pos = Vec2::new(bb.min.x as f32, bb.min.y as f32);
         glyph.build_outline(&mut builder);
+        builder.finish_glyph();
     }
-    builder.polys.pop();
-    for poly in &mut builder.polys {
-        remove_dup_points(&mut poly.points);
+    builder.finish()
+}
+
+/// A closed loop of points from the outline of a glyph.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Contour {
+    /// The points, where `(0.0, 0.0)` is the top-left corner of the top-left pixel.
+    /// The last point connects back to the first.
+    pub points: Vec<Vec2<f32>>,
+    /// If this contour cuts a hole out of the glyph (like the inside of an 'o'), rather than being an outer shell.
+    pub is_hole: bool,
+}
+impl Contour {
+    /// Twice the area enclosed by the points, positive if they go clockwise (on screen, where y points down).
+    pub fn signed_area(&self) -> f32 {
+        let len = self.points.len();
+        (0..len)
+            .map(|i| {
+                let (a, b) = (self.points[i], self.points[(i + 1) % len]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    /// Rounds the points to the nearest pixel corners.
+    pub fn to_poly(&self) -> Poly {
+        let mut points: Vec<_> = self
+            .points
+            .iter()
+            .map(|p| p.map(|e| e.round() as i32))
+            .collect();
+        points.dedup();
+        Poly { points }
+    }
+}
+impl AsRef<[Vec2<f32>]> for Contour {
+    fn as_ref(&self) -> &[Vec2<f32>] {
+        &self.points
     }
-    builder.polys
 }
 
 pub struct TextBuilder {
-    // on init, should have 1 blank poly pushed
-    pub polys: Vec<Poly>,
-    pub pos: Vec2<i32>,
-    pub glyph_pos: Vec2<i32>,
+    // the last contour is the one being built, and may be empty
+    pub contours: Vec<Contour>,
+    pub pos: Vec2<f32>,
+    pub glyph_pos: Vec2<f32>,
+    /// The largest distance, in pixels, that a flattened curve may be from the true curve.
+    pub tolerance: f32,
+    /// the index of the first contour of the current glyph
+    glyph_start: usize,
+}
+impl Default for TextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl TextBuilder {
     pub fn new() -> Self {
-        let mut polys = Vec::new();
-        polys.push(Poly::empty());
         Self {
-            polys,
-            pos: Vec2::new(0, 0),
-            glyph_pos: Vec2::new(0, 0),
+            contours: vec![Contour::default()],
+            pos: Vec2::new(0.0, 0.0),
+            glyph_pos: Vec2::new(0.0, 0.0),
+            tolerance: path::DEFAULT_TOLERANCE,
+            glyph_start: 0,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Starts a new contour, if the last one has any points.
+    pub fn close_open_contour(&mut self) {
+        let last = self.contours.last_mut().unwrap();
+        // the closing point is implied
+        if last.points.len() > 1 && last.points.first() == last.points.last() {
+            last.points.pop();
+        }
+        if !last.points.is_empty() {
+            self.contours.push(Contour::default());
         }
     }
 
-    pub fn vertex(&mut self, pos: Vec2<i32>) {
-        let x = pos.x + self.glyph_pos.x;
-        let y = pos.y + self.glyph_pos.y;
+    /// Closes the current glyph, and marks which of its contours are holes.
+    ///
+    /// Fonts wind all the outer shells of a glyph one way, and all the holes the other way,
+    /// so any contour wound the opposite way to the glyph's largest contour is a hole.
+    pub fn finish_glyph(&mut self) {
+        // CFF2 outlines don't close their last contour
+        self.close_open_contour();
+        let end = self.contours.len() - 1;
+        let glyph = &mut self.contours[self.glyph_start..end];
 
-        self.polys.last_mut().unwrap().points.push(Vec2::new(x, y));
+        let areas: Vec<f32> = glyph.iter().map(Contour::signed_area).collect();
+        let outer = areas
+            .iter()
+            .copied()
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0);
+        for (contour, area) in glyph.iter_mut().zip(areas) {
+            contour.is_hole = area * outer < 0.0;
+        }
+        self.glyph_start = end;
+    }
+
+    /// Finishes the last glyph, returning every contour built.
+    pub fn finish(mut self) -> Vec<Contour> {
+        self.finish_glyph();
+        self.contours.pop();
+        // contours too small to enclose anything
+        self.contours.retain(|c| c.points.len() >= 3);
+        self.contours
+    }
+
+    pub fn vertex(&mut self, pos: Vec2<f32>) {
+        let pos = pos + self.glyph_pos;
+        let points = &mut self.contours.last_mut().unwrap().points;
+        if points.last() != Some(&pos) {
+            points.push(pos);
+        }
     }
 }
 impl OutlineBuilder for TextBuilder {
     fn move_to(&mut self, x: f32, y: f32) {
-        self.pos = Vec2::new(x as i32, y as i32);
+        // a move without a close (which CFF fonts can do) still starts a new loop
+        self.close_open_contour();
+        self.pos = Vec2::new(x, y);
+        self.vertex(self.pos);
     }
 
     fn line_to(&mut self, x: f32, y: f32) {
-        self.vertex(Vec2::new(x as i32, y as i32));
-        self.pos = Vec2::new(x as i32, y as i32);
+        self.pos = Vec2::new(x, y);
+        self.vertex(self.pos);
     }
 
     // (x1,y1) is the control
-    fn quad_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
-        let a = self.pos;
-        let c = Vec2::new(x1 as i32, y1 as i32);
-        let b = Vec2::new(x2 as i32, y2 as i32);
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let mut points = Vec::new();
+        let to = Vec2::new(x, y);
+        path::flatten_quad(self.pos, Vec2::new(x1, y1), to, self.tolerance, &mut points);
+        for point in points {
+            self.vertex(point);
+        }
+        self.pos = to;
+    }
 
-        fn lerp(a: Vec2<i32>, b: Vec2<i32>, t: f32) -> Vec2<i32> {
-            // `t` should be normalized
-            let dist_x = ((b.x as f32 - a.x as f32) * t) as i32;
-            let dist_y = ((b.y as f32 - a.y as f32) * t) as i32;
-            Vec2::new(a.x + dist_x, a.y + dist_y)
+    // (x1,y1) and (x2,y2) are the controls, used by CFF (.otf) fonts
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let mut points = Vec::new();
+        let to = Vec2::new(x, y);
+        path::flatten_cubic(
+            self.pos,
+            Vec2::new(x1, y1),
+            Vec2::new(x2, y2),
+            to,
+            self.tolerance,
+            &mut points,
+        );
+        for point in points {
+            self.vertex(point);
         }
+        self.pos = to;
+    }
+
+    // when a vertex loop in the font has closed
+    fn close(&mut self) {
+        self.close_open_contour();
+    }
+}
+
+/// How the lines of a `TextLayout` are lined up with each other.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// The spaces between words are stretched so that each wrapped line fills the max width.
+    /// The last line of each paragraph is left aligned.
+    Justify,
+}
+
+/// A list of fonts, where each char is drawn with the first font that has a glyph for it,
+/// so text in several scripts can be drawn together.
+#[derive(Clone, Debug)]
+pub struct FontChain<'f> {
+    pub fonts: Vec<Font<'f>>,
+}
+impl<'f> FontChain<'f> {
+    pub fn new(font: Font<'f>) -> Self {
+        Self { fonts: vec![font] }
+    }
+
+    /// Adds a font to fall back on, for chars that none of the fonts before it have.
+    pub fn with_fallback(mut self, font: Font<'f>) -> Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// The glyph for `c` from the first font that has one,
+    /// or the first font's "missing glyph" if none of them do.
+    pub fn glyph(&self, c: char) -> Glyph<'f> {
+        self.fonts
+            .iter()
+            .map(|font| font.glyph(c))
+            .find(|glyph| glyph.id() != GlyphId(0))
+            .unwrap_or_else(|| self.fonts[0].glyph(c))
+    }
+}
+impl<'f> From<Font<'f>> for FontChain<'f> {
+    fn from(font: Font<'f>) -> Self {
+        Self::new(font)
+    }
+}
 
-        let mut t = 0.0;
-        while t <= 1.0 {
-            let lerp_ac = lerp(a, c, t);
-            let lerp_bc = lerp(c, b, t);
+/// A piece of rich text, with its own fonts, size, and color.
+#[derive(Clone, Debug)]
+pub struct TextSpan<'s, 'f> {
+    pub text: &'s str,
+    pub fonts: FontChain<'f>,
+    /// The height of the text in pixels.
+    pub size: f32,
+    pub color: Color,
+}
+impl<'s, 'f> TextSpan<'s, 'f> {
+    pub fn new(text: &'s str, fonts: impl Into<FontChain<'f>>, size: f32) -> Self {
+        Self {
+            text,
+            fonts: fonts.into(),
+            size,
+            color: Color::WHITE,
+        }
+    }
 
-            self.vertex(lerp(lerp_ac, lerp_bc, t));
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
 
-            t += 0.5;
+/// Lays out (possibly multi-line) text: each `\n` starts a new line,
+/// and lines longer than the max width are wrapped at word boundaries.
+///
+/// Text can be a single string in one font, or a list of `TextSpan`s that flow together,
+/// where each line is as tall as the largest text in it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TextLayout {
+    /// The height of text laid out with `layout`, in pixels (each `TextSpan` has its own size).
+    pub size: f32,
+    /// The width that lines are wrapped to, if any.
+    /// A single word wider than this is left on a line of its own, overflowing it.
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+    /// A multiple of the font's line height, used as the distance between baselines.
+    pub line_spacing: f32,
+    /// If the space between pairs of glyphs is adjusted by the font's kerning table.
+    pub kerning: bool,
+}
+impl TextLayout {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            max_width: None,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+            kerning: true,
         }
-        self.pos = b;
     }
 
-    fn curve_to(&mut self, _x1: f32, _y1: f32, _x2: f32, _y2: f32, _x: f32, _y: f32) {
-        // TODO (cubic bezier curves)
-        unimplemented!("Currently only supports TrueType fonts")
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+    pub fn with_kerning(mut self, kerning: bool) -> Self {
+        self.kerning = kerning;
+        self
     }
 
-    // when a vertex loop in the font has closed
-    fn close(&mut self) {
-        // push blank poly into vec
-        self.polys.push(Poly::empty());
+    /// The width and height of `text` once laid out.
+    pub fn measure(&self, text: &str, font: &Font) -> Vec2<f32> {
+        self.layout(text, font, Vec2::new(0.0, 0.0)).size
+    }
+
+    /// Positions every glyph of `text`, with `pos` being the top-left of the first line.
+    pub fn layout<'f>(&self, text: &str, font: &Font<'f>, pos: Vec2<f32>) -> LaidOutText<'f> {
+        self.layout_spans(&[TextSpan::new(text, font.clone(), self.size)], pos)
+    }
+
+    /// Positions every glyph of the spans, one after the other, with `pos` being the top-left of the first line.
+    pub fn layout_spans<'f>(&self, spans: &[TextSpan<'_, 'f>], pos: Vec2<f32>) -> LaidOutText<'f> {
+        let chars: Vec<(char, usize)> = spans
+            .iter()
+            .enumerate()
+            .flat_map(|(i, span)| span.text.chars().map(move |c| (c, i)))
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut rest = &chars[..];
+        // the span that the current paragraph starts in
+        let mut span = 0;
+        loop {
+            let newline = rest.iter().position(|&(c, _)| c == '\n');
+            let paragraph = &rest[..newline.unwrap_or(rest.len())];
+            let paragraph = match paragraph {
+                [before @ .., ('\r', _)] => before,
+                _ => paragraph,
+            };
+            if let Some(&(_, first)) = paragraph.first() {
+                span = first;
+            }
+            self.wrap_paragraph(paragraph, spans, span, &mut lines);
+
+            let Some(newline) = newline else {
+                break;
+            };
+            span = rest[newline].1;
+            rest = &rest[newline + 1..];
+        }
+
+        // lines are aligned within the max width, or else within the widest line
+        let widest = lines.iter().map(|l| l.width).fold(0.0, f32::max);
+        let block_width = self.max_width.unwrap_or(widest);
+
+        let mut out = LaidOutText {
+            glyphs: Vec::new(),
+            colors: Vec::new(),
+            lines: Vec::with_capacity(lines.len()),
+            size: Vec2::new(widest, 0.0),
+        };
+        let mut top = pos.y;
+        for (i, line) in lines.iter().enumerate() {
+            let metrics = line.metrics.unwrap_or(VMetrics {
+                ascent: 0.0,
+                descent: 0.0,
+                line_gap: 0.0,
+            });
+            let baseline = top + metrics.ascent;
+
+            let gaps = line.words.len().saturating_sub(1);
+            let (x, extra_gap, width) = match self.align {
+                TextAlign::Left => (0.0, 0.0, line.width),
+                TextAlign::Center => ((block_width - line.width) * 0.5, 0.0, line.width),
+                TextAlign::Right => (block_width - line.width, 0.0, line.width),
+                TextAlign::Justify if line.wrapped && gaps > 0 && line.width < block_width => {
+                    (0.0, (block_width - line.width) / gaps as f32, block_width)
+                }
+                TextAlign::Justify => (0.0, 0.0, line.width),
+            };
+
+            let start = out.glyphs.len();
+            for (i, word) in line.words.iter().enumerate() {
+                let word_x = pos.x + x + word.x + extra_gap * i as f32;
+                for (glyph, glyph_x, span) in &word.glyphs {
+                    let point = Point {
+                        x: word_x + glyph_x,
+                        y: baseline,
+                    };
+                    out.glyphs.push(glyph.clone().positioned(point));
+                    out.colors.push(spans[*span].color);
+                }
+            }
+            out.lines.push(TextLine {
+                glyphs: start..out.glyphs.len(),
+                x: pos.x + x,
+                baseline,
+                width,
+            });
+
+            if i + 1 == lines.len() {
+                out.size.y = baseline - metrics.descent - pos.y;
+            } else {
+                top += (metrics.ascent - metrics.descent + metrics.line_gap) * self.line_spacing;
+            }
+        }
+        out
+    }
+
+    /// Breaks one paragraph (with no `\n`) into lines, adding them to `lines`.
+    fn wrap_paragraph<'f>(
+        &self,
+        paragraph: &[(char, usize)],
+        spans: &[TextSpan<'_, 'f>],
+        span: usize,
+        lines: &mut Vec<WrappedLine<'f>>,
+    ) {
+        // a line with no text is as tall as the span it's in
+        let empty_line = || WrappedLine {
+            metrics: spans.get(span).map(span_metrics),
+            ..WrappedLine::default()
+        };
+        let mut line = empty_line();
+        // the width of the whitespace since the last word
+        let mut gap = 0.0;
+        let mut last_glyph = None;
+
+        for (is_space, piece) in split_words(paragraph) {
+            if is_space {
+                gap += self.layout_word(piece, spans, &mut last_glyph).width;
+                continue;
+            }
+            let mut word_last_glyph = last_glyph.clone();
+            let word = self.layout_word(piece, spans, &mut word_last_glyph);
+
+            // whitespace at the start of a paragraph indents its first word (an empty line's width is 0),
+            // but whitespace at a soft wrap is dropped below
+            let x = line.width + gap;
+            let fits = match self.max_width {
+                Some(max) => x + word.width <= max,
+                None => true,
+            };
+            if !line.words.is_empty() && !fits {
+                line.wrapped = true;
+                lines.push(std::mem::replace(&mut line, empty_line()));
+                // kerning doesn't carry over to a new line
+                word_last_glyph = None;
+                let word = self.layout_word(piece, spans, &mut word_last_glyph);
+                line.width = word.width;
+                line.add_word(word);
+            } else {
+                line.width = x + word.width;
+                line.add_word(PlacedWord { x, ..word });
+            }
+            last_glyph = word_last_glyph;
+            gap = 0.0;
+        }
+        lines.push(line);
+    }
+
+    fn layout_word<'f>(
+        &self,
+        chars: &[(char, usize)],
+        spans: &[TextSpan<'_, 'f>],
+        last_glyph: &mut Option<ScaledGlyph<'f>>,
+    ) -> PlacedWord<'f> {
+        let mut glyphs = Vec::with_capacity(chars.len());
+        let mut metrics = None;
+        let mut x = 0.0;
+        for &(c, span_index) in chars {
+            let span = &spans[span_index];
+            let scale = Scale::uniform(span.size);
+            let glyph = span.fonts.glyph(c).scaled(scale);
+            if let Some(last) = last_glyph.as_ref().filter(|_| self.kerning) {
+                // only glyphs of the same font and size have kerning between them
+                if last.scale() == scale && FontId::of(last.font()) == FontId::of(glyph.font()) {
+                    x += glyph.font().pair_kerning(scale, last.id(), glyph.id());
+                }
+            }
+            metrics = Some(merge_metrics(metrics, glyph.font().v_metrics(scale)));
+            let advance = glyph.h_metrics().advance_width;
+            glyphs.push((glyph.clone(), x, span_index));
+            x += advance;
+            *last_glyph = Some(glyph);
+        }
+        PlacedWord {
+            x: 0.0,
+            width: x,
+            glyphs,
+            metrics,
+        }
+    }
+}
+
+/// Text that has been positioned by a `TextLayout`, ready to be drawn with `Graphics::draw_text`.
+#[derive(Clone, Debug)]
+pub struct LaidOutText<'f> {
+    pub glyphs: Vec<PositionedGlyph<'f>>,
+    /// The color of each glyph, from the span it's in.
+    pub colors: Vec<Color>,
+    pub lines: Vec<TextLine>,
+    /// The width of the widest line, and the height from the ascent of the first line to the descent of the last.
+    pub size: Vec2<f32>,
+}
+impl<'f> LaidOutText<'f> {
+    /// The smallest rect of pixels that the glyphs actually cover, if any are visible.
+    pub fn pixel_bounds(&self) -> Option<Rect> {
+        let bb = self
+            .glyphs
+            .iter()
+            .filter_map(PositionedGlyph::pixel_bounding_box)
+            .reduce(|a, b| rusttype::Rect {
+                min: Point {
+                    x: a.min.x.min(b.min.x),
+                    y: a.min.y.min(b.min.y),
+                },
+                max: Point {
+                    x: a.max.x.max(b.max.x),
+                    y: a.max.y.max(b.max.y),
+                },
+            })?;
+        Some(Rect::new(bb.min.x, bb.min.y, bb.width(), bb.height()))
+    }
+}
+
+/// One line of `LaidOutText`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextLine {
+    /// The range of `LaidOutText::glyphs` in this line.
+    pub glyphs: Range<usize>,
+    /// Where the line starts, after alignment.
+    pub x: f32,
+    pub baseline: f32,
+    pub width: f32,
+}
+
+struct WrappedLine<'f> {
+    words: Vec<PlacedWord<'f>>,
+    width: f32,
+    /// if the line was broken because it was too wide, rather than at the end of a paragraph
+    wrapped: bool,
+    /// the metrics of the tallest text in the line
+    metrics: Option<VMetrics>,
+}
+impl<'f> WrappedLine<'f> {
+    fn add_word(&mut self, word: PlacedWord<'f>) {
+        if let Some(metrics) = word.metrics {
+            // the metrics of an empty line are replaced by its text
+            let current = if self.words.is_empty() {
+                None
+            } else {
+                self.metrics
+            };
+            self.metrics = Some(merge_metrics(current, metrics));
+        }
+        self.words.push(word);
+    }
+}
+impl Default for WrappedLine<'_> {
+    fn default() -> Self {
+        Self {
+            words: Vec::new(),
+            width: 0.0,
+            wrapped: false,
+            metrics: None,
+        }
+    }
+}
+
+struct PlacedWord<'f> {
+    /// the x of the word, relative to the start of its line
+    x: f32,
+    width: f32,
+    /// each glyph, its x relative to the word, and the index of its span
+    glyphs: Vec<(ScaledGlyph<'f>, f32, usize)>,
+    metrics: Option<VMetrics>,
+}
+
+/// Combines the metrics of text on the same line, to fit the tallest of it.
+fn merge_metrics(a: Option<VMetrics>, b: VMetrics) -> VMetrics {
+    let Some(a) = a else {
+        return b;
+    };
+    VMetrics {
+        ascent: a.ascent.max(b.ascent),
+        descent: a.descent.min(b.descent),
+        line_gap: a.line_gap.max(b.line_gap),
+    }
+}
+
+/// The metrics of the first font of a span.
+fn span_metrics(span: &TextSpan) -> VMetrics {
+    span.fonts.fonts[0].v_metrics(Scale::uniform(span.size))
+}
+
+/// Splits `chars` into runs of whitespace and non-whitespace, flagging the whitespace.
+fn split_words<T>(chars: &[(char, T)]) -> impl Iterator<Item = (bool, &[(char, T)])> {
+    let mut rest = chars;
+    std::iter::from_fn(move || {
+        let is_space = rest.first()?.0.is_whitespace();
+        let end = rest
+            .iter()
+            .position(|(c, _)| c.is_whitespace() != is_space)
+            .unwrap_or(rest.len());
+        let (piece, tail) = rest.split_at(end);
+        rest = tail;
+        Some((is_space, piece))
+    })
+}
+
+/// A font that can be drawn by `Graphics::draw_str`, either vector or bitmap.
+#[derive(Clone, Debug)]
+pub enum TextFont<'a> {
+    Vector(Font<'a>),
+    Bitmap(Arc<BitmapFont>),
+}
+impl<'a> From<Font<'a>> for TextFont<'a> {
+    fn from(font: Font<'a>) -> Self {
+        Self::Vector(font)
+    }
+}
+impl From<BitmapFont> for TextFont<'_> {
+    fn from(font: BitmapFont) -> Self {
+        Self::Bitmap(Arc::new(font))
+    }
+}
+impl From<Arc<BitmapFont>> for TextFont<'_> {
+    fn from(font: Arc<BitmapFont>) -> Self {
+        Self::Bitmap(font)
+    }
+}
+
+/// A single glyph of a `BitmapFont`, where each pixel is either on or off.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BitmapGlyph {
+    pub size: Vec2<u32>,
+    /// Where the top-left pixel of the glyph is, relative to the pen position on the baseline.
+    pub offset: Vec2<i32>,
+    /// How far the pen moves right after this glyph.
+    pub advance: i32,
+    /// The pixels, row by row.
+    pub pixels: Vec<bool>,
+}
+impl BitmapGlyph {
+    #[inline(always)]
+    pub fn pixel(&self, pos: Vec2<u32>) -> bool {
+        self.pixels[(pos.y * self.size.x + pos.x) as usize]
+    }
+}
+
+/// A fixed-size font made of pixels, for crisp pixel-art text.
+///
+/// Can be loaded from BDF or PSF (1 or 2) files, or from a grid of glyphs in an `Image`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct BitmapFont {
+    pub glyphs: Vec<BitmapGlyph>,
+    /// The index in `glyphs` of each char.
+    pub chars: HashMap<char, usize>,
+    /// The glyph drawn for chars that the font doesn't have.
+    pub default_glyph: Option<usize>,
+    /// The distance from the top of a line to the baseline.
+    pub ascent: i32,
+    /// The distance between the baselines of each line.
+    pub line_height: i32,
+}
+impl BitmapFont {
+    /// The glyph for `c`, or the default glyph if the font doesn't have one.
+    pub fn glyph(&self, c: char) -> Option<&BitmapGlyph> {
+        self.chars
+            .get(&c)
+            .copied()
+            .or(self.default_glyph)
+            .map(|index| &self.glyphs[index])
+    }
+
+    /// The width and height of `text` when drawn at a scale of 1, where each `\n` starts a new line.
+    pub fn measure(&self, text: &str) -> Vec2<i32> {
+        let lines = text.split('\n');
+        let width = lines
+            .clone()
+            .map(|line| {
+                line.chars()
+                    .filter_map(|c| self.glyph(c))
+                    .map(|g| g.advance)
+                    .sum::<i32>()
+            })
+            .max()
+            .unwrap_or(0);
+        Vec2::new(width, self.line_height * lines.count() as i32)
+    }
+
+    /// Loads a font from the text of a BDF (Glyph Bitmap Distribution Format) file.
+    pub fn from_bdf(src: &str) -> Result<Self, String> {
+        let mut font = Self::default();
+        let mut default_char = None;
+        // (ascent, descent) from FONTBOUNDINGBOX, and from the FONT_ASCENT and FONT_DESCENT properties
+        let mut bounds = (0, 0);
+        let (mut ascent, mut descent) = (None, None);
+
+        let mut lines = src
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        while let Some((line_num, line)) = lines.next() {
+            let err = |msg: &str| format!("line {line_num}: {msg}");
+            let mut words = line.split_whitespace();
+            let nums = |words: std::str::SplitWhitespace| -> Result<Vec<i32>, String> {
+                words
+                    .map(|w| w.parse().map_err(|_| err(&format!("invalid number `{w}`"))))
+                    .collect()
+            };
+            match words.next() {
+                Some("FONTBOUNDINGBOX") => match nums(words)?[..] {
+                    [_, h, _, y_off] => bounds = (h + y_off, -y_off),
+                    _ => return Err(err("FONTBOUNDINGBOX needs 4 numbers")),
+                },
+                Some("FONT_ASCENT") => ascent = nums(words)?.first().copied(),
+                Some("FONT_DESCENT") => descent = nums(words)?.first().copied(),
+                Some("DEFAULT_CHAR") => default_char = nums(words)?.first().copied(),
+                Some("STARTCHAR") => {
+                    let (encoding, glyph) = parse_bdf_char(&mut lines)?;
+                    if let Some(c) = encoding.and_then(char::from_u32) {
+                        font.chars.insert(c, font.glyphs.len());
+                    }
+                    if default_char.is_some() && encoding.map(|e| e as i32) == default_char {
+                        font.default_glyph = Some(font.glyphs.len());
+                    }
+                    font.glyphs.push(glyph);
+                }
+                _ => {}
+            }
+        }
+        if font.glyphs.is_empty() {
+            return Err(String::from("no glyphs in BDF font"));
+        }
+        if font.default_glyph.is_none() {
+            font.default_glyph = font.chars.get(&'?').copied();
+        }
+        font.ascent = ascent.unwrap_or(bounds.0);
+        font.line_height = font.ascent + descent.unwrap_or(bounds.1);
+        Ok(font)
+    }
+
+    /// Loads a font from a PSF (PC Screen Font) file, version 1 or 2,
+    /// using its unicode table if it has one, or else mapping each glyph to the char of its index.
+    pub fn from_psf(bytes: &[u8]) -> Result<Self, String> {
+        let u32_at = |i: usize| -> Result<u32, String> {
+            let b = bytes
+                .get(i..i + 4)
+                .ok_or_else(|| String::from("PSF header is cut short"))?;
+            Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        // the header size, glyph count, glyph size, and whether there is a unicode table
+        let (header_size, count, size, has_table, psf2) = if bytes.starts_with(&[0x36, 0x04]) {
+            let mode = *bytes.get(2).ok_or("PSF header is cut short")?;
+            let height = *bytes.get(3).ok_or("PSF header is cut short")?;
+            let count = if mode & 0x01 != 0 { 512 } else { 256 };
+            (
+                4,
+                count,
+                Vec2::new(8, height as u32),
+                mode & 0x06 != 0,
+                false,
+            )
+        } else if bytes.starts_with(&[0x72, 0xb5, 0x4a, 0x86]) {
+            let size = Vec2::new(u32_at(28)?, u32_at(24)?);
+            if u32_at(20)? as u64 != size.x.div_ceil(8) as u64 * size.y as u64 {
+                return Err(String::from(
+                    "PSF glyph size doesn't match its width and height",
+                ));
+            }
+            (
+                u32_at(8)? as usize,
+                u32_at(16)? as usize,
+                size,
+                u32_at(12)? & 1 != 0,
+                true,
+            )
+        } else {
+            return Err(String::from("not a PSF font"));
+        };
+
+        if size.x == 0 || size.y == 0 {
+            return Err(String::from("PSF glyphs have no size"));
+        }
+        let row_bytes = size.x.div_ceil(8) as usize;
+        let glyph_bytes = row_bytes * size.y as usize;
+        // the sizes in the header could be anything, so they're checked rather than allowed to overflow
+        let glyphs_end = count
+            .checked_mul(glyph_bytes)
+            .and_then(|len| len.checked_add(header_size))
+            .ok_or("PSF glyphs are cut short")?;
+        let data = bytes
+            .get(header_size..glyphs_end)
+            .ok_or("PSF glyphs are cut short")?;
+
+        let mut font = Self {
+            ascent: size.y as i32,
+            line_height: size.y as i32,
+            ..Self::default()
+        };
+        for glyph in data.chunks_exact(glyph_bytes) {
+            font.glyphs.push(BitmapGlyph {
+                size,
+                offset: Vec2::new(0, -(size.y as i32)),
+                advance: size.x as i32,
+                pixels: unpack_bits(glyph, size, row_bytes),
+            });
+        }
+
+        if !has_table {
+            // glyphs at the indices of surrogates have no char to be drawn with
+            for i in 0..count {
+                if let Some(c) = char::from_u32(i as u32) {
+                    font.chars.insert(c, i);
+                }
+            }
+        } else if psf2 {
+            // each glyph has a list of UTF-8 chars, then sequences (starting with 0xFE), ending with 0xFF
+            let mut table = &bytes[glyphs_end..];
+            for i in 0..count {
+                let end = table.iter().position(|&b| b == 0xFF).unwrap_or(table.len());
+                let entry = &table[..end];
+                let singles =
+                    &entry[..entry.iter().position(|&b| b == 0xFE).unwrap_or(entry.len())];
+                let singles = std::str::from_utf8(singles)
+                    .map_err(|_| format!("invalid UTF-8 in PSF unicode table for glyph {i}"))?;
+                for c in singles.chars() {
+                    font.chars.entry(c).or_insert(i);
+                }
+                table = table.get(end + 1..).unwrap_or(&[]);
+            }
+        } else {
+            // each glyph has a list of UCS-2 chars, then sequences (starting with 0xFFFE), ending with 0xFFFF
+            let mut table = bytes[glyphs_end..]
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]));
+            for i in 0..count {
+                let mut in_sequence = false;
+                for unit in table.by_ref() {
+                    match unit {
+                        0xFFFF => break,
+                        0xFFFE => in_sequence = true,
+                        _ if in_sequence => {}
+                        _ => {
+                            if let Some(c) = char::from_u32(unit as u32) {
+                                font.chars.entry(c).or_insert(i);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        font.default_glyph = font.chars.get(&'?').copied();
+        Ok(font)
+    }
+
+    /// Loads a monospaced font from a grid of glyphs in an image, each `cell_size` pixels,
+    /// going left to right then top to bottom, for each char of `chars` in order.
+    ///
+    /// A pixel is on if it's mostly opaque and light, so glyphs should be light on a dark or transparent background.
+    pub fn from_image_grid(image: &Image, cell_size: Vec2<u32>, chars: &str) -> Self {
+        let mut font = Self {
+            ascent: cell_size.y as i32,
+            line_height: cell_size.y as i32,
+            ..Self::default()
+        };
+        if cell_size.x == 0 || cell_size.y == 0 {
+            return font;
+        }
+        let columns = image.size.x / cell_size.x;
+        let rows = image.size.y / cell_size.y;
+
+        for (i, c) in chars.chars().enumerate().take((columns * rows) as usize) {
+            let cell = Vec2::new(i as u32 % columns, i as u32 / columns) * cell_size;
+            let mut pixels = Vec::with_capacity((cell_size.x * cell_size.y) as usize);
+            for y in 0..cell_size.y {
+                for x in 0..cell_size.x {
+                    let index = ((cell.y + y) * image.size.x + cell.x + x) as usize * 4;
+                    let [r, g, b, a] = [0, 1, 2, 3].map(|c| image.bytes[index + c] as u32);
+                    pixels.push(a >= 128 && (r * 3 + g * 6 + b) >= 128 * 10);
+                }
+            }
+            font.chars.insert(c, font.glyphs.len());
+            font.glyphs.push(BitmapGlyph {
+                size: cell_size,
+                offset: Vec2::new(0, -(cell_size.y as i32)),
+                advance: cell_size.x as i32,
+                pixels,
+            });
+        }
+        font.default_glyph = font.chars.get(&'?').copied();
+        font
+    }
+}
+
+/// Parses a glyph of a BDF font, from after its STARTCHAR line to its ENDCHAR line.
+fn parse_bdf_char<'s>(
+    lines: &mut impl Iterator<Item = (usize, &'s str)>,
+) -> Result<(Option<u32>, BitmapGlyph), String> {
+    let mut encoding = None;
+    let mut glyph = BitmapGlyph {
+        size: Vec2::new(0, 0),
+        offset: Vec2::new(0, 0),
+        advance: 0,
+        pixels: Vec::new(),
+    };
+    let mut last_line = 0;
+    while let Some((line_num, line)) = lines.next() {
+        last_line = line_num;
+        let err = |msg: &str| format!("line {line_num}: {msg}");
+        let mut words = line.split_whitespace();
+        let keyword = words.next();
+        let nums: Vec<i32> = words.map(|w| w.parse().unwrap_or(i32::MIN)).collect();
+        if nums.contains(&i32::MIN) {
+            return Err(err("invalid number"));
+        }
+        match (keyword, &nums[..]) {
+            // -1 means the glyph isn't mapped to a char
+            (Some("ENCODING"), [e, ..]) => encoding = u32::try_from(*e).ok(),
+            (Some("DWIDTH"), [dx, ..]) => glyph.advance = *dx,
+            (Some("BBX"), [w, h, x_off, y_off]) => {
+                if *w < 0 || *h < 0 {
+                    return Err(err("BBX size can't be negative"));
+                }
+                glyph.size = Vec2::new(*w as u32, *h as u32);
+                // BDF measures y upwards from the baseline, to the bottom of the glyph
+                glyph.offset = Vec2::new(*x_off, -(y_off + h));
+            }
+            (Some("BBX"), _) => return Err(err("BBX needs 4 numbers")),
+            (Some("BITMAP"), _) => {
+                let row_bytes = glyph.size.x.div_ceil(8) as usize;
+                let mut bytes = Vec::with_capacity(row_bytes * glyph.size.y as usize);
+                for _ in 0..glyph.size.y {
+                    let (line_num, row) = lines.next().ok_or_else(|| err("BITMAP is cut short"))?;
+                    let row = row.get(..row_bytes * 2).unwrap_or(row);
+                    for i in 0..row_bytes {
+                        let hex = row.get(i * 2..i * 2 + 2).unwrap_or("00");
+                        let byte = u8::from_str_radix(hex, 16).map_err(|_| {
+                            format!("line {line_num}: invalid hex `{hex}` in BITMAP")
+                        })?;
+                        bytes.push(byte);
+                    }
+                }
+                glyph.pixels = unpack_bits(&bytes, glyph.size, row_bytes);
+            }
+            (Some("ENDCHAR"), _) => {
+                if glyph.pixels.len() != (glyph.size.x * glyph.size.y) as usize {
+                    return Err(err("glyph has no BITMAP"));
+                }
+                return Ok((encoding, glyph));
+            }
+            _ => {}
+        }
+    }
+    Err(format!("line {last_line}: glyph has no ENDCHAR"))
+}
+
+/// Unpacks rows of bits (most significant bit first, each row padded to whole bytes) into pixels.
+fn unpack_bits(bytes: &[u8], size: Vec2<u32>, row_bytes: usize) -> Vec<bool> {
+    let mut pixels = Vec::with_capacity((size.x * size.y) as usize);
+    for row in bytes.chunks_exact(row_bytes).take(size.y as usize) {
+        for x in 0..size.x as usize {
+            pixels.push(row[x / 8] & (0x80 >> (x % 8)) != 0);
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cantarell (SIL Open Font License), which has CFF2 outlines
+    const CFF_FONT: &[u8] = include_bytes!("../tests/fonts/Cantarell-VF.otf");
+
+    #[test]
+    fn build_cff_text() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let contours = build_text("Bog", Vec2::new(10.0, 60.0), &font, 48.0);
+
+        // Cantarell draws 'B' as a single contour, and 'g' as two overlapping shells,
+        // so only the 'o' has a hole
+        assert_eq!(contours.len(), 5);
+        assert_eq!(contours.iter().filter(|c| c.is_hole).count(), 1);
+        for contour in &contours {
+            assert!(contour.points.len() >= 3);
+        }
+    }
+
+    #[test]
+    fn text_sits_on_baseline() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let contours = build_text("o", Vec2::new(10.5, 60.25), &font, 48.0);
+        assert_eq!(contours.len(), 2);
+        assert!(!contours[0].is_hole);
+        assert!(contours[1].is_hole);
+
+        let points = || contours.iter().flat_map(|c| &c.points);
+        let bottom = points().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let left = points().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        // an 'o' overshoots the baseline slightly, and starts just after the pen position
+        assert!((60.0..62.0).contains(&bottom), "{bottom}");
+        assert!((10.5..15.0).contains(&left), "{left}");
+        // coordinates aren't rounded to whole pixels
+        assert!(points().any(|p| p.x.fract() != 0.0 && p.y.fract() != 0.0));
+    }
+
+    #[test]
+    fn quad_is_flattened_within_tolerance() {
+        let mut builder = TextBuilder::new();
+        builder.move_to(0.0, 0.0);
+        builder.quad_to(50.0, 100.0, 100.0, 0.0);
+        let points = &builder.contours[0].points;
+        assert_eq!(*points.last().unwrap(), Vec2::new(100.0, 0.0));
+
+        // the curve is furthest from its base at t = 0.5, 50 pixels away
+        let max_y = points.iter().map(|p| p.y).fold(0.0, f32::max);
+        assert!((50.0 - max_y) <= path::DEFAULT_TOLERANCE);
+        // the tolerance is in pixels, so a bigger curve needs more points
+        let mut big = TextBuilder::new();
+        big.move_to(0.0, 0.0);
+        big.quad_to(500.0, 1000.0, 1000.0, 0.0);
+        assert!(big.contours[0].points.len() > points.len());
+    }
+
+    #[test]
+    fn cubic_is_flattened_within_tolerance() {
+        let mut builder = TextBuilder::new();
+        builder.move_to(0.0, 0.0);
+        builder.curve_to(0.0, 100.0, 100.0, 100.0, 100.0, 0.0);
+
+        let points = &builder.contours[0].points;
+        assert_eq!(*points.last().unwrap(), Vec2::new(100.0, 0.0));
+        assert!(points.len() > 10);
+
+        // the furthest point of this curve from its base is at t = 0.5, 75 pixels away
+        let max_y = points.iter().map(|p| p.y).fold(0.0, f32::max);
+        assert!((75.0 - max_y) <= path::DEFAULT_TOLERANCE);
+    }
+
+    fn line_text(text: &LaidOutText, line: usize, font: &Font) -> String {
+        text.glyphs[text.lines[line].glyphs.clone()]
+            .iter()
+            .map(|g| {
+                let id = g.id();
+                (' '..='~').find(|&c| font.glyph(c).id() == id).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measure_single_line() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let layout = TextLayout::new(20.0);
+        let scale = Scale::uniform(20.0);
+
+        let size = layout.measure("Hi", &font);
+        let expected: f32 = ['H', 'i']
+            .iter()
+            .map(|&c| font.glyph(c).scaled(scale).h_metrics().advance_width)
+            .sum::<f32>()
+            + font.pair_kerning(scale, 'H', 'i');
+        assert!((size.x - expected).abs() < 0.001);
+
+        let v = font.v_metrics(scale);
+        assert_eq!(size.y, v.ascent - v.descent);
+        // the width grows with the text
+        assert!(layout.measure("Hi there", &font).x > size.x);
+    }
+
+    #[test]
+    fn newlines_and_line_spacing() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let layout = TextLayout::new(20.0).with_line_spacing(1.5);
+        let text = layout.layout("one\ntwo\n\nfour", &font, Vec2::new(0.0, 0.0));
+
+        assert_eq!(text.lines.len(), 4);
+        assert_eq!(line_text(&text, 1, &font), "two");
+        assert!(text.lines[2].glyphs.is_empty());
+
+        let v = font.v_metrics(Scale::uniform(20.0));
+        let line_height = (v.ascent - v.descent + v.line_gap) * 1.5;
+        assert_eq!(text.lines[0].baseline, v.ascent);
+        assert!(
+            (text.lines[3].baseline - text.lines[0].baseline - line_height * 3.0).abs() < 0.001
+        );
+    }
+
+    #[test]
+    fn wraps_at_words() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let max_width = TextLayout::new(20.0).measure("ab cd", &font).x + 1.0;
+        let layout = TextLayout::new(20.0).with_max_width(max_width);
+        let text = layout.layout("ab cd ab cd ab", &font, Vec2::new(0.0, 0.0));
+
+        let lines: Vec<String> = (0..text.lines.len())
+            .map(|i| line_text(&text, i, &font))
+            .collect();
+        assert_eq!(lines, ["abcd", "abcd", "ab"]);
+        for line in &text.lines {
+            assert!(line.width <= max_width);
+        }
+        assert!(text.size.x <= max_width);
+
+        // a word too wide for any line overflows on its own
+        let narrow = TextLayout::new(20.0).with_max_width(1.0);
+        assert_eq!(
+            narrow
+                .layout("a bb", &font, Vec2::new(0.0, 0.0))
+                .lines
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn alignment() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let layout = TextLayout::new(20.0).with_max_width(200.0);
+        let text = "the quick brown fox jumps over the lazy dog";
+        let pos = Vec2::new(10.0, 0.0);
+
+        let left = layout.layout(text, &font, pos);
+        let right = layout.with_align(TextAlign::Right).layout(text, &font, pos);
+        let center = layout
+            .with_align(TextAlign::Center)
+            .layout(text, &font, pos);
+        let justify = layout
+            .with_align(TextAlign::Justify)
+            .layout(text, &font, pos);
+        assert!(left.lines.len() > 1);
+
+        let last = left.lines.len() - 1;
+        for i in 0..left.lines.len() {
+            let width = left.lines[i].width;
+            assert_eq!(left.lines[i].x, 10.0);
+            assert!((right.lines[i].x + width - 210.0).abs() < 0.001);
+            assert!((center.lines[i].x - (10.0 + (200.0 - width) * 0.5)).abs() < 0.001);
+
+            // every line but the last is stretched to the max width
+            let end = justify.glyphs[justify.lines[i].glyphs.end - 1].clone();
+            let end_x = end.position().x + end.unpositioned().h_metrics().advance_width;
+            if i == last {
+                assert!(end_x < 209.0);
+            } else {
+                assert!((end_x - 210.0).abs() < 0.01, "{end_x}");
+            }
+        }
+    }
+
+    // a 4x6 font with two glyphs, 'A' and an unencoded box
+    const BDF: &str = "STARTFONT 2.1
+FONT -test-tiny
+SIZE 6 75 75
+FONTBOUNDINGBOX 4 6 0 -1
+STARTPROPERTIES 2
+FONT_ASCENT 5
+FONT_DESCENT 1
+ENDPROPERTIES
+CHARS 2
+STARTCHAR A
+ENCODING 65
+SWIDTH 500 0
+DWIDTH 5 0
+BBX 3 4 0 0
+BITMAP
+40
+A0
+E0
+A0
+ENDCHAR
+STARTCHAR box
+ENCODING -1
+DWIDTH 4 0
+BBX 2 2 1 -1
+BITMAP
+C0
+C0
+ENDCHAR
+ENDFONT
+";
+
+    #[test]
+    fn load_bdf() {
+        let font = BitmapFont::from_bdf(BDF).unwrap();
+        assert_eq!(font.glyphs.len(), 2);
+        assert_eq!(font.chars.len(), 1);
+        assert_eq!((font.ascent, font.line_height), (5, 6));
+
+        let a = font.glyph('A').unwrap();
+        assert_eq!(a.size, Vec2::new(3, 4));
+        assert_eq!(a.offset, Vec2::new(0, -4));
+        assert_eq!(a.advance, 5);
+        let rows: Vec<String> = a
+            .pixels
+            .chunks(3)
+            .map(|row| row.iter().map(|&p| if p { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(rows, [".#.", "#.#", "###", "#.#"]);
+
+        // the box sits one pixel below the baseline
+        assert_eq!(font.glyphs[1].offset, Vec2::new(1, -1));
+        assert_eq!(font.glyph('B'), None);
+        assert_eq!(font.measure("AA\nA"), Vec2::new(10, 12));
+    }
+
+    #[test]
+    fn bdf_errors() {
+        let bad_hex = BDF.replace("E0", "G0");
+        assert_eq!(
+            BitmapFont::from_bdf(&bad_hex).unwrap_err(),
+            "line 18: invalid hex `G0` in BITMAP"
+        );
+        let no_end = &BDF[..BDF.find("ENDCHAR").unwrap()];
+        assert!(BitmapFont::from_bdf(no_end)
+            .unwrap_err()
+            .contains("no ENDCHAR"));
+        assert_eq!(
+            BitmapFont::from_bdf("STARTFONT 2.1\n").unwrap_err(),
+            "no glyphs in BDF font"
+        );
+    }
+
+    // a PSF glyph 8x2, with the top row set
+    fn psf_glyph() -> [u8; 2] {
+        [0xFF, 0x00]
+    }
+
+    #[test]
+    fn load_psf1() {
+        let mut bytes = vec![0x36, 0x04, 0x02, 2];
+        for _ in 0..256 {
+            bytes.extend(psf_glyph());
+        }
+        // glyph 0 is 'x' and 'y', every other glyph has no chars
+        bytes.extend([b'x', 0, b'y', 0, 0xFF, 0xFF]);
+        for _ in 1..256 {
+            bytes.extend([0xFF, 0xFF]);
+        }
+        let font = BitmapFont::from_psf(&bytes).unwrap();
+        assert_eq!(font.glyphs.len(), 256);
+        assert_eq!(font.chars.len(), 2);
+        assert_eq!(font.chars[&'y'], 0);
+        let x = font.glyph('x').unwrap();
+        assert_eq!(x.size, Vec2::new(8, 2));
+        assert_eq!(x.pixels.iter().filter(|&&p| p).count(), 8);
+        assert!(x.pixel(Vec2::new(7, 0)) && !x.pixel(Vec2::new(0, 1)));
+    }
+
+    #[test]
+    fn load_psf2() {
+        let mut bytes = vec![0x72, 0xb5, 0x4a, 0x86];
+        // version, header size, flags (no unicode table), count, glyph size, height, width
+        for n in [0, 32, 0, 3, 4, 2, 10] {
+            bytes.extend(u32::to_le_bytes(n));
+        }
+        for _ in 0..3 {
+            bytes.extend([0xFF, 0xC0, 0x00, 0x00]);
+        }
+        let font = BitmapFont::from_psf(&bytes).unwrap();
+        assert_eq!(font.glyphs.len(), 3);
+        let glyph = font.glyph('\u{2}').unwrap();
+        assert_eq!(glyph.size, Vec2::new(10, 2));
+        assert_eq!(glyph.pixels.iter().filter(|&&p| p).count(), 10);
+
+        assert!(BitmapFont::from_psf(&bytes[..40]).is_err());
+        assert_eq!(BitmapFont::from_psf(b"nope").unwrap_err(), "not a PSF font");
+    }
+
+    fn psf2_header(header: [u32; 7]) -> Vec<u8> {
+        let mut bytes = vec![0x72, 0xb5, 0x4a, 0x86];
+        for n in header {
+            bytes.extend(u32::to_le_bytes(n));
+        }
+        bytes
+    }
+
+    #[test]
+    fn psf_bad_sizes() {
+        // glyphs with no width or height
+        let mut bytes = psf2_header([0, 32, 0, 3, 0, 0, 10]);
+        bytes.extend([0; 8]);
+        assert_eq!(
+            BitmapFont::from_psf(&bytes).unwrap_err(),
+            "PSF glyphs have no size"
+        );
+        let bytes = psf2_header([0, 32, 0, 3, 0, 2, 0]);
+        assert_eq!(
+            BitmapFont::from_psf(&bytes).unwrap_err(),
+            "PSF glyphs have no size"
+        );
+        assert_eq!(
+            BitmapFont::from_psf(&[0x36, 0x04, 0x00, 0]).unwrap_err(),
+            "PSF glyphs have no size"
+        );
+        // a glyph size that doesn't match the width and height
+        let bytes = psf2_header([0, 32, 0, 3, 0, 2, 10]);
+        assert_eq!(
+            BitmapFont::from_psf(&bytes).unwrap_err(),
+            "PSF glyph size doesn't match its width and height"
+        );
+
+        // sizes so big that the end of the glyphs could overflow
+        let huge = psf2_header([0, 32, 0, u32::MAX, u32::MAX, u32::MAX, 8]);
+        let header_past_end = psf2_header([0, u32::MAX, 0, u32::MAX, 4, 2, 10]);
+        for bytes in [huge, header_past_end] {
+            assert_eq!(
+                BitmapFont::from_psf(&bytes).unwrap_err(),
+                "PSF glyphs are cut short"
+            );
+        }
+    }
+
+    #[test]
+    fn psf_without_table_skips_surrogates() {
+        // enough glyphs that some are at the indices of surrogates (0xD800 to 0xDFFF)
+        let count = 0xE010;
+        let mut bytes = psf2_header([0, 32, 0, count, 1, 1, 1]);
+        bytes.extend(vec![0x80; count as usize]);
+        let font = BitmapFont::from_psf(&bytes).unwrap();
+        assert_eq!(font.glyphs.len(), count as usize);
+        assert_eq!(font.chars.len(), count as usize - 0x800);
+        assert_eq!(font.chars[&'\u{D7FF}'], 0xD7FF);
+        assert_eq!(font.chars[&'\u{E000}'], 0xE000);
+    }
+
+    #[test]
+    fn image_grid_font_draws_through_graphics() {
+        // two 2x2 cells: "a" is a full square, "b" only the top-left pixel
+        let mut image = Image::empty(Vec2::new(4, 2));
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1), (2, 0)] {
+            let i = (y * 4 + x) * 4;
+            image.bytes[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+        }
+        let font = BitmapFont::from_image_grid(&image, Vec2::new(2, 2), "ab");
+        assert_eq!(font.glyphs.len(), 2);
+
+        let mut target = Image::empty(Vec2::new(8, 4));
+        {
+            let mut g = target.create_graphics();
+            g.set_font(font);
+            // twice the line height, so each font pixel is 2x2
+            g.set_font_size(4.0);
+            g.draw_str("ba", Vec2::new(0, 0), crate::graphics::Color::WHITE);
+        }
+        let lit: Vec<bool> = target.bytes.chunks(4).map(|p| p[3] != 0).collect();
+        let rows: Vec<String> = lit
+            .chunks(8)
+            .map(|row| row.iter().map(|&p| if p { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(rows, ["##..####", "##..####", "....####", "....####"]);
+    }
+
+    const MENLO: &[u8] = include_bytes!("../examples/menlo-regular.ttf");
+
+    #[test]
+    fn font_fallback() {
+        let cantarell = Font::try_from_bytes(CFF_FONT).unwrap();
+        let menlo = Font::try_from_bytes(MENLO).unwrap();
+        let chain = FontChain::new(cantarell.clone()).with_fallback(menlo.clone());
+
+        // Cantarell has no box drawing glyphs
+        assert_eq!(cantarell.glyph('█').id(), GlyphId(0));
+        let block = chain.glyph('█');
+        assert_ne!(block.id(), GlyphId(0));
+        assert_eq!(FontId::of(block.font()), FontId::of(&menlo));
+        assert_eq!(FontId::of(chain.glyph('A').font()), FontId::of(&cantarell));
+
+        // with no font that has it, the first font's missing glyph is used
+        let alone = FontChain::new(cantarell.clone());
+        assert_eq!(FontId::of(alone.glyph('█').font()), FontId::of(&cantarell));
+
+        let span = TextSpan::new("a█b", chain, 20.0);
+        let text = TextLayout::new(20.0).layout_spans(&[span], Vec2::new(0.0, 0.0));
+        let fonts: Vec<_> = text.glyphs.iter().map(|g| FontId::of(g.font())).collect();
+        assert_eq!(
+            fonts,
+            [
+                FontId::of(&cantarell),
+                FontId::of(&menlo),
+                FontId::of(&cantarell)
+            ]
+        );
+    }
+
+    #[test]
+    fn kerning_can_be_disabled() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let scale = Scale::uniform(30.0);
+        let text = "AVATAR";
+        let advances: f32 = text
+            .chars()
+            .map(|c| font.glyph(c).scaled(scale).h_metrics().advance_width)
+            .sum();
+        let kerning: f32 = text
+            .chars()
+            .zip(text.chars().skip(1))
+            .map(|(a, b)| font.pair_kerning(scale, a, b))
+            .sum();
+
+        let kerned = TextLayout::new(30.0).measure(text, &font).x;
+        let unkerned = TextLayout::new(30.0)
+            .with_kerning(false)
+            .measure(text, &font)
+            .x;
+        assert!((kerned - (advances + kerning)).abs() < 0.01);
+        assert!((unkerned - advances).abs() < 0.01);
+    }
+
+    #[test]
+    fn rich_text_spans() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let red = Color::rgb(255, 0, 0);
+        let spans = [
+            TextSpan::new("small ", font.clone(), 10.0),
+            TextSpan::new("BI", font.clone(), 30.0).with_color(red),
+            // continues the same word, in another span
+            TextSpan::new("g\nnext", font.clone(), 10.0),
+        ];
+        let text = TextLayout::new(10.0).layout_spans(&spans, Vec2::new(0.0, 0.0));
+
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(text.glyphs.len(), 5 + 2 + 1 + 4);
+        assert_eq!(text.colors[5..7], [red, red]);
+        assert_eq!(text.colors[7], Color::WHITE);
+
+        // the first line is as tall as its biggest text, and the small text shares its baseline
+        let big = font.v_metrics(Scale::uniform(30.0));
+        let small = font.v_metrics(Scale::uniform(10.0));
+        assert_eq!(text.lines[0].baseline, big.ascent);
+        assert_eq!(text.glyphs[0].position().y, big.ascent);
+        let second = big.ascent - big.descent + big.line_gap + small.ascent;
+        assert!((text.lines[1].baseline - second).abs() < 0.001);
+
+        // "BIg" is one word, so it wraps as one
+        let width = TextLayout::new(10.0)
+            .layout_spans(&spans[..1], Vec2::new(0.0, 0.0))
+            .size
+            .x;
+        let wrapped = TextLayout::new(10.0)
+            .with_max_width(width + 1.0)
+            .layout_spans(&spans, Vec2::new(0.0, 0.0));
+        assert_eq!(wrapped.lines.len(), 3);
+        assert_eq!(wrapped.lines[1].glyphs, 5..8);
+    }
+
+    #[test]
+    fn rich_text_draws_in_span_colors() {
+        let font = Font::try_from_bytes(CFF_FONT).unwrap();
+        let red = Color::rgb(255, 0, 0);
+        let spans = [
+            TextSpan::new("I", font.clone(), 20.0),
+            TextSpan::new("I", font.clone(), 20.0).with_color(red),
+        ];
+        let mut image = Image::empty(Vec2::new(30, 30));
+        {
+            let mut g = image.create_graphics();
+            g.draw_rich_text(&spans, &TextLayout::new(20.0), Vec2::new(2, 2));
+        }
+        let pixels: Vec<&[u8]> = image.bytes.chunks(4).collect();
+        assert!(pixels.contains(&&[255, 255, 255, 255][..]));
+        assert!(pixels.contains(&&[255, 0, 0, 255][..]));
     }
 }
@@ -0,0 +1,499 @@
+use crate::fonts::Font;
+use crate::graphics::Image;
+use crate::shapes::Rect;
+use crate::vectors::Vec2;
+use rusttype::{GlyphId, Point, PositionedGlyph};
+use std::collections::HashMap;
+
+/// The number of positions within a pixel (on each axis) that glyphs are rasterized at.
+/// Glyphs are snapped to the nearest of these, so text can be placed between pixels.
+pub const SUBPIXEL_STEPS: u8 = 4;
+
+/// Identifies a font while it is alive. Clones of a `Font` share the same data, so have the same id.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct FontId(usize);
+impl FontId {
+    pub fn of(font: &Font) -> Self {
+        // the address of the font data, which every clone points to
+        match font {
+            Font::Ref(face) => Self(std::sync::Arc::as_ptr(face) as *const u8 as usize),
+            Font::Owned(face) => Self(std::sync::Arc::as_ptr(face) as *const u8 as usize),
+        }
+    }
+}
+
+/// What a rasterized glyph is cached by.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct GlyphKey {
+    pub font: FontId,
+    /// the bits of the horizontal and vertical scale
+    pub scale: (u32, u32),
+    pub glyph: GlyphId,
+    /// the fraction of a pixel the glyph is offset by, in `SUBPIXEL_STEPS`
+    pub subpixel: (u8, u8),
+}
+impl GlyphKey {
+    /// The key for a glyph, and the whole pixel that its origin is in.
+    pub fn of(glyph: &PositionedGlyph) -> (Self, Vec2<i32>) {
+        let pos = glyph.position();
+        let (origin_x, step_x) = snap_to_subpixel(pos.x);
+        let (origin_y, step_y) = snap_to_subpixel(pos.y);
+        let scale = glyph.scale();
+        let key = Self {
+            font: FontId::of(glyph.font()),
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            glyph: glyph.id(),
+            subpixel: (step_x, step_y),
+        };
+        (key, Vec2::new(origin_x, origin_y))
+    }
+}
+
+/// Splits a coordinate into a whole pixel and the nearest subpixel step within it.
+fn snap_to_subpixel(e: f32) -> (i32, u8) {
+    let steps = (e * SUBPIXEL_STEPS as f32).round() as i32;
+    (
+        steps.div_euclid(SUBPIXEL_STEPS as i32),
+        steps.rem_euclid(SUBPIXEL_STEPS as i32) as u8,
+    )
+}
+
+/// A glyph that has been rasterized into the atlas.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CachedGlyph {
+    /// Where the coverage of the glyph is in the atlas.
+    pub rect: Rect,
+    /// Where the top-left of the glyph is drawn, relative to the pixel of the glyph's origin.
+    pub offset: Vec2<i32>,
+    /// the tick that the glyph was last used at
+    last_used: u64,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct GlyphCacheStats {
+    /// Lookups of glyphs that were already in the cache.
+    pub hits: u64,
+    /// Lookups of glyphs that had to be rasterized.
+    pub misses: u64,
+    /// Glyphs removed to make room for others.
+    pub evictions: u64,
+    /// Times the atlas ran out of space, and the glyphs still cached were packed into it again.
+    pub repacks: u64,
+}
+
+/// Caches the coverage of rasterized glyphs in an atlas, so drawing the same text again
+/// (like an FPS counter every frame) only copies pixels, instead of rasterizing outlines.
+///
+/// When the cache is full, the least recently used glyphs are evicted.
+pub struct GlyphCache {
+    /// The rasterized glyphs, as white pixels with the coverage in the alpha channel.
+    atlas: Image,
+    entries: HashMap<GlyphKey, CachedGlyph>,
+    /// The most glyphs that are kept at once.
+    capacity: usize,
+    packer: ShelfPacker,
+    tick: u64,
+    stats: GlyphCacheStats,
+}
+impl GlyphCache {
+    pub const DEFAULT_ATLAS_SIZE: Vec2<u32> = Vec2::new(512, 512);
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    pub fn new() -> Self {
+        Self::with_size(Self::DEFAULT_ATLAS_SIZE, Self::DEFAULT_CAPACITY)
+    }
+    /// Constructs a cache with an atlas of `atlas_size` pixels, that keeps at most `capacity` glyphs.
+    pub fn with_size(atlas_size: Vec2<u32>, capacity: usize) -> Self {
+        Self {
+            atlas: Image::empty(atlas_size),
+            entries: HashMap::new(),
+            capacity: capacity.max(1),
+            packer: ShelfPacker::new(atlas_size),
+            tick: 0,
+            stats: GlyphCacheStats::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn atlas(&self) -> &Image {
+        &self.atlas
+    }
+    #[inline(always)]
+    pub fn stats(&self) -> GlyphCacheStats {
+        self.stats
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats = GlyphCacheStats::default();
+    }
+    /// The number of glyphs currently cached.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every glyph. This should be called if a font is dropped and another is loaded,
+    /// since the new font could reuse the old font's `FontId`.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.packer = ShelfPacker::new(self.atlas.size);
+    }
+
+    /// Finds the glyph in the cache, rasterizing it into the atlas if it isn't there.
+    ///
+    /// Returns the cached glyph and the pixel of the glyph's origin,
+    /// or `None` if the glyph has no pixels, or is too big to fit in the atlas.
+    pub fn get_or_insert(&mut self, glyph: &PositionedGlyph) -> Option<(CachedGlyph, Vec2<i32>)> {
+        let (key, origin) = GlyphKey::of(glyph);
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.tick;
+            self.stats.hits += 1;
+            return Some((entry.clone(), origin));
+        }
+
+        // the glyph, moved to its subpixel offset from (0, 0)
+        let step = 1.0 / SUBPIXEL_STEPS as f32;
+        let glyph = glyph.unpositioned().clone().positioned(Point {
+            x: key.subpixel.0 as f32 * step,
+            y: key.subpixel.1 as f32 * step,
+        });
+        // glyphs with no pixels, like spaces, have nothing to cache, so they aren't misses
+        let bb = glyph.pixel_bounding_box()?;
+        self.stats.misses += 1;
+        let size = Vec2::new(bb.width() as u32, bb.height() as u32);
+        if size.x > self.atlas.size.x || size.y > self.atlas.size.y {
+            // no amount of evicting would make room for it
+            return None;
+        }
+
+        if self.entries.len() >= self.capacity {
+            self.evict_lru(self.capacity - 1);
+        }
+        let pos = match self.packer.alloc(size) {
+            Some(pos) => pos,
+            None => {
+                // keep the most recently used half, which will hopefully leave room
+                self.evict_lru(self.entries.len() / 2);
+                self.repack();
+                self.packer.alloc(size)?
+            }
+        };
+
+        let rect = Rect::new(pos.x as i32, pos.y as i32, size.x as i32, size.y as i32);
+        self.clear_rect(&rect);
+        let atlas_width = self.atlas.size.x as usize;
+        let bytes = &mut self.atlas.bytes;
+        glyph.draw(|x, y, coverage| {
+            let index = ((pos.y + y) as usize * atlas_width + (pos.x + x) as usize) * 4;
+            bytes[index + 3] = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+
+        let entry = CachedGlyph {
+            rect,
+            offset: Vec2::new(bb.min.x, bb.min.y),
+            last_used: self.tick,
+        };
+        self.entries.insert(key, entry.clone());
+        Some((entry, origin))
+    }
+
+    /// The coverage (from 0 to 255) of a pixel in the atlas.
+    #[inline(always)]
+    pub fn coverage(&self, pos: Vec2<u32>) -> u8 {
+        self.atlas.bytes[(pos.y as usize * self.atlas.size.x as usize + pos.x as usize) * 4 + 3]
+    }
+
+    /// Evicts the least recently used glyphs, until there are at most `keep` left.
+    fn evict_lru(&mut self, keep: usize) {
+        if self.entries.len() <= keep {
+            return;
+        }
+        let mut ages: Vec<u64> = self.entries.values().map(|e| e.last_used).collect();
+        ages.sort_unstable();
+        let cutoff = ages[ages.len() - keep - 1];
+
+        let before = self.entries.len();
+        self.entries.retain(|_, e| e.last_used > cutoff);
+        self.stats.evictions += (before - self.entries.len()) as u64;
+    }
+
+    /// Packs the glyphs still cached into a fresh atlas, freeing the space of evicted glyphs.
+    fn repack(&mut self) {
+        self.stats.repacks += 1;
+        let size = self.atlas.size;
+        let old = std::mem::replace(&mut self.atlas, Image::empty(size));
+        self.packer = ShelfPacker::new(self.atlas.size);
+
+        // packing the tallest glyphs first wastes the least space
+        let mut entries: Vec<_> = self.entries.iter_mut().collect();
+        entries.sort_unstable_by_key(|(_, e)| -e.rect.h);
+
+        let width = self.atlas.size.x as usize;
+        let mut lost = Vec::new();
+        for (key, entry) in entries {
+            let size = Vec2::new(entry.rect.w as u32, entry.rect.h as u32);
+            let Some(pos) = self.packer.alloc(size) else {
+                lost.push(*key);
+                continue;
+            };
+            for y in 0..entry.rect.h as usize {
+                let src = ((entry.rect.y as usize + y) * width + entry.rect.x as usize) * 4;
+                let dst = ((pos.y as usize + y) * width + pos.x as usize) * 4;
+                let len = entry.rect.w as usize * 4;
+                self.atlas.bytes[dst..dst + len].copy_from_slice(&old.bytes[src..src + len]);
+            }
+            entry.rect.x = pos.x as i32;
+            entry.rect.y = pos.y as i32;
+        }
+        self.stats.evictions += lost.len() as u64;
+        for key in lost {
+            self.entries.remove(&key);
+        }
+    }
+
+    fn clear_rect(&mut self, rect: &Rect) {
+        let width = self.atlas.size.x as usize;
+        for y in rect.y..rect.y + rect.h {
+            let start = (y as usize * width + rect.x as usize) * 4;
+            for pixel in self.atlas.bytes[start..start + rect.w as usize * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[255, 255, 255, 0]);
+            }
+        }
+    }
+
+    /// Draws a cached glyph, with the pixel of its origin at `origin`.
+    pub(crate) fn glyph_pixels(
+        &self,
+        glyph: &CachedGlyph,
+        origin: Vec2<i32>,
+        mut f: impl FnMut(Vec2<i32>, f32),
+    ) {
+        let top_left = origin + glyph.offset;
+        for y in 0..glyph.rect.h {
+            for x in 0..glyph.rect.w {
+                let atlas_pos = Vec2::new((glyph.rect.x + x) as u32, (glyph.rect.y + y) as u32);
+                let coverage = self.coverage(atlas_pos);
+                if coverage != 0 {
+                    f(top_left + Vec2::new(x, y), coverage as f32 / 255.0);
+                }
+            }
+        }
+    }
+}
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Packs rects into rows ("shelves"), each as tall as the tallest rect put in it.
+pub(crate) struct ShelfPacker {
+    size: Vec2<u32>,
+    /// the y, height, and used width of each shelf
+    shelves: Vec<(u32, u32, u32)>,
+}
+impl ShelfPacker {
+    pub(crate) fn new(size: Vec2<u32>) -> Self {
+        Self {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    pub(crate) fn alloc(&mut self, size: Vec2<u32>) -> Option<Vec2<u32>> {
+        if size.x > self.size.x || size.y > self.size.y {
+            return None;
+        }
+        // the shelf that wastes the least height
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|(_, h, used)| size.y <= *h && size.x <= self.size.x - *used)
+            .min_by_key(|(_, h, _)| *h);
+        if let Some((y, _, used)) = best {
+            let pos = Vec2::new(*used, *y);
+            *used += size.x;
+            return Some(pos);
+        }
+
+        let y = self.shelves.last().map_or(0, |(y, h, _)| y + h);
+        if y + size.y > self.size.y {
+            return None;
+        }
+        self.shelves.push((y, size.y, size.x));
+        Some(Vec2::new(0, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::TextLayout;
+    use crate::graphics::Color;
+
+    const FONT: &[u8] = include_bytes!("../tests/fonts/Cantarell-VF.otf");
+
+    fn glyphs<'f>(font: &Font<'f>, text: &str, pos: Vec2<f32>) -> Vec<PositionedGlyph<'f>> {
+        TextLayout::new(20.0).layout(text, font, pos).glyphs
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut cache = GlyphCache::new();
+        for glyph in glyphs(&font, "abc", Vec2::new(0.0, 0.0)) {
+            cache.get_or_insert(&glyph);
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.stats().misses, 3);
+
+        // the same glyphs in another place are reused, if they're at the same subpixel offset
+        for glyph in glyphs(&font, "abc", Vec2::new(10.0, 7.0)) {
+            cache.get_or_insert(&glyph);
+        }
+        assert_eq!(cache.stats().misses, 3);
+        assert_eq!(cache.stats().hits, 3);
+
+        // a different size or subpixel offset is a different glyph
+        cache.get_or_insert(&glyphs(&font, "a", Vec2::new(0.5, 0.0))[0]);
+        let bigger = TextLayout::new(30.0).layout("a", &font, Vec2::new(0.0, 0.0));
+        cache.get_or_insert(&bigger.glyphs[0]);
+        assert_eq!(cache.stats().misses, 5);
+
+        // a clone of the font is the same font
+        let clone = font.clone();
+        cache.get_or_insert(&glyphs(&clone, "a", Vec2::new(0.0, 0.0))[0]);
+        assert_eq!(cache.stats().misses, 5);
+
+        // spaces have no pixels, so drawing text with them a second time doesn't miss either
+        let spaced: Vec<_> = font
+            .layout(
+                "a b  c",
+                rusttype::Scale::uniform(20.0),
+                Point { x: 0.0, y: 20.0 },
+            )
+            .collect();
+        for glyph in &spaced {
+            cache.get_or_insert(glyph);
+        }
+        let misses = cache.stats().misses;
+        for glyph in &spaced {
+            cache.get_or_insert(glyph);
+        }
+        assert_eq!(cache.stats().misses, misses);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut cache = GlyphCache::with_size(GlyphCache::DEFAULT_ATLAS_SIZE, 3);
+        let g = glyphs(&font, "abcd", Vec2::new(0.0, 0.0));
+
+        cache.get_or_insert(&g[0]);
+        cache.get_or_insert(&g[1]);
+        cache.get_or_insert(&g[2]);
+        // 'a' is used again, so 'b' is the least recently used
+        cache.get_or_insert(&g[0]);
+        cache.get_or_insert(&g[3]);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.stats().evictions, 1);
+
+        let misses = cache.stats().misses;
+        cache.get_or_insert(&g[0]);
+        assert_eq!(cache.stats().misses, misses);
+        cache.get_or_insert(&g[1]);
+        assert_eq!(cache.stats().misses, misses + 1);
+    }
+
+    #[test]
+    fn repacks_full_atlas() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        // only room for a few glyphs at a time
+        let mut cache = GlyphCache::with_size(Vec2::new(40, 40), 100);
+        let text = "abcdefghijklmnopqrstuvwxyz";
+        for glyph in glyphs(&font, text, Vec2::new(0.0, 0.0)) {
+            assert!(cache.get_or_insert(&glyph).is_some());
+        }
+        assert!(cache.stats().repacks > 0);
+        assert!(cache.len() < text.len());
+
+        // the glyphs kept after repacking still draw the same
+        let fresh_glyph = &glyphs(&font, "z", Vec2::new(0.0, 0.0))[0];
+        let mut fresh = GlyphCache::new();
+        let (a, _) = cache.get_or_insert(fresh_glyph).unwrap();
+        let (b, _) = fresh.get_or_insert(fresh_glyph).unwrap();
+        let (mut pixels_a, mut pixels_b) = (Vec::new(), Vec::new());
+        cache.glyph_pixels(&a, Vec2::new(0, 0), |p, c| pixels_a.push((p, c)));
+        fresh.glyph_pixels(&b, Vec2::new(0, 0), |p, c| pixels_b.push((p, c)));
+        assert_eq!(pixels_a, pixels_b);
+    }
+
+    #[test]
+    fn cached_text_matches_uncached() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let draw = |cache: Option<&mut GlyphCache>| {
+            let mut image = Image::empty(Vec2::new(80, 30));
+            {
+                let mut g = image.create_graphics();
+                if let Some(cache) = cache {
+                    g.set_glyph_cache(cache);
+                }
+                g.draw_str_with_font("Hello", &font, 20.0, Vec2::new(2, 2), Color::WHITE);
+            }
+            image.bytes
+        };
+        let mut cache = GlyphCache::new();
+        let cached = draw(Some(&mut cache));
+        let misses = cache.stats().misses;
+        assert_eq!(cache.stats().hits + misses, 5);
+
+        // the second time, every glyph is already cached
+        assert_eq!(draw(Some(&mut cache)), cached);
+        assert_eq!(cache.stats().misses, misses);
+        assert_eq!(cache.stats().hits, 5);
+
+        // glyphs are snapped to the nearest subpixel step, so they aren't exactly the same as uncached,
+        // but the same amount of ink is drawn
+        let ink = |bytes: &[u8]| bytes.iter().step_by(4).map(|&b| b as f32).sum::<f32>();
+        let uncached = draw(None);
+        assert!((ink(&cached) / ink(&uncached) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn glyphs_too_big_for_the_atlas() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut cache = GlyphCache::with_size(Vec2::new(16, 16), 100);
+        let small = glyphs(&font, "a", Vec2::new(0.0, 0.0));
+        assert!(cache.get_or_insert(&small[0]).is_some());
+
+        // a glyph bigger than the atlas isn't cached, and doesn't evict the glyphs that are
+        let big = TextLayout::new(60.0).layout("W", &font, Vec2::new(0.0, 0.0));
+        assert!(cache.get_or_insert(&big.glyphs[0]).is_none());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.stats().evictions, 0);
+
+        // so it's drawn without the cache instead
+        let draw = |cache: Option<&mut GlyphCache>| {
+            let mut image = Image::empty(Vec2::new(80, 80));
+            {
+                let mut g = image.create_graphics();
+                if let Some(cache) = cache {
+                    g.set_glyph_cache(cache);
+                }
+                g.draw_str_with_font("W", &font, 60.0, Vec2::new(2, 2), Color::WHITE);
+                if let Some(cache) = g.glyph_cache_mut() {
+                    cache.clear();
+                }
+            }
+            image.bytes
+        };
+        let uncached = draw(None);
+        assert!(uncached.iter().any(|&b| b != 0));
+        assert_eq!(draw(Some(&mut cache)), uncached);
+        assert!(cache.is_empty());
+    }
+}
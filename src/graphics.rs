@@ -1,7 +1,20 @@
+use crate::blend::BlendMode;
+use crate::calc_index;
+use crate::depth::DepthBuffer;
+use crate::fonts::{BitmapFont, Font, LaidOutText, TextFont, TextLayout, TextSpan};
+use crate::glyph_cache::GlyphCache;
+use crate::paint::{Filter, Paint};
+use crate::path::{self, Path};
 use crate::pixel_buf::{PixBufMutView, PixBufView};
-use crate::shapes::{Line, Poly, Rect, Tri};
-use crate::tri_rasterizer;
-use crate::vectors::Vec2;
+use crate::poly_rasterizer::{self, FillRule};
+use crate::sdf::{self, SdfCache, SdfStyle};
+use crate::shapes::{CornerRadii, Line, Poly, Rect, Tri};
+use crate::stroke::{self, Stroke};
+use crate::tri_rasterizer::{self, TexMapping};
+use crate::vectors::{Vec2, Vec3};
+use rusttype::PositionedGlyph;
+use std::f32::consts::TAU;
+use std::ops::RangeInclusive;
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 #[repr(C)]
@@ -50,6 +63,49 @@ impl Color {
         }
     }
 
+    /// Returns this color with its alpha multiplied by `factor` (0 to 1).
+    #[inline(always)]
+    pub fn scale_alpha(self, factor: f32) -> Self {
+        let a = (self.a as f32 * factor.clamp(0.0, 1.0)).round() as u8;
+        Self { a, ..self }
+    }
+
+    /// Composites this color on top of `dst`, using "source-over" alpha blending.
+    pub fn blend_over(self, dst: Color) -> Self {
+        if self.a == 255 || dst.a == 0 {
+            return self;
+        }
+        if self.a == 0 {
+            return dst;
+        }
+        let sa = self.a as u32;
+        let inv_sa = 255 - sa;
+
+        if dst.a == 255 {
+            let mix = |s: u8, d: u8| ((s as u32 * sa + d as u32 * inv_sa + 127) / 255) as u8;
+            return Self::rgba(
+                mix(self.r, dst.r),
+                mix(self.g, dst.g),
+                mix(self.b, dst.b),
+                255,
+            );
+        }
+
+        // the alpha's are scaled by 255 so the division is only done once per channel
+        let src_weight = sa * 255;
+        let dst_weight = dst.a as u32 * inv_sa;
+        let out_a = src_weight + dst_weight;
+        let mix = |s: u8, d: u8| {
+            ((s as u32 * src_weight + d as u32 * dst_weight + out_a / 2) / out_a) as u8
+        };
+        Self::rgba(
+            mix(self.r, dst.r),
+            mix(self.g, dst.g),
+            mix(self.b, dst.b),
+            ((out_a + 127) / 255) as u8,
+        )
+    }
+
     #[inline(always)]
     pub const fn to_u32(self) -> u32 {
         unsafe { std::mem::transmute(self) }
@@ -103,10 +159,8 @@ impl Image {
     /// Creates a `Graphics` with a mutable borrow of the buffer for this image.
     /// All drawing functions in `Graphics` will directly effect this image.
     pub fn create_graphics(&mut self) -> Graphics {
-        Graphics {
-            size: self.size,
-            buffer: self.pixels_mut(),
-        }
+        let size = self.size;
+        Graphics::new(self.pixels_mut(), size)
     }
     /// Creates a `Rect` at the position given, and with the same size as this image.
     pub fn rect_at(&self, pos: Vec2<i32>) -> Rect {
@@ -126,10 +180,31 @@ impl Image {
 pub struct Graphics<'a> {
     pub buffer: PixBufMutView<'a>,
     size: Vec2<u32>,
+    font: Option<TextFont<'a>>,
+    font_size: f32,
+    blend_mode: BlendMode,
+    blend_mode_stack: Vec<BlendMode>,
+    anti_alias: bool,
+    stroke: Stroke,
+    glyph_cache: Option<&'a mut GlyphCache>,
+    depth_buffer: Option<&'a mut DepthBuffer>,
 }
 impl<'a> Graphics<'a> {
+    pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
     pub fn new(buffer: PixBufMutView<'a>, size: Vec2<u32>) -> Self {
-        Self { buffer, size }
+        Self {
+            buffer,
+            size,
+            font: None,
+            font_size: Self::DEFAULT_FONT_SIZE,
+            blend_mode: BlendMode::default(),
+            blend_mode_stack: Vec::new(),
+            anti_alias: false,
+            stroke: Stroke::default(),
+            glyph_cache: None,
+            depth_buffer: None,
+        }
     }
 
     #[inline(always)]
@@ -137,6 +212,103 @@ impl<'a> Graphics<'a> {
         self.size
     }
 
+    #[inline(always)]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+    /// Sets how colors are blended with the buffer by every drawing function (except `fill`).
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+    /// Saves the current blend mode, then sets it to `mode`.
+    /// The saved mode can be restored with `pop_blend_mode`.
+    pub fn push_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode_stack.push(self.blend_mode);
+        self.blend_mode = mode;
+    }
+    /// Restores the blend mode saved by the last call to `push_blend_mode`.
+    /// Does nothing if there is no saved mode.
+    pub fn pop_blend_mode(&mut self) {
+        if let Some(mode) = self.blend_mode_stack.pop() {
+            self.blend_mode = mode;
+        }
+    }
+
+    #[inline(always)]
+    pub fn anti_alias(&self) -> bool {
+        self.anti_alias
+    }
+    /// When enabled, `draw_line`, `draw_tri`, `draw_poly`, `fill_tri` and `fill_circle`
+    /// use their anti-aliased variants (`draw_line_aa`, `fill_tri_aa` and `fill_circle_aa`),
+    /// and ellipses, arcs, pies and rounded rectangles blend the pixels along their edges.
+    pub fn set_anti_alias(&mut self, anti_alias: bool) {
+        self.anti_alias = anti_alias;
+    }
+
+    #[inline(always)]
+    pub fn stroke(&self) -> &Stroke {
+        &self.stroke
+    }
+    /// Sets the stroke used by `draw_line`, `draw_polyline`, `draw_tri`, `draw_rect` and `draw_poly`.
+    /// Strokes that are wider than a pixel are drawn with `stroke_polyline`.
+    pub fn set_stroke(&mut self, stroke: Stroke) {
+        self.stroke = stroke;
+    }
+
+    /// Sets the font used by `draw_str`, which can be a vector `Font` or a `BitmapFont`.
+    pub fn set_font(&mut self, font: impl Into<TextFont<'a>>) {
+        self.font = Some(font.into());
+    }
+    /// Sets the size, in pixels, of the text drawn by `draw_str`.
+    pub fn set_font_size(&mut self, size: f32) {
+        self.font_size = size;
+    }
+    #[inline(always)]
+    pub fn font(&self) -> Option<&TextFont<'a>> {
+        self.font.as_ref()
+    }
+    #[inline(always)]
+    pub fn font_size(&self) -> f32 {
+        self.font_size
+    }
+
+    /// Sets the cache that glyphs are drawn from, so text drawn again doesn't have to be rasterized again.
+    pub fn set_glyph_cache(&mut self, cache: &'a mut GlyphCache) {
+        self.glyph_cache = Some(cache);
+    }
+    pub fn glyph_cache(&self) -> Option<&GlyphCache> {
+        self.glyph_cache.as_deref()
+    }
+    pub fn glyph_cache_mut(&mut self) -> Option<&mut GlyphCache> {
+        self.glyph_cache.as_deref_mut()
+    }
+
+    /// Sets the depth buffer that depth-tested drawing (`fill_tri_depth` and `Renderer3d`) is tested against.
+    /// Without one, depth-tested drawing draws every pixel.
+    ///
+    /// A `CanvasApp` can be given one by `CanvasConfig::with_depth_buffer`.
+    pub fn set_depth_buffer(&mut self, buffer: &'a mut DepthBuffer) {
+        self.depth_buffer = Some(buffer);
+    }
+    pub fn depth_buffer(&self) -> Option<&DepthBuffer> {
+        self.depth_buffer.as_deref()
+    }
+    pub fn depth_buffer_mut(&mut self) -> Option<&mut DepthBuffer> {
+        self.depth_buffer.as_deref_mut()
+    }
+    /// Detaches the depth buffer, so depth-tested drawing draws every pixel again.
+    pub fn take_depth_buffer(&mut self) -> Option<&'a mut DepthBuffer> {
+        self.depth_buffer.take()
+    }
+    /// If a pixel at `depth` passes the depth buffer's test (writing its depth if it does), or `true` without a depth buffer.
+    #[inline(always)]
+    pub(crate) fn depth_test(&mut self, pos: Vec2<i32>, depth: f32) -> bool {
+        match &mut self.depth_buffer {
+            Some(buffer) => buffer.test(pos, depth),
+            None => true,
+        }
+    }
+
     pub fn draw_pixel(&mut self, pos: Vec2<i32>, color: Color) {
         if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x as i32 || pos.y >= self.size.y as i32 {
             return;
@@ -146,11 +318,43 @@ impl<'a> Graphics<'a> {
             self.draw_pixel_unchecked(pos, color);
         }
     }
+    /// Blends `color` into the pixel at `pos`, using the current blend mode.
+    ///
+    /// **UNSAFE** - Given a position that is not in bounds, this function will cause undefined behavior.
     #[inline(always)]
     pub unsafe fn draw_pixel_unchecked(&mut self, pos: Vec2<i32>, color: Color) {
-        self.buffer.set_pixel_unchecked(pos, color); // TODO implement alpha blending
+        self.draw_pixel_by_index(calc_index!(pos, self.size.x), color);
+    }
+
+    /// Blends `color` into the pixel at `index` in the buffer, using the current blend mode.
+    ///
+    /// **UNSAFE** - Given an invalid index, this function will cause undefined behavior.
+    #[inline(always)]
+    unsafe fn draw_pixel_by_index(&mut self, index: usize, color: Color) {
+        match (self.blend_mode, color.a) {
+            (BlendMode::Replace, _) | (BlendMode::SourceOver, 255) => {
+                self.buffer.set_pixel_by_index(index, color)
+            }
+            (_, 0) => {}
+            (mode, _) => {
+                let dst = self.buffer.get_pixel_by_index(index);
+                self.buffer
+                    .set_pixel_by_index(index, mode.blend(color, dst));
+            }
+        }
+    }
+
+    /// Blends `color` into the pixel at `pos`, where `coverage` (0 to 1) is
+    /// how much of the pixel is covered by the shape being drawn.
+    #[inline(always)]
+    pub fn draw_pixel_coverage(&mut self, pos: Vec2<i32>, color: Color, coverage: f32) {
+        self.draw_pixel(pos, color.scale_alpha(coverage));
     }
 
+    /// Sets every pixel to `color`.
+    ///
+    /// Unlike the other drawing functions, this ignores the blend mode,
+    /// so it can be used to clear a buffer to a transparent color.
     pub fn fill(&mut self, color: Color) {
         let buffer_size = self.buffer.bytes.len();
         let max_index = buffer_size - 16;
@@ -175,6 +379,19 @@ impl<'a> Graphics<'a> {
     }
 
     pub fn draw_line(&mut self, line: &Line, color: Color) {
+        if !self.stroke.is_plain() {
+            let stroke = self.stroke.clone();
+            self.stroke_polyline(&[line.0, line.1], false, &stroke, color);
+            return;
+        }
+        self.draw_plain_line(line, color);
+    }
+    /// Draws a 1px line, ignoring the current stroke.
+    fn draw_plain_line(&mut self, line: &Line, color: Color) {
+        if self.anti_alias {
+            self.draw_line_aa(line, color);
+            return;
+        }
         let Line(mut from, to) = *line;
 
         let dist_x = (to.x - from.x).abs();
@@ -203,11 +420,73 @@ impl<'a> Graphics<'a> {
         }
     }
 
+    /// Draws an anti-aliased line using Xiaolin Wu's algorithm.
+    pub fn draw_line_aa(&mut self, line: &Line, color: Color) {
+        let Line(from, to) = *line;
+        self.draw_line_aa_f32(from.map(|e| e as f32), to.map(|e| e as f32), color);
+    }
+    /// Draws an anti-aliased line, between points that don't have to lie on pixel centers,
+    /// using Xiaolin Wu's algorithm.
+    pub fn draw_line_aa_f32(&mut self, mut from: Vec2<f32>, mut to: Vec2<f32>, color: Color) {
+        fn fpart(v: f32) -> f32 {
+            v - v.floor()
+        }
+        fn rfpart(v: f32) -> f32 {
+            1.0 - fpart(v)
+        }
+
+        // the algorithm steps along x, so for steep lines x and y are swapped (and swapped back when plotting)
+        let steep = (to.y - from.y).abs() > (to.x - from.x).abs();
+        if steep {
+            from = Vec2::new(from.y, from.x);
+            to = Vec2::new(to.y, to.x);
+        }
+        if from.x > to.x {
+            std::mem::swap(&mut from, &mut to);
+        }
+        let mut plot = |x: i32, y: i32, coverage: f32| {
+            let pos = if steep {
+                Vec2::new(y, x)
+            } else {
+                Vec2::new(x, y)
+            };
+            self.draw_pixel_coverage(pos, color, coverage);
+        };
+
+        let dx = to.x - from.x;
+        let gradient = if dx == 0.0 { 1.0 } else { (to.y - from.y) / dx };
+
+        // first endpoint
+        let x_end = from.x.round();
+        let y_end = from.y + gradient * (x_end - from.x);
+        let x_gap = rfpart(from.x + 0.5);
+        let x_start = x_end as i32;
+        plot(x_start, y_end.floor() as i32, rfpart(y_end) * x_gap);
+        plot(x_start, y_end.floor() as i32 + 1, fpart(y_end) * x_gap);
+        let mut inter_y = y_end + gradient;
+
+        // second endpoint
+        let x_end = to.x.round();
+        let y_end = to.y + gradient * (x_end - to.x);
+        let x_gap = fpart(to.x + 0.5);
+        let x_stop = x_end as i32;
+        if x_stop != x_start {
+            plot(x_stop, y_end.floor() as i32, rfpart(y_end) * x_gap);
+            plot(x_stop, y_end.floor() as i32 + 1, fpart(y_end) * x_gap);
+        }
+
+        for x in (x_start + 1)..x_stop {
+            plot(x, inter_y.floor() as i32, rfpart(inter_y));
+            plot(x, inter_y.floor() as i32 + 1, fpart(inter_y));
+            inter_y += gradient;
+        }
+    }
+
     pub fn fill_col(&mut self, col: i32, mut from: i32, mut to: i32, color: Color) {
         let (self_w, self_h) = (self.size.x as i32, self.size.y as i32);
 
         // check that column is visible
-        if col < 0 || col >= self_w || from >= self_w {
+        if col < 0 || col >= self_w || from >= self_h {
             return;
         }
 
@@ -236,7 +515,7 @@ impl<'a> Graphics<'a> {
         if from < 0 {
             from = 0
         }
-        if from >= self_w {
+        if to >= self_w {
             to = self_w - 1
         }
 
@@ -245,28 +524,109 @@ impl<'a> Graphics<'a> {
             self.draw_pixel(Vec2 { x, y: row }, color);
         }
     }
+    /// Like `fill_row`, but with each pixel colored by `paint`.
+    pub(crate) fn paint_row(&mut self, row: i32, mut from: i32, mut to: i32, paint: &Paint) {
+        if let Some(color) = paint.as_solid() {
+            self.fill_row(row, from, to, color);
+            return;
+        }
+        if row < 0 || row >= self.size.y as i32 {
+            return;
+        }
+        from = from.max(0);
+        to = to.min(self.size.x as i32 - 1);
+        for x in from..=to {
+            let pos = Vec2 { x, y: row };
+            self.draw_pixel(pos, paint.color_at_pixel(pos));
+        }
+    }
+    /// Like `draw_pixel_coverage`, but with the pixel colored by `paint`.
+    #[inline(always)]
+    pub(crate) fn paint_pixel_coverage(&mut self, pos: Vec2<i32>, paint: &Paint, coverage: f32) {
+        self.draw_pixel_coverage(pos, paint.color_at_pixel(pos), coverage);
+    }
 
     pub fn draw_tri(&mut self, tri: &Tri, color: Color) {
+        if !self.stroke.is_plain() {
+            let stroke = self.stroke.clone();
+            self.stroke_polyline(&[tri.0, tri.1, tri.2], true, &stroke, color);
+            return;
+        }
         self.draw_line(&Line(tri.0, tri.1), color);
         self.draw_line(&Line(tri.1, tri.2), color);
         self.draw_line(&Line(tri.2, tri.0), color);
     }
-    pub fn fill_tri(&mut self, tri: &Tri, color: Color) {
-        tri_rasterizer::raster_tri(self, [tri.0, tri.1, tri.2], color);
+    pub fn fill_tri<'p>(&mut self, tri: &Tri, paint: impl Into<Paint<'p>>) {
+        if self.anti_alias {
+            self.fill_tri_aa(tri, paint);
+            return;
+        }
+        tri_rasterizer::raster_tri(self, [tri.0, tri.1, tri.2], &paint.into());
+    }
+    /// Fills a triangle, blending the pixels along its edges by how much of them the triangle covers.
+    ///
+    /// Note that pixels on an edge shared by two triangles are blended by both,
+    /// which can leave a faint seam between them.
+    pub fn fill_tri_aa<'p>(&mut self, tri: &Tri, paint: impl Into<Paint<'p>>) {
+        let points = [tri.0, tri.1, tri.2].map(|p| p.map(|e| e as f32));
+        tri_rasterizer::raster_tri_aa(self, points, &paint.into());
+    }
+
+    /// Fills a triangle with a color at each of its points, blending smoothly between them (Gouraud shading).
+    ///
+    /// Triangles that share an edge, like those in a mesh, never overlap or leave a gap between them.
+    pub fn fill_tri_colored(&mut self, tri: &Tri, colors: [Color; 3]) {
+        tri_rasterizer::raster_tri_colored(self, [tri.0, tri.1, tri.2], colors);
+    }
+
+    /// Fills a triangle whose points each have a depth (in `z`), which is blended across the triangle
+    /// and tested against the depth buffer (see `set_depth_buffer`), so nearer triangles cover farther ones whatever order they're drawn in.
+    ///
+    /// Like `fill_tri_colored`, triangles that share an edge never overlap or leave a gap between them.
+    pub fn fill_tri_depth<'p>(&mut self, points: [Vec3<f32>; 3], paint: impl Into<Paint<'p>>) {
+        tri_rasterizer::raster_tri_depth(self, points, &paint.into());
+    }
+
+    /// Fills a triangle with part of a texture, where `uvs` are the texture coordinates of each point
+    /// (from `(0.0, 0.0)` at the top-left of the texture to `(1.0, 1.0)` at the bottom-right, repeating past them).
+    ///
+    /// `mapping` is how the coordinates are spread across the triangle (see `TexMapping`),
+    /// and `filter` is how the texture is sampled.
+    pub fn fill_tri_textured(
+        &mut self,
+        tri: &Tri,
+        uvs: [Vec2<f32>; 3],
+        texture: PixBufView,
+        mapping: TexMapping,
+        filter: Filter,
+    ) {
+        let points = [tri.0, tri.1, tri.2].map(|p| p.map(|e| e as f32));
+        tri_rasterizer::raster_tri_textured(self, points, uvs, texture, mapping, filter);
     }
 
     pub fn draw_rect(&mut self, rect: &Rect, color: Color) {
+        if !self.stroke.is_plain() {
+            let stroke = self.stroke.clone();
+            self.stroke_polyline(&rect.points(), true, &stroke, color);
+            return;
+        }
         let l = rect.x;
         let t = rect.y;
         let r = l + rect.w;
         let b = t + rect.h;
 
-        self.fill_col(l, t, b, color);
-        self.fill_col(r, t, b, color);
+        // the columns skip the corners so no pixel is blended twice
         self.fill_row(t, l, r, color);
-        self.fill_row(b, l, r, color);
+        if b != t {
+            self.fill_row(b, l, r, color);
+        }
+        self.fill_col(l, t + 1, b - 1, color);
+        if r != l {
+            self.fill_col(r, t + 1, b - 1, color);
+        }
     }
-    pub fn fill_rect(&mut self, rect: &Rect, color: Color) {
+    pub fn fill_rect<'p>(&mut self, rect: &Rect, paint: impl Into<Paint<'p>>) {
+        let paint = paint.into();
         // clipping
         let self_w = self.size.x as i32;
         let self_h = self.size.y as i32;
@@ -288,9 +648,154 @@ impl<'a> Graphics<'a> {
         }
 
         // drawing
-        for x in x..(x + w) {
-            for y in y..(y + h) {
-                self.draw_pixel(Vec2 { x, y }, color);
+        for y in y..(y + h) {
+            self.paint_row(y, x, x + w - 1, &paint);
+        }
+    }
+
+    /// Draws the outline of a rectangle with rounded corners (or with the current stroke, if it isn't plain).
+    ///
+    /// The outline goes through the same pixels as `draw_rect`, with each corner replaced by a quarter of a circle.
+    /// Radii that are too big for the rectangle are shrunk to fit (see `CornerRadii::clamped`).
+    pub fn draw_round_rect(&mut self, rect: &Rect, radii: impl Into<CornerRadii>, color: Color) {
+        if rect.w < 0 || rect.h < 0 {
+            return;
+        }
+        let radii = radii.into().clamped(rect.size());
+        if !self.stroke.is_plain() {
+            let mut path = Path::new();
+            let pos = rect.pos().map(|e| e as f32 + 0.5);
+            path.round_rect(
+                pos,
+                rect.size().map(|e| e as f32),
+                round_rect_radii_f32(radii),
+            );
+            let stroke = self.stroke.clone();
+            self.stroke_path(&path, &stroke, color);
+            return;
+        }
+        if self.anti_alias {
+            // the outline is centered on the centers of the pixels along the edge of the rectangle
+            let (min, max) = (rect.tl(), rect.br());
+            let paint = Paint::Solid(color);
+            self.shade_round_rect(
+                rect.x - 1..=max.x + 1,
+                rect.y - 1..=max.y + 1,
+                &paint,
+                |p| {
+                    let dist = round_rect_dist(p.map(|e| e as f32), min, max, radii);
+                    1.0 - dist.abs()
+                },
+            );
+            return;
+        }
+
+        let mut points = Vec::new();
+        let (l, t) = (rect.x, rect.y);
+        let (r, b) = (l + rect.w, t + rect.h);
+        let corners = [
+            (
+                Vec2::new(l + radii.tl, t + radii.tl),
+                radii.tl,
+                Vec2::new(-1, -1),
+            ),
+            (
+                Vec2::new(r - radii.tr, t + radii.tr),
+                radii.tr,
+                Vec2::new(1, -1),
+            ),
+            (
+                Vec2::new(r - radii.br, b - radii.br),
+                radii.br,
+                Vec2::new(1, 1),
+            ),
+            (
+                Vec2::new(l + radii.bl, b - radii.bl),
+                radii.bl,
+                Vec2::new(-1, 1),
+            ),
+        ];
+        for (center, radius, dir) in corners {
+            ellipse_points(Vec2::new(radius, radius), |offset| {
+                if offset.x * dir.x >= 0 && offset.y * dir.y >= 0 {
+                    points.push(center + offset);
+                }
+            });
+        }
+        points.extend((l + radii.tl + 1..r - radii.tr).map(|x| Vec2::new(x, t)));
+        points.extend((l + radii.bl + 1..r - radii.br).map(|x| Vec2::new(x, b)));
+        points.extend((t + radii.tl + 1..b - radii.bl).map(|y| Vec2::new(l, y)));
+        points.extend((t + radii.tr + 1..b - radii.br).map(|y| Vec2::new(r, y)));
+
+        // corners and sides can meet on the same pixel in small rectangles, so each pixel is only drawn once
+        points.sort_unstable_by_key(|p| (p.y, p.x));
+        points.dedup();
+        for p in points {
+            self.draw_pixel(p, color);
+        }
+    }
+
+    /// Fills a rectangle with rounded corners, covering the same pixels as `fill_rect` except at the corners.
+    ///
+    /// Radii that are too big for the rectangle are shrunk to fit (see `CornerRadii::clamped`).
+    pub fn fill_round_rect<'p>(
+        &mut self,
+        rect: &Rect,
+        radii: impl Into<CornerRadii>,
+        paint: impl Into<Paint<'p>>,
+    ) {
+        if rect.w <= 0 || rect.h <= 0 {
+            return;
+        }
+        let radii = radii.into().clamped(rect.size());
+        let paint = paint.into();
+        let (min, max) = (rect.tl(), rect.br());
+        if self.anti_alias {
+            self.shade_round_rect(rect.x..=max.x - 1, rect.y..=max.y - 1, &paint, |p| {
+                // the distance is measured from the centers of pixels
+                let dist = round_rect_dist(p.map(|e| e as f32 + 0.5), min, max, radii);
+                0.5 - dist
+            });
+            return;
+        }
+
+        // how far in from the side of the rectangle a corner's curve is, `dist` pixels in from the top or bottom
+        let inset = |radius: i32, dist: f32| {
+            let r = radius as f32;
+            if dist >= r {
+                return 0.0;
+            }
+            r - (r * r - (r - dist) * (r - dist)).sqrt()
+        };
+        for y in rect.y..max.y {
+            let (from_top, from_bottom) =
+                (y as f32 + 0.5 - min.y as f32, max.y as f32 - y as f32 - 0.5);
+            let left = inset(radii.tl, from_top).max(inset(radii.bl, from_bottom));
+            let right = inset(radii.tr, from_top).max(inset(radii.br, from_bottom));
+            // the first and last pixels with their centers inside the shape
+            let from = (min.x as f32 + left - 0.5).ceil() as i32;
+            let to = (max.x as f32 - right - 0.5).floor() as i32;
+            if from <= to {
+                self.paint_row(y, from, to, &paint);
+            }
+        }
+    }
+
+    /// Draws every pixel in the given ranges, with the coverage given for its position.
+    fn shade_round_rect(
+        &mut self,
+        xs: RangeInclusive<i32>,
+        ys: RangeInclusive<i32>,
+        paint: &Paint,
+        coverage: impl Fn(Vec2<i32>) -> f32,
+    ) {
+        for y in ys {
+            for x in xs.clone() {
+                let p = Vec2::new(x, y);
+                let c = coverage(p).clamp(0.0, 1.0);
+                if c > 0.0 {
+                    self.paint_pixel_coverage(p, paint, c);
+                }
             }
         }
     }
@@ -299,6 +804,11 @@ impl<'a> Graphics<'a> {
         if poly.points.len() < 2 {
             return;
         }
+        if !self.stroke.is_plain() {
+            let stroke = self.stroke.clone();
+            self.stroke_polyline(&poly.points, true, &stroke, color);
+            return;
+        }
 
         for i in 1..poly.points.len() {
             self.draw_line(&Line(poly.points[i - 1], poly.points[i]), color);
@@ -306,9 +816,268 @@ impl<'a> Graphics<'a> {
         self.draw_line(&Line(poly.points[0], *poly.points.last().unwrap()), color);
     }
 
-    #[allow(unused_variables)]
-    pub fn draw_str(&mut self, s: &str, color: Color) {
-        todo!()
+    /// Fills a polygon, which may be concave or intersect itself.
+    ///
+    /// `rule` decides which parts of a self-intersecting polygon are inside it.
+    pub fn fill_poly<'p>(&mut self, poly: &Poly, rule: FillRule, paint: impl Into<Paint<'p>>) {
+        self.fill_polys(std::slice::from_ref(poly), rule, paint);
+    }
+    /// Fills the shape made by several polygons (contours) together,
+    /// such as a shape with holes.
+    ///
+    /// `rule` decides which parts of the shape are inside it (see `FillRule`).
+    pub fn fill_polys<'p>(&mut self, polys: &[Poly], rule: FillRule, paint: impl Into<Paint<'p>>) {
+        let contours: Vec<Vec<Vec2<f32>>> = polys
+            .iter()
+            .map(|poly| poly.points.iter().map(|p| p.map(|e| e as f32)).collect())
+            .collect();
+        self.fill_polys_f32(&contours, rule, paint);
+    }
+    /// Fills the shape made by several contours together, where `(0.0, 0.0)` is the top-left corner of the top-left pixel,
+    /// such as the glyphs from `fonts::build_text`.
+    ///
+    /// `rule` decides which parts of the shape are inside it (see `FillRule`).
+    pub fn fill_polys_f32<'p, C: AsRef<[Vec2<f32>]>>(
+        &mut self,
+        contours: &[C],
+        rule: FillRule,
+        paint: impl Into<Paint<'p>>,
+    ) {
+        let paint = paint.into();
+        if self.anti_alias {
+            poly_rasterizer::raster_polys_aa(self, contours, rule, &paint);
+        } else {
+            poly_rasterizer::raster_polys(self, contours, rule, &paint);
+        }
+    }
+
+    /// Fills the shape made by all the sub-paths of `path`, with its curves flattened to within `path::DEFAULT_TOLERANCE`.
+    ///
+    /// `rule` decides which parts of the shape are inside it (see `FillRule`).
+    pub fn fill_path<'p>(&mut self, path: &Path, rule: FillRule, paint: impl Into<Paint<'p>>) {
+        let sub_paths = path.flatten(path::DEFAULT_TOLERANCE);
+        self.fill_polys_f32(&sub_paths, rule, paint);
+    }
+    /// Draws the outline of every sub-path of `path` with the given stroke.
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke, color: Color) {
+        for sub_path in path.flatten(path::DEFAULT_TOLERANCE) {
+            // paths use the corners of pixels, but strokes are centered on the points they are given
+            let points: Vec<Vec2<f32>> = sub_path.points.iter().map(|p| *p - 0.5).collect();
+            self.stroke_polyline_f32(&points, sub_path.closed, stroke, color);
+        }
+    }
+
+    /// Draws lines connecting each point to the next, without connecting the last point back to the first.
+    pub fn draw_polyline(&mut self, points: &[Vec2<i32>], color: Color) {
+        if !self.stroke.is_plain() {
+            let stroke = self.stroke.clone();
+            self.stroke_polyline(points, false, &stroke, color);
+            return;
+        }
+        for i in 1..points.len() {
+            self.draw_line(&Line(points[i - 1], points[i]), color);
+        }
+    }
+
+    /// Draws a line through `points` with the given stroke.
+    ///
+    /// If `closed` is true, the last point is joined back to the first.
+    pub fn stroke_polyline(
+        &mut self,
+        points: &[Vec2<i32>],
+        closed: bool,
+        stroke: &Stroke,
+        color: Color,
+    ) {
+        let points: Vec<Vec2<f32>> = points.iter().map(|p| p.map(|e| e as f32)).collect();
+        self.stroke_polyline_f32(&points, closed, stroke, color);
+    }
+    /// Draws a line through `points` with the given stroke, where `(0.0, 0.0)` is the center of the top-left pixel.
+    ///
+    /// Strokes wider than a pixel are drawn by filling the triangles from `stroke::stroke_to_tris` with `fill_polys_f32`.
+    /// If `closed` is true, the last point is joined back to the first.
+    pub fn stroke_polyline_f32(
+        &mut self,
+        points: &[Vec2<f32>],
+        closed: bool,
+        stroke: &Stroke,
+        color: Color,
+    ) {
+        if !stroke.is_dashed() {
+            self.stroke_solid_polyline(points, closed, stroke, color);
+            return;
+        }
+        for dash in stroke::dash_polyline(points, closed, &stroke.dash, stroke.dash_offset) {
+            self.stroke_solid_polyline(&dash, false, stroke, color);
+        }
+    }
+    fn stroke_solid_polyline(
+        &mut self,
+        points: &[Vec2<f32>],
+        closed: bool,
+        stroke: &Stroke,
+        color: Color,
+    ) {
+        if stroke.is_hairline() {
+            let mut points = points.to_vec();
+            points.dedup();
+            if closed && points.len() > 2 {
+                points.push(points[0]);
+            }
+            if points.len() == 1 {
+                self.draw_pixel(points[0].map(|e| e.round() as i32), color);
+            }
+            for i in 1..points.len() {
+                let (from, to) = (points[i - 1], points[i]);
+                if self.anti_alias {
+                    self.draw_line_aa_f32(from, to, color);
+                } else {
+                    let line = Line(from.map(|e| e.round() as i32), to.map(|e| e.round() as i32));
+                    self.draw_plain_line(&line, color);
+                }
+            }
+            return;
+        }
+
+        // the stroke is centered on the pixels the points refer to, rather than their top-left corners
+        let points: Vec<Vec2<f32>> = points.iter().map(|p| *p + 0.5).collect();
+
+        // filling the triangles together means the pixels where they overlap are only drawn once
+        let tris = stroke::stroke_to_tris(&points, closed, stroke);
+        self.fill_polys_f32(&tris, FillRule::NonZero, color);
+    }
+
+    /// Draws a string with the font set by `set_font`, at the size set by `set_font_size`.
+    /// Bitmap fonts are scaled up by the whole number closest to the font size over their line height.
+    ///
+    /// `pos` is the top-left of the first line of text, and each `\n` starts a new line.
+    /// Does nothing if no font has been set.
+    pub fn draw_str(&mut self, s: &str, pos: Vec2<i32>, color: Color) {
+        match self.font.clone() {
+            Some(TextFont::Vector(font)) => {
+                self.draw_str_with_font(s, &font, self.font_size, pos, color)
+            }
+            Some(TextFont::Bitmap(font)) => {
+                let scale = (self.font_size / font.line_height.max(1) as f32).round();
+                self.draw_str_with_bitmap_font(s, &font, scale.max(1.0) as u32, pos, color);
+            }
+            None => {}
+        }
+    }
+
+    /// Draws a string with the given font, with `size` being the height of the text in pixels.
+    ///
+    /// `pos` is the top-left of the first line of text, and each `\n` starts a new line.
+    pub fn draw_str_with_font(
+        &mut self,
+        s: &str,
+        font: &Font,
+        size: f32,
+        pos: Vec2<i32>,
+        color: Color,
+    ) {
+        let pos = pos.map(|e| e as f32);
+        let text = TextLayout::new(size).layout(s, font, pos);
+        self.draw_text(&text, color);
+    }
+
+    /// Draws a string with a bitmap font, with each pixel of the font drawn as a `scale` by `scale` square.
+    ///
+    /// `pos` is the top-left of the first line of text, and each `\n` starts a new line.
+    pub fn draw_str_with_bitmap_font(
+        &mut self,
+        s: &str,
+        font: &BitmapFont,
+        scale: u32,
+        pos: Vec2<i32>,
+        color: Color,
+    ) {
+        let scale = scale as i32;
+        let mut baseline = pos.y + font.ascent * scale;
+        for line in s.split('\n') {
+            let mut pen_x = pos.x;
+            for c in line.chars() {
+                let Some(glyph) = font.glyph(c) else {
+                    continue;
+                };
+                let top_left = Vec2::new(pen_x, baseline) + glyph.offset * scale;
+                for y in 0..glyph.size.y {
+                    for x in 0..glyph.size.x {
+                        if !glyph.pixel(Vec2::new(x, y)) {
+                            continue;
+                        }
+                        let pos = top_left + Vec2::new(x as i32, y as i32) * scale;
+                        if scale == 1 {
+                            self.draw_pixel(pos, color);
+                        } else {
+                            self.fill_rect(&Rect::new(pos.x, pos.y, scale, scale), color);
+                        }
+                    }
+                }
+                pen_x += glyph.advance * scale;
+            }
+            baseline += font.line_height * scale;
+        }
+    }
+
+    /// Draws a string from signed distance fields, which stay smooth at any size,
+    /// with the style's outline, glow and shadow (if any).
+    ///
+    /// `pos` is the top-left of the first line of text, and each `\n` starts a new line.
+    /// The fields of glyphs are generated once, and kept in `cache` to be used at every size.
+    pub fn draw_sdf_text(
+        &mut self,
+        cache: &mut SdfCache,
+        s: &str,
+        font: &Font,
+        size: f32,
+        pos: Vec2<f32>,
+        style: &SdfStyle,
+    ) {
+        sdf::draw_sdf_text(self, cache, s, font, size, pos, style);
+    }
+
+    /// Draws text that has been positioned by a `TextLayout`, all in one color.
+    pub fn draw_text(&mut self, text: &LaidOutText, color: Color) {
+        for glyph in &text.glyphs {
+            self.draw_glyph(glyph, color);
+        }
+    }
+
+    /// Lays out rich text with `layout`, then draws each span in its own color.
+    ///
+    /// `pos` is the top-left of the first line of text.
+    pub fn draw_rich_text(&mut self, spans: &[TextSpan], layout: &TextLayout, pos: Vec2<i32>) {
+        let text = layout.layout_spans(spans, pos.map(|e| e as f32));
+        for (glyph, &color) in text.glyphs.iter().zip(&text.colors) {
+            self.draw_glyph(glyph, color);
+        }
+    }
+
+    /// Rasterizes a single glyph, using the glyph's coverage of each pixel to mix in `color`.
+    ///
+    /// If a glyph cache is set, the glyph is drawn from the cache (snapped to the nearest subpixel step),
+    /// unless it's too big to fit in the cache's atlas.
+    pub fn draw_glyph(&mut self, glyph: &PositionedGlyph, color: Color) {
+        if let Some(cache) = self.glyph_cache.take() {
+            let cached = cache.get_or_insert(glyph);
+            if let Some((cached, origin)) = &cached {
+                cache.glyph_pixels(cached, *origin, |pos, coverage| {
+                    self.draw_pixel_coverage(pos, color, coverage);
+                });
+            }
+            self.glyph_cache = Some(cache);
+            if cached.is_some() {
+                return;
+            }
+        }
+        let Some(bb) = glyph.pixel_bounding_box() else {
+            return;
+        };
+        glyph.draw(|x, y, coverage| {
+            let pos = Vec2::new(bb.min.x + x as i32, bb.min.y + y as i32);
+            self.draw_pixel_coverage(pos, color, coverage);
+        });
     }
 
     pub fn draw_pixels(&mut self, raster: PixBufView, rect: &Rect) {
@@ -340,7 +1109,23 @@ impl<'a> Graphics<'a> {
     }
     pub fn draw_raster_1to1(&mut self, raster: PixBufView) {
         assert_eq!(raster.size, self.size);
-        self.buffer.bytes.clone_from_slice(raster.bytes)
+
+        // an opaque raster drawn over the buffer would completely cover it, so it can just be copied
+        let copy = match self.blend_mode {
+            BlendMode::Replace => true,
+            BlendMode::SourceOver => raster.bytes.chunks_exact(4).all(|pixel| pixel[3] == 255),
+            _ => false,
+        };
+        if copy {
+            self.buffer.bytes.clone_from_slice(raster.bytes);
+            return;
+        }
+        for index in (0..raster.bytes.len()).step_by(4) {
+            // SAFETY: `raster` and `self.buffer` have the same size, so `index` is in bounds for both
+            unsafe {
+                self.draw_pixel_by_index(index, raster.get_pixel_by_index(index));
+            }
+        }
     }
 
     pub fn shade_rect(&mut self, rect: &Rect, color_fn: impl Fn(Vec2<i32>) -> Color) {
@@ -376,21 +1161,976 @@ impl<'a> Graphics<'a> {
         }
     }
 
-    pub fn fill_circle(&mut self, center: Vec2<i32>, radius: i32, color: Color) {
-        let x0 = center.x - radius;
-        let x1 = center.x + radius;
-        let y0 = center.y - radius;
-        let y1 = center.y + radius;
-        let radius_sq = radius * radius;
+    /// Fills every pixel whose center is within `radius` of the center of the `center` pixel.
+    pub fn fill_circle<'p>(&mut self, center: Vec2<i32>, radius: i32, paint: impl Into<Paint<'p>>) {
+        if self.anti_alias {
+            self.fill_circle_aa(center, radius, paint);
+            return;
+        }
+        self.fill_ellipse(center, Vec2::new(radius, radius), paint);
+    }
+
+    /// Fills a circle, blending the pixels along its edge by how much of them the circle covers.
+    pub fn fill_circle_aa<'p>(
+        &mut self,
+        center: Vec2<i32>,
+        radius: i32,
+        paint: impl Into<Paint<'p>>,
+    ) {
+        let paint = paint.into();
+        let radius_f = radius as f32;
 
-        for x in x0..x1 {
-            for y in y0..y1 {
+        for x in (center.x - radius - 1)..=(center.x + radius + 1) {
+            for y in (center.y - radius - 1)..=(center.y + radius + 1) {
                 let line = Vec2::new(x, y) - center;
-                let dist_sq = line.x * line.x + line.y * line.y;
-                if dist_sq <= radius_sq {
-                    self.draw_pixel(Vec2::new(x, y), color);
+                let dist = ((line.x * line.x + line.y * line.y) as f32).sqrt();
+                let coverage = radius_f + 0.5 - dist;
+                if coverage > 0.0 {
+                    self.paint_pixel_coverage(Vec2::new(x, y), &paint, coverage);
+                }
+            }
+        }
+    }
+
+    /// Draws the outline of a circle, using the midpoint circle algorithm (or the current stroke, if it isn't plain).
+    pub fn draw_circle(&mut self, center: Vec2<i32>, radius: i32, color: Color) {
+        self.draw_ellipse(center, Vec2::new(radius, radius), color);
+    }
+
+    /// Draws the outline of an ellipse, using the midpoint ellipse algorithm (or the current stroke, if it isn't plain).
+    pub fn draw_ellipse(&mut self, center: Vec2<i32>, radii: Vec2<i32>, color: Color) {
+        if !self.stroke.is_plain() {
+            let mut path = Path::new();
+            path.ellipse(center.map(|e| e as f32 + 0.5), radii.map(|e| e as f32));
+            let stroke = self.stroke.clone();
+            self.stroke_path(&path, &stroke, color);
+            return;
+        }
+        if self.anti_alias {
+            self.shade_ellipse_outline(center, radii, |_| true, color);
+            return;
+        }
+        ellipse_points(radii, |offset| self.draw_pixel(center + offset, color));
+    }
+
+    /// Fills every pixel whose center is inside the ellipse with the given radii.
+    pub fn fill_ellipse<'p>(
+        &mut self,
+        center: Vec2<i32>,
+        radii: Vec2<i32>,
+        paint: impl Into<Paint<'p>>,
+    ) {
+        let paint = paint.into();
+        let Vec2 { x: rx, y: ry } = radii;
+        if rx < 0 || ry < 0 {
+            return;
+        }
+        if self.anti_alias {
+            let (rx, ry) = (rx as f32, ry as f32);
+            self.shade_ellipse_bounds(
+                center,
+                radii,
+                |offset| (0.5 - ellipse_dist(offset, rx, ry)).clamp(0.0, 1.0),
+                &paint,
+            );
+            return;
+        }
+        for dy in -ry..=ry {
+            // the widest dx where (dx / rx)^2 + (dy / ry)^2 <= 1
+            let half_width = if ry == 0 {
+                rx
+            } else {
+                let (dy, rx, ry) = (dy as i64, rx as i64, ry as i64);
+                isqrt((rx * rx * (ry * ry - dy * dy)) / (ry * ry)) as i32
+            };
+            let y = center.y + dy;
+            self.paint_row(y, center.x - half_width, center.x + half_width, &paint);
+        }
+    }
+
+    /// Draws part of the outline of a circle, from `start_angle` to `end_angle` (in radians).
+    ///
+    /// Angles start from the right, and go clockwise (since y points down).
+    /// If `end_angle` is less than `start_angle`, the arc goes anti-clockwise.
+    pub fn draw_arc(
+        &mut self,
+        center: Vec2<i32>,
+        radius: i32,
+        start_angle: f32,
+        end_angle: f32,
+        color: Color,
+    ) {
+        let sweep = end_angle - start_angle;
+        if !self.stroke.is_plain() {
+            let mut path = Path::new();
+            let center = center.map(|e| e as f32 + 0.5);
+            path.arc(center, radius as f32, start_angle, sweep.clamp(-TAU, TAU));
+            let stroke = self.stroke.clone();
+            self.stroke_path(&path, &stroke, color);
+            return;
+        }
+        let in_arc = |offset: Vec2<i32>| angle_in_sweep(offset, start_angle, sweep);
+        if self.anti_alias {
+            self.shade_ellipse_outline(center, Vec2::new(radius, radius), in_arc, color);
+            return;
+        }
+        ellipse_points(Vec2::new(radius, radius), |offset| {
+            if in_arc(offset) {
+                self.draw_pixel(center + offset, color);
+            }
+        });
+    }
+
+    /// Fills a slice of a circle, from `start_angle` to `end_angle` (in radians), like a piece of a pie chart.
+    ///
+    /// Angles start from the right, and go clockwise (since y points down).
+    pub fn fill_pie<'p>(
+        &mut self,
+        center: Vec2<i32>,
+        radius: i32,
+        start_angle: f32,
+        end_angle: f32,
+        paint: impl Into<Paint<'p>>,
+    ) {
+        let sweep = end_angle - start_angle;
+        if radius < 0 {
+            return;
+        }
+        if sweep.abs() >= TAU {
+            self.fill_circle(center, radius, paint);
+            return;
+        }
+        if self.anti_alias {
+            // the pie's edges from the center are at any angle, so its shape is filled as a path
+            let c = center.map(|e| e as f32 + 0.5);
+            let mut path = Path::new();
+            path.move_to(c);
+            path.arc(c, radius as f32 + 0.5, start_angle, sweep);
+            path.close();
+            self.fill_path(&path, FillRule::NonZero, paint);
+            return;
+        }
+        let paint = paint.into();
+        let radius_sq = radius * radius;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let offset = Vec2::new(dx, dy);
+                if dx * dx + dy * dy <= radius_sq && angle_in_sweep(offset, start_angle, sweep) {
+                    let pos = center + offset;
+                    self.draw_pixel(pos, paint.color_at_pixel(pos));
+                }
+            }
+        }
+    }
+
+    /// Draws the anti-aliased outline of an ellipse, for the pixels where `include` is true.
+    fn shade_ellipse_outline(
+        &mut self,
+        center: Vec2<i32>,
+        radii: Vec2<i32>,
+        include: impl Fn(Vec2<i32>) -> bool,
+        color: Color,
+    ) {
+        let (rx, ry) = (radii.x as f32, radii.y as f32);
+        self.shade_ellipse_bounds(
+            center,
+            radii,
+            |offset| {
+                if !include(offset) {
+                    return 0.0;
+                }
+                (1.0 - ellipse_dist(offset, rx, ry).abs()).clamp(0.0, 1.0)
+            },
+            &Paint::Solid(color),
+        );
+    }
+
+    /// Draws every pixel in (and just around) an ellipse's bounds, with the coverage given for its offset from the center.
+    fn shade_ellipse_bounds(
+        &mut self,
+        center: Vec2<i32>,
+        radii: Vec2<i32>,
+        coverage: impl Fn(Vec2<i32>) -> f32,
+        paint: &Paint,
+    ) {
+        for dy in -radii.y - 1..=radii.y + 1 {
+            for dx in -radii.x - 1..=radii.x + 1 {
+                let offset = Vec2::new(dx, dy);
+                let c = coverage(offset);
+                if c > 0.0 {
+                    self.paint_pixel_coverage(center + offset, paint, c);
+                }
+            }
+        }
+    }
+}
+
+/// Calls `f` with the offset from the center of every pixel on the outline of an ellipse, each only once,
+/// using the midpoint ellipse algorithm (which is the midpoint circle algorithm when the radii are equal).
+fn ellipse_points(radii: Vec2<i32>, mut f: impl FnMut(Vec2<i32>)) {
+    let (a, b) = (radii.x as i64, radii.y as i64);
+    if a < 0 || b < 0 {
+        return;
+    }
+    if a == 0 || b == 0 {
+        // a flat ellipse is a line
+        for dy in -b..=b {
+            for dx in -a..=a {
+                f(Vec2::new(dx as i32, dy as i32));
+            }
+        }
+        return;
+    }
+    let mut plot4 = |x: i64, y: i64| {
+        let (x, y) = (x as i32, y as i32);
+        f(Vec2::new(x, y));
+        if x != 0 {
+            f(Vec2::new(-x, y));
+        }
+        if y != 0 {
+            f(Vec2::new(x, -y));
+            if x != 0 {
+                f(Vec2::new(-x, -y));
+            }
+        }
+    };
+    let (a2, b2) = (a * a, b * b);
+
+    // region 1, where the outline is closer to horizontal: step x every time, and y sometimes
+    let (mut x, mut y) = (0, b);
+    // 4 times the decision value, to keep it whole
+    let mut d = 4 * b2 - 4 * a2 * b + a2;
+    while b2 * x <= a2 * y {
+        plot4(x, y);
+        if d >= 0 {
+            y -= 1;
+            d -= 8 * a2 * y;
+        }
+        x += 1;
+        d += 4 * b2 * (2 * x + 1);
+    }
+
+    // region 2, where the outline is closer to vertical: step y every time, and x sometimes
+    let mut d = b2 * (2 * x + 1) * (2 * x + 1) + 4 * a2 * (y - 1) * (y - 1) - 4 * a2 * b2;
+    while y >= 0 {
+        plot4(x, y);
+        if d <= 0 {
+            x += 1;
+            d += 8 * b2 * x;
+        }
+        y -= 1;
+        d += 4 * a2 * (1 - 2 * y);
+    }
+}
+
+/// The approximate distance (in pixels) of a point from the outline of an ellipse, negative inside it.
+fn ellipse_dist(offset: Vec2<i32>, rx: f32, ry: f32) -> f32 {
+    let (x, y) = (offset.x as f32, offset.y as f32);
+    if rx <= 0.0 || ry <= 0.0 {
+        // a flat ellipse is a line, from -r to r
+        let (along, r, across) = if rx <= 0.0 { (y, ry, x) } else { (x, rx, y) };
+        return (along.abs() - r).max(0.0).max(across.abs()) - 0.5;
+    }
+    let q = ((x / rx).powi(2) + (y / ry).powi(2)).sqrt();
+    if q == 0.0 {
+        return -rx.min(ry);
+    }
+    // divided by the gradient of q, for the distance in pixels
+    let grad = ((x / (rx * rx)).powi(2) + (y / (ry * ry)).powi(2)).sqrt() / q;
+    (q - 1.0) / grad
+}
+
+/// The distance of a point from the outline of a rectangle with rounded corners, negative inside it.
+///
+/// The rectangle goes from `min` to `max`, and its radii should already fit it.
+fn round_rect_dist(p: Vec2<f32>, min: Vec2<i32>, max: Vec2<i32>, radii: CornerRadii) -> f32 {
+    let (min, max) = (min.map(|e| e as f32), max.map(|e| e as f32));
+    let center = (min + max) * 0.5;
+    let half_size = (max - min) * 0.5;
+    let p = p - center;
+    let radius = match (p.x < 0.0, p.y < 0.0) {
+        (true, true) => radii.tl,
+        (false, true) => radii.tr,
+        (false, false) => radii.br,
+        (true, false) => radii.bl,
+    } as f32;
+
+    // the offset from the inner rectangle, which the corner circles are centered on the corners of
+    let q = Vec2::new(p.x.abs(), p.y.abs()) - half_size + radius;
+    let outside = Vec2::new(q.x.max(0.0), q.y.max(0.0));
+    let outside_len = (outside.x * outside.x + outside.y * outside.y).sqrt();
+    q.x.max(q.y).min(0.0) + outside_len - radius
+}
+
+/// `radii` as floats, in the order `Path::round_rect` takes them.
+fn round_rect_radii_f32(radii: CornerRadii) -> [f32; 4] {
+    [radii.tl, radii.tr, radii.br, radii.bl].map(|r| r as f32)
+}
+
+/// If the angle of `offset` is within the arc that starts at `start` and sweeps by `sweep` (both in radians).
+fn angle_in_sweep(offset: Vec2<i32>, start: f32, sweep: f32) -> bool {
+    if sweep.abs() >= TAU || offset == Vec2::new(0, 0) {
+        return true;
+    }
+    let angle = (offset.y as f32).atan2(offset.x as f32);
+    let (start, sweep) = if sweep < 0.0 {
+        (start + sweep, -sweep)
+    } else {
+        (start, sweep)
+    };
+    (angle - start).rem_euclid(TAU) <= sweep
+}
+
+/// The square root of `n`, rounded down.
+fn isqrt(n: i64) -> i64 {
+    if n <= 0 {
+        return 0;
+    }
+    let mut r = (n as f64).sqrt() as i64;
+    while r * r > n {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    /// Draws with additive blending of a tiny color, so the red channel counts how many times each pixel is drawn.
+    fn count_draws(size: u32, draw: impl FnOnce(&mut Graphics)) -> Vec<Vec<u8>> {
+        let mut image = Image::empty(Vec2::new(size, size));
+        {
+            let mut g = image.create_graphics();
+            g.fill(Color::BLACK);
+            g.set_blend_mode(BlendMode::Add);
+            draw(&mut g);
+        }
+        image
+            .bytes
+            .chunks(size as usize * 4)
+            .map(|row| row.chunks(4).map(|p| p[0]).collect())
+            .collect()
+    }
+
+    fn drawn(counts: &[Vec<u8>]) -> Vec<Vec2<i32>> {
+        let mut points = Vec::new();
+        for (y, row) in counts.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                assert!(count <= 1, "({x}, {y}) drawn {count} times");
+                if count == 1 {
+                    points.push(Vec2::new(x as i32, y as i32));
+                }
+            }
+        }
+        points
+    }
+
+    const ONE: Color = Color::rgb(1, 1, 1);
+
+    #[test]
+    fn blending_over() {
+        let red = Color::rgba(255, 0, 0, 128);
+        assert_eq!(red.blend_over(Color::BLUE), Color::rgb(128, 0, 127));
+        assert_eq!(
+            red.blend_over(Color::rgba(0, 0, 255, 128)),
+            Color::rgba(170, 0, 85, 192)
+        );
+        // opaque colors cover everything, and transparent ones nothing
+        assert_eq!(Color::GREEN.blend_over(red), Color::GREEN);
+        assert_eq!(Color::rgba(9, 9, 9, 0).blend_over(red), red);
+        assert_eq!(red.blend_over(Color::rgba(7, 7, 7, 0)), red);
+        // the alpha adds up the same way whatever the colors are
+        for a in [1, 64, 200, 254] {
+            for b in [1, 100, 255] {
+                let out = Color::rgba(10, 20, 30, a).blend_over(Color::rgba(200, 100, 0, b));
+                let expected = a as f32 + b as f32 * (1.0 - a as f32 / 255.0);
+                assert!((out.a as f32 - expected).abs() <= 0.5, "{a} {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn scaling_alpha() {
+        let color = Color::rgba(1, 2, 3, 200);
+        assert_eq!(color.scale_alpha(0.5), Color::rgba(1, 2, 3, 100));
+        assert_eq!(color.scale_alpha(0.0), Color::rgba(1, 2, 3, 0));
+        // the factor is clamped, so alpha can't overflow
+        assert_eq!(color.scale_alpha(2.0), color);
+        assert_eq!(color.scale_alpha(-1.0).a, 0);
+    }
+
+    #[test]
+    fn fills_reach_the_right_and_bottom_edges() {
+        let fill = |size: Vec2<u32>, draw: &dyn Fn(&mut Graphics)| {
+            let mut image = Image::empty(size);
+            draw(&mut image.create_graphics());
+            (0..size.x * size.y)
+                .filter(|i| image.bytes[*i as usize * 4 + 3] != 0)
+                .map(|i| Vec2::new((i % size.x) as i32, (i / size.x) as i32))
+                .collect::<Vec<_>>()
+        };
+        // a tall image, so columns past the width are still in the image
+        let col = fill(Vec2::new(4, 8), &|g| g.fill_col(1, 5, 20, Color::WHITE));
+        assert_eq!(col, (5..8).map(|y| Vec2::new(1, y)).collect::<Vec<_>>());
+        let col = fill(Vec2::new(4, 8), &|g| g.fill_col(3, -2, 7, Color::WHITE));
+        assert_eq!(col, (0..8).map(|y| Vec2::new(3, y)).collect::<Vec<_>>());
+        // a wide image, so rows past the height are still in the image
+        let row = fill(Vec2::new(8, 4), &|g| g.fill_row(3, 5, 20, Color::WHITE));
+        assert_eq!(row, (5..8).map(|x| Vec2::new(x, 3)).collect::<Vec<_>>());
+        let row = fill(Vec2::new(8, 4), &|g| g.fill_row(0, -3, 7, Color::WHITE));
+        assert_eq!(row, (0..8).map(|x| Vec2::new(x, 0)).collect::<Vec<_>>());
+        // nothing is drawn outside the image
+        assert!(fill(Vec2::new(4, 8), &|g| g.fill_col(4, 0, 7, Color::WHITE)).is_empty());
+        assert!(fill(Vec2::new(8, 4), &|g| g.fill_row(4, 0, 7, Color::WHITE)).is_empty());
+        let rect = fill(Vec2::new(6, 5), &|g| {
+            g.fill_rect(&Rect::new(3, 2, 10, 10), Color::WHITE)
+        });
+        assert_eq!(rect.len(), 3 * 3);
+        assert_eq!(rect.last(), Some(&Vec2::new(5, 4)));
+    }
+
+    const MENLO: &[u8] = include_bytes!("../examples/menlo-regular.ttf");
+
+    #[test]
+    fn text_is_drawn_at_its_position() {
+        let font = Font::try_from_bytes(MENLO).unwrap();
+        let lit = |pos: Vec2<i32>| {
+            let mut image = Image::empty(Vec2::new(40, 30));
+            {
+                let mut g = image.create_graphics();
+                g.set_font(font.clone());
+                g.set_font_size(16.0);
+                g.draw_str("H", pos, Color::WHITE);
+            }
+            let mut points = Vec::new();
+            for (i, pixel) in image.bytes.chunks(4).enumerate() {
+                if pixel[3] != 0 {
+                    points.push(Vec2::new(i as i32 % 40, i as i32 / 40));
+                }
+            }
+            points
+        };
+
+        let at_origin = lit(Vec2::new(0, 0));
+        let min = Vec2::new(
+            at_origin.iter().map(|p| p.x).min().unwrap(),
+            at_origin.iter().map(|p| p.y).min().unwrap(),
+        );
+        let max_y = at_origin.iter().map(|p| p.y).max().unwrap();
+        // the H's side bearing is small, and it sits on the baseline, one ascent below the top
+        let ascent = font.v_metrics(rusttype::Scale::uniform(16.0)).ascent;
+        assert!(min.x >= 0 && min.x <= 2, "{min:?}");
+        assert!(
+            min.y > 0 && max_y as f32 <= ascent && max_y as f32 >= ascent - 1.5,
+            "{max_y}"
+        );
+
+        // moving the position moves the text by exactly as much
+        let offset = Vec2::new(9, 7);
+        let moved: Vec<_> = at_origin.iter().map(|p| *p + offset).collect();
+        assert_eq!(lit(offset), moved);
+    }
+
+    #[test]
+    fn leading_whitespace_indents_text() {
+        let font = Font::try_from_bytes(MENLO).unwrap();
+        let left = |s: &str| {
+            let mut image = Image::empty(Vec2::new(60, 30));
+            {
+                let mut g = image.create_graphics();
+                g.set_font(font.clone());
+                g.set_font_size(16.0);
+                g.draw_str(s, Vec2::new(0, 0), Color::WHITE);
+            }
+            (image.bytes.chunks(4).enumerate())
+                .filter(|(_, pixel)| pixel[3] != 0)
+                .map(|(i, _)| i as f32 % 60.0)
+                .fold(f32::INFINITY, f32::min)
+        };
+        let space = font
+            .glyph(' ')
+            .scaled(rusttype::Scale::uniform(16.0))
+            .h_metrics()
+            .advance_width;
+        let indent = left("  a") - left("a");
+        assert!((indent - 2.0 * space).abs() <= 1.0, "{indent} {space}");
+    }
+
+    #[test]
+    fn fill_circle_includes_last_row_and_column() {
+        let center = Vec2::new(10, 10);
+        let points = drawn(&count_draws(21, |g| g.fill_circle(center, 4, ONE)));
+        let expected: Vec<Vec2<i32>> = (0..21)
+            .flat_map(|y| (0..21).map(move |x| Vec2::new(x, y)))
+            .filter(|p| {
+                let d = *p - center;
+                d.x * d.x + d.y * d.y <= 16
+            })
+            .collect();
+        assert_eq!(points, expected);
+        assert!(points.contains(&Vec2::new(14, 10)) && points.contains(&Vec2::new(10, 14)));
+    }
+
+    #[test]
+    fn circle_outline() {
+        let center = Vec2::new(10, 10);
+        for radius in [0, 1, 5, 8] {
+            let points = drawn(&count_draws(21, |g| g.draw_circle(center, radius, ONE)));
+            assert!(!points.is_empty());
+            for p in &points {
+                let d = *p - center;
+                let dist = ((d.x * d.x + d.y * d.y) as f32).sqrt();
+                assert!((dist - radius as f32).abs() < 0.75, "{radius}: {p:?}");
+                // the outline is symmetric
+                assert!(points.contains(&(center + Vec2::new(d.y, d.x))));
+                assert!(points.contains(&(center - d)));
+            }
+        }
+    }
+
+    #[test]
+    fn ellipses() {
+        let center = Vec2::new(15, 10);
+        let radii = Vec2::new(12, 5);
+        let outline = drawn(&count_draws(31, |g| g.draw_ellipse(center, radii, ONE)));
+        for p in [
+            Vec2::new(27, 10),
+            Vec2::new(3, 10),
+            Vec2::new(15, 5),
+            Vec2::new(15, 15),
+        ] {
+            assert!(outline.contains(&p), "{p:?}");
+        }
+        for p in &outline {
+            let d = *p - center;
+            let q = (d.x as f32 / 12.0).powi(2) + (d.y as f32 / 5.0).powi(2);
+            assert!((q.sqrt() - 1.0).abs() < 0.2, "{p:?}");
+        }
+
+        let filled = drawn(&count_draws(31, |g| g.fill_ellipse(center, radii, ONE)));
+        assert!(filled.contains(&Vec2::new(27, 10)) && !filled.contains(&Vec2::new(28, 10)));
+        assert!(filled.contains(&Vec2::new(15, 15)) && !filled.contains(&Vec2::new(15, 16)));
+        // the outline is along the edge of the filled ellipse
+        for p in &outline {
+            let near = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)];
+            assert!(near
+                .iter()
+                .any(|&(x, y)| filled.contains(&(*p + Vec2::new(x, y)))));
+        }
+    }
+
+    #[test]
+    fn arcs_and_pies() {
+        let center = Vec2::new(10, 10);
+        // the bottom-right quarter, since y points down
+        let arc = drawn(&count_draws(21, |g| {
+            g.draw_arc(center, 8, 0.0, FRAC_PI_2, ONE)
+        }));
+        assert!(arc.contains(&Vec2::new(18, 10)) && arc.contains(&Vec2::new(10, 18)));
+        assert!(arc.iter().all(|p| p.x >= 10 && p.y >= 10));
+        // the same arc, swept the other way
+        let reversed = drawn(&count_draws(21, |g| {
+            g.draw_arc(center, 8, FRAC_PI_2, 0.0, ONE)
+        }));
+        assert_eq!(arc, reversed);
+
+        let top_half = drawn(&count_draws(21, |g| {
+            g.fill_pie(center, 8, PI, 2.0 * PI, ONE)
+        }));
+        assert!(top_half.iter().all(|p| p.y <= 10));
+        assert!(top_half.contains(&Vec2::new(10, 2)) && top_half.contains(&center));
+        let circle = drawn(&count_draws(21, |g| g.fill_circle(center, 8, ONE)));
+        let bottom_half = drawn(&count_draws(21, |g| g.fill_pie(center, 8, 0.0, PI, ONE)));
+        // the halves only overlap along the middle row
+        let middle_row = circle.iter().filter(|p| p.y == 10).count();
+        assert_eq!(
+            top_half.len() + bottom_half.len(),
+            circle.len() + middle_row
+        );
+    }
+
+    #[test]
+    fn round_rects_without_radii_are_rects() {
+        let rect = Rect::new(3, 4, 12, 9);
+        let fill = count_draws(21, |g| g.fill_round_rect(&rect, 0, ONE));
+        assert_eq!(fill, count_draws(21, |g| g.fill_rect(&rect, ONE)));
+        let outline = count_draws(21, |g| g.draw_round_rect(&rect, 0, ONE));
+        assert_eq!(outline, count_draws(21, |g| g.draw_rect(&rect, ONE)));
+    }
+
+    #[test]
+    fn round_rect_corners() {
+        let rect = Rect::new(2, 2, 16, 12);
+        let radii = CornerRadii::new(6, 0, 3, 0);
+        let filled = drawn(&count_draws(21, |g| g.fill_round_rect(&rect, radii, ONE)));
+        // the square corners are filled, and the rounded ones aren't
+        assert!(filled.contains(&Vec2::new(17, 2)) && filled.contains(&Vec2::new(2, 13)));
+        assert!(!filled.contains(&Vec2::new(2, 2)) && !filled.contains(&Vec2::new(17, 13)));
+        assert!(filled.contains(&Vec2::new(8, 2)) && filled.contains(&Vec2::new(2, 8)));
+        assert!(!filled.contains(&Vec2::new(3, 3)) && filled.contains(&Vec2::new(4, 4)));
+
+        let outline = drawn(&count_draws(21, |g| g.draw_round_rect(&rect, radii, ONE)));
+        assert!(outline.contains(&Vec2::new(18, 2)) && outline.contains(&Vec2::new(2, 14)));
+        assert!(outline.contains(&Vec2::new(8, 2)) && outline.contains(&Vec2::new(2, 8)));
+        assert!(!outline.contains(&Vec2::new(2, 2)) && !outline.contains(&Vec2::new(18, 14)));
+        // the outline is connected, with each pixel touching exactly two others
+        for p in &outline {
+            let neighbours = outline
+                .iter()
+                .filter(|o| *o != p && (o.x - p.x).abs() <= 1 && (o.y - p.y).abs() <= 1)
+                .count();
+            assert!(neighbours >= 2, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn round_rect_radii_are_clamped() {
+        let radii = CornerRadii::new(10, 10, -2, 4).clamped(Vec2::new(10, 30));
+        assert_eq!(radii, CornerRadii::new(5, 5, 0, 2));
+        // a fully rounded square is a circle, drawn without overlapping pixels
+        let rect = Rect::new(2, 2, 10, 10);
+        let circle = drawn(&count_draws(15, |g| g.draw_round_rect(&rect, 20, ONE)));
+        let expected = drawn(&count_draws(15, |g| g.draw_circle(Vec2::new(7, 7), 5, ONE)));
+        assert_eq!(circle, expected);
+    }
+
+    #[test]
+    fn anti_aliased_round_rect() {
+        let mut image = Image::empty(Vec2::new(20, 20));
+        {
+            let mut g = image.create_graphics();
+            g.set_anti_alias(true);
+            g.fill_round_rect(&Rect::new(2, 2, 16, 16), 5, Color::WHITE);
+        }
+        let alpha = |x: usize, y: usize| image.bytes[(y * 20 + x) * 4 + 3];
+        // straight edges are exact, and the corners are blended
+        assert_eq!(
+            (alpha(10, 2), alpha(10, 1), alpha(17, 10), alpha(18, 10)),
+            (255, 0, 255, 0)
+        );
+        assert_eq!(alpha(2, 2), 0);
+        assert!(alpha(3, 3) > 0 && alpha(3, 3) < 255);
+        // the shape is symmetric
+        for (x, y) in [(3, 3), (4, 2), (5, 3)] {
+            assert_eq!(alpha(x, y), alpha(19 - x, 19 - y));
+            assert_eq!(alpha(x, y), alpha(y, x));
+        }
+    }
+
+    #[test]
+    fn colored_tris_share_edges_exactly() {
+        // a fan of triangles around a center, with edges at many angles
+        let center = Vec2::new(10, 9);
+        let rim = [
+            (2, 1),
+            (11, 0),
+            (19, 3),
+            (18, 12),
+            (20, 20),
+            (9, 17),
+            (0, 19),
+            (1, 8),
+        ];
+        let counts = count_draws(21, |g| {
+            for i in 0..rim.len() {
+                let (a, b) = (rim[i], rim[(i + 1) % rim.len()]);
+                let tri = Tri(center, Vec2::new(a.0, a.1), Vec2::new(b.0, b.1));
+                g.fill_tri_colored(&tri, [ONE; 3]);
+            }
+        });
+        // `drawn` checks that no pixel is drawn twice
+        let fan = drawn(&counts);
+
+        // every pixel with its center inside the fan's outline is drawn
+        let outline: Vec<Vec2<f32>> = rim
+            .iter()
+            .map(|&(x, y)| Vec2::new(x as f32, y as f32))
+            .collect();
+        let filled = drawn(&count_draws(21, |g| {
+            g.fill_polys_f32(&[outline], FillRule::NonZero, ONE)
+        }));
+        let missing: Vec<_> = filled.iter().filter(|p| !fan.contains(p)).collect();
+        let extra: Vec<_> = fan.iter().filter(|p| !filled.contains(p)).collect();
+        // the polygon rasterizer doesn't follow the top-left rule, so they can differ right on the edges
+        assert!(missing.len() + extra.len() <= 8, "{missing:?} {extra:?}");
+    }
+
+    #[test]
+    fn colored_tri_blends_colors() {
+        let mut image = Image::empty(Vec2::new(20, 20));
+        {
+            let mut g = image.create_graphics();
+            let tri = Tri(Vec2::new(0, 0), Vec2::new(20, 0), Vec2::new(0, 20));
+            g.fill_tri_colored(&tri, [Color::RED, Color::GREEN, Color::BLUE]);
+        }
+        let pixel = |x: usize, y: usize| &image.bytes[(y * 20 + x) * 4..][..4];
+        // near each corner, the corner's color is strongest
+        assert!(pixel(0, 0)[0] > 230);
+        assert!(pixel(18, 0)[1] > 230);
+        assert!(pixel(0, 18)[2] > 230);
+        // the colors always add up to the same brightness, and the alpha is kept
+        for (x, y) in [(5, 5), (9, 2), (2, 12)] {
+            let p = pixel(x, y);
+            let sum = p[0] as i32 + p[1] as i32 + p[2] as i32;
+            assert!((sum - 255).abs() <= 2, "{p:?}");
+            assert_eq!(p[3], 255);
+        }
+        // pixels past the long edge aren't drawn
+        assert_eq!(pixel(10, 10)[3], 0);
+    }
+
+    #[test]
+    fn depth_tested_tris_ignore_draw_order() {
+        // a flat square, and a triangle that tilts through it from in front on the left to behind on the right
+        let square = [
+            [
+                Vec3::new(0.0, 0.0, 0.5),
+                Vec3::new(20.0, 0.0, 0.5),
+                Vec3::new(20.0, 20.0, 0.5),
+            ],
+            [
+                Vec3::new(0.0, 0.0, 0.5),
+                Vec3::new(20.0, 20.0, 0.5),
+                Vec3::new(0.0, 20.0, 0.5),
+            ],
+        ];
+        let tilted = [
+            Vec3::new(0.0, 10.0, 0.0),
+            Vec3::new(20.0, 0.0, 1.0),
+            Vec3::new(20.0, 20.0, 1.0),
+        ];
+        let render = |tilted_first: bool, depth: Option<&mut DepthBuffer>| {
+            let mut image = Image::empty(Vec2::new(20, 20));
+            {
+                let mut g = image.create_graphics();
+                if let Some(depth) = depth {
+                    g.set_depth_buffer(depth);
+                }
+                if tilted_first {
+                    g.fill_tri_depth(tilted, Color::RED);
+                }
+                for tri in square {
+                    g.fill_tri_depth(tri, Color::BLUE);
+                }
+                if !tilted_first {
+                    g.fill_tri_depth(tilted, Color::RED);
                 }
             }
+            image
+        };
+
+        let mut depth = DepthBuffer::new(Vec2::new(20, 20));
+        let image = render(true, Some(&mut depth));
+        depth.clear(DepthBuffer::FAR);
+        assert_eq!(render(false, Some(&mut depth)).bytes, image.bytes);
+        let pixel = |x: usize, y: usize| {
+            Color::from(<[u8; 4]>::try_from(&image.bytes[(y * 20 + x) * 4..][..4]).unwrap())
+        };
+        // the triangle goes behind the square halfway across
+        assert_eq!(pixel(7, 10), Color::RED);
+        assert_eq!(pixel(13, 10), Color::BLUE);
+        assert_eq!(pixel(7, 2), Color::BLUE);
+        // the depths of the nearest pixels are kept
+        assert!((depth.get(Vec2::new(7, 10)).unwrap() - 0.375).abs() < 0.01);
+        assert!((depth.get(Vec2::new(13, 10)).unwrap() - 0.5).abs() < 0.001);
+
+        // without a depth buffer, whatever is drawn last covers the rest
+        assert_eq!(render(false, None).bytes[(10 * 20 + 13) * 4], 255);
+    }
+
+    /// An image where the red channel is 16 times the x of each pixel, and the green is 16 times the y.
+    fn coordinate_texture(size: u32) -> Image {
+        let mut image = Image::empty(Vec2::new(size, size));
+        for (i, pixel) in image.bytes.chunks_mut(4).enumerate() {
+            let (x, y) = (i as u32 % size, i as u32 / size);
+            pixel.copy_from_slice(&[(x * 16) as u8, (y * 16) as u8, 0, 255]);
+        }
+        image
+    }
+
+    /// Draws `texture` over a square from (0, 0) to (`size`, `size`), as two triangles.
+    fn draw_textured_square(
+        size: u32,
+        texture: &Image,
+        mapping: [TexMapping; 2],
+        filter: Filter,
+    ) -> Image {
+        let mut image = Image::empty(Vec2::new(size, size));
+        {
+            let mut g = image.create_graphics();
+            let s = size as i32;
+            let (tl, tr, br, bl) = (
+                Vec2::new(0, 0),
+                Vec2::new(s, 0),
+                Vec2::new(s, s),
+                Vec2::new(0, s),
+            );
+            let (uv_tl, uv_tr, uv_br, uv_bl) = (
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            );
+            let pixels = texture.pixels();
+            g.fill_tri_textured(
+                &Tri(tl, tr, br),
+                [uv_tl, uv_tr, uv_br],
+                pixels,
+                mapping[0],
+                filter,
+            );
+            g.fill_tri_textured(
+                &Tri(tl, br, bl),
+                [uv_tl, uv_br, uv_bl],
+                pixels,
+                mapping[1],
+                filter,
+            );
+        }
+        image
+    }
+
+    #[test]
+    fn textured_tris_copy_the_texture() {
+        let texture = coordinate_texture(12);
+        let affine = [TexMapping::Affine; 2];
+        let copy = draw_textured_square(12, &texture, affine, Filter::Nearest);
+        assert!(copy.bytes == texture.bytes);
+
+        // with every point at the same depth, perspective is the same as affine
+        let flat = [
+            TexMapping::Perspective([2.0; 3]),
+            TexMapping::Perspective([2.0; 3]),
+        ];
+        let perspective = draw_textured_square(12, &texture, flat, Filter::Nearest);
+        assert!(perspective.bytes == texture.bytes);
+
+        // bilinear filtering blends between pixels when the texture is scaled up
+        let scaled = draw_textured_square(24, &texture, affine, Filter::Bilinear);
+        let red = |x: usize, y: usize| scaled.bytes[(y * 24 + x) * 4];
+        assert_eq!((red(9, 5), red(10, 5)), (68, 76));
+    }
+
+    #[test]
+    fn perspective_textures() {
+        let texture = coordinate_texture(16);
+        // the right side of the square is 3 times further away than the left
+        let mapping = [
+            TexMapping::Perspective([1.0, 3.0, 3.0]),
+            TexMapping::Perspective([1.0, 3.0, 1.0]),
+        ];
+        let image = draw_textured_square(16, &texture, mapping, Filter::Nearest);
+        let u = |x: usize, y: usize| image.bytes[(y * 16 + x) * 4] / 16;
+        // so the nearer half of the texture takes up 3 quarters of the screen
+        assert!(u(11, 8) <= 8 && u(13, 8) >= 8, "{} {}", u(11, 8), u(13, 8));
+        // and the far end is squashed, so the last column of pixels skips part of the texture
+        assert_eq!((u(0, 8), u(15, 8)), (0, 14));
+        // each column of pixels has the same texture coordinates all the way down
+        assert!((0..16).all(|y| u(12, y) == u(12, 0)));
+    }
+
+    /// Draws in white on a transparent image, and returns the alpha of each pixel.
+    fn alphas(size: u32, draw: impl FnOnce(&mut Graphics)) -> Vec<Vec<u8>> {
+        let mut image = Image::empty(Vec2::new(size, size));
+        draw(&mut image.create_graphics());
+        image
+            .bytes
+            .chunks(size as usize * 4)
+            .map(|row| row.chunks(4).map(|p| p[3]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn anti_aliased_lines() {
+        // a diagonal through the pixels covers each of them fully, except the half-covered ends
+        let diagonal = alphas(20, |g| {
+            g.draw_line_aa(&Line(Vec2::new(2, 2), Vec2::new(17, 17)), Color::WHITE)
+        });
+        for (y, row) in diagonal.iter().enumerate() {
+            for (x, &alpha) in row.iter().enumerate() {
+                let expected = match x {
+                    _ if x != y || !(2..=17).contains(&x) => 0,
+                    2 | 17 => 128,
+                    _ => 255,
+                };
+                assert_eq!(alpha, expected, "({x}, {y})");
+            }
+        }
+
+        // a shallow line is split between two pixels in each column, that add up to one
+        let shallow = alphas(20, |g| {
+            g.draw_line_aa(&Line(Vec2::new(0, 3), Vec2::new(19, 10)), Color::WHITE)
+        });
+        for x in 1..19 {
+            let column: Vec<u32> = shallow.iter().map(|row| row[x] as u32).collect();
+            assert!((column.iter().sum::<u32>() as i32 - 255).abs() <= 1, "{x}");
+            assert!(column.iter().filter(|&&a| a > 0).count() <= 2, "{x}");
+        }
+        // the line is drawn the same way from either end
+        let reversed = alphas(20, |g| {
+            g.draw_line_aa(&Line(Vec2::new(19, 10), Vec2::new(0, 3)), Color::WHITE)
+        });
+        assert_eq!(shallow, reversed);
+    }
+
+    #[test]
+    fn anti_aliased_tris() {
+        let tri = Tri(Vec2::new(0, 0), Vec2::new(20, 0), Vec2::new(0, 20));
+        let image = alphas(20, |g| g.fill_tri_aa(&tri, Color::WHITE));
+        // inside, and along the edges of the image
+        assert_eq!((image[5][5], image[0][5], image[5][0]), (255, 255, 255));
+        // pixels whose centers are on the long edge are half covered, and those past it aren't at all
+        assert_eq!((image[10][9], image[9][10]), (128, 128));
+        assert_eq!((image[10][10], image[12][12]), (0, 0));
+        // the edge is blended over a pixel's width, so a pixel whose center is more than half a pixel inside is covered
+        assert_eq!(image[9][9], 255);
+        // the winding order doesn't matter
+        let reversed = Tri(tri.0, tri.2, tri.1);
+        assert_eq!(
+            alphas(20, |g| g.fill_tri_aa(&reversed, Color::WHITE)),
+            image
+        );
+    }
+
+    #[test]
+    fn anti_aliased_circle_edges() {
+        let image = alphas(21, |g| g.fill_circle_aa(Vec2::new(10, 10), 6, Color::WHITE));
+        // pixels right on the radius are half covered
+        for (x, y) in [(16, 10), (4, 10), (10, 16), (10, 4)] {
+            assert_eq!(image[y][x], 128, "({x}, {y})");
+        }
+        assert_eq!((image[10][15], image[10][17]), (255, 0));
+        // along the diagonal, the edge pixel is covered by how far inside it is
+        let dist = (32.0f32).sqrt();
+        let expected = ((6.5 - dist) * 255.0).round() as u8;
+        assert_eq!(image[14][14], expected);
+        assert_eq!(image[15][15], 0);
+        // the circle is symmetric
+        for (y, row) in image.iter().enumerate() {
+            for (x, &alpha) in row.iter().enumerate() {
+                assert_eq!(alpha, image[x][y]);
+                assert_eq!(alpha, image[20 - y][x]);
+            }
+        }
+    }
+
+    #[test]
+    fn anti_aliased_outline_is_smooth() {
+        let mut image = Image::empty(Vec2::new(21, 21));
+        {
+            let mut g = image.create_graphics();
+            g.set_anti_alias(true);
+            g.draw_circle(Vec2::new(10, 10), 7, Color::WHITE);
         }
+        let alphas: Vec<u8> = image.bytes.chunks(4).map(|p| p[3]).collect();
+        assert!(alphas.iter().any(|&a| a > 0 && a < 255));
+        // the center and corners are untouched
+        assert_eq!(alphas[10 * 21 + 10], 0);
+        assert_eq!(alphas[0], 0);
     }
 }
@@ -1,16 +1,40 @@
+pub mod blend;
+pub mod depth;
 pub mod fonts;
+pub mod glyph_cache;
 pub mod graphics;
 pub mod input;
+pub mod matrix;
+pub mod mesh;
+pub mod paint;
+pub mod path;
 pub mod pixel_buf;
+pub mod poly_rasterizer;
+pub mod render3d;
+pub mod sdf;
 pub mod shapes;
+pub mod stroke;
 pub mod tri_rasterizer;
 pub mod triangulation;
 pub mod vectors;
 pub mod window;
 
+pub use blend::BlendMode;
+pub use depth::{DepthBuffer, DepthCompare};
+pub use fonts::{BitmapFont, FontChain, TextAlign, TextFont, TextLayout, TextSpan};
+pub use glyph_cache::GlyphCache;
 pub use graphics::{Color, Graphics, Image};
 pub use input::{InputEvent, Key, MouseButton};
-pub use shapes::{Line, Poly, Rect, Tri};
+pub use matrix::{Mat2, Mat3, Mat4};
+pub use mesh::{Material, Mesh};
+pub use paint::{ColorStop, Filter, Gradient, Paint, Pattern, Spread, Wrap};
+pub use path::Path;
+pub use poly_rasterizer::FillRule;
+pub use render3d::{CullMode, Renderer3d};
+pub use sdf::{SdfCache, SdfStyle};
+pub use shapes::{CornerRadii, Line, Poly, Rect, Tri};
+pub use stroke::{LineCap, LineJoin, Stroke};
+pub use tri_rasterizer::TexMapping;
 pub use vectors::{Vec2, Vec3, Vec4, VecMath};
 pub use window::{run_canvas_app, CanvasApp, CanvasConfig, Window};
 
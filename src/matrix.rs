@@ -0,0 +1,544 @@
+use crate::vectors::{ToFromF64, Vec2, Vec3, Vec4, VecMath};
+
+macro_rules! impl_math_matrix_op {
+	($name:ident,$op_trait:ident,$op_fn:ident,$op_tt:tt) => {
+		impl<T: std::ops::$op_trait<Output = T> + Copy> std::ops::$op_trait<Self> for $name<T> {
+			type Output = Self;
+			fn $op_fn(self, rhs:Self) -> Self::Output {
+				Self {
+					rows: std::array::from_fn(|r| std::array::from_fn(|c| self.rows[r][c] $op_tt rhs.rows[r][c])),
+				}
+			}
+		}
+		impl_math_matrix_op!(scalar,$name,$op_trait,$op_fn,$op_tt);
+	};
+	(scalar,$name:ident,$op_trait:ident,$op_fn:ident,$op_tt:tt) => {
+		impl<T: std::ops::$op_trait<Output = T> + Copy> std::ops::$op_trait<T> for $name<T> {
+			type Output = Self;
+			fn $op_fn(self, rhs:T) -> Self::Output {
+				Self {
+					rows: self.rows.map(|row| row.map(|e| e $op_tt rhs)),
+				}
+			}
+		}
+	};
+	(asn,$name:ident,$op_trait:ident,$op_fn:ident,$op_tt:tt) => {
+		impl<T: std::ops::$op_trait + Copy> std::ops::$op_trait<Self> for $name<T> {
+			fn $op_fn(&mut self, rhs:Self) {
+				for (row, rhs) in self.rows.iter_mut().zip(rhs.rows) {
+					for (e, rhs) in row.iter_mut().zip(rhs) { *e $op_tt rhs; }
+				}
+			}
+		}
+		impl_math_matrix_op!(asn scalar,$name,$op_trait,$op_fn,$op_tt);
+	};
+	(asn scalar,$name:ident,$op_trait:ident,$op_fn:ident,$op_tt:tt) => {
+		impl<T: std::ops::$op_trait + Copy> std::ops::$op_trait<T> for $name<T> {
+			fn $op_fn(&mut self, rhs:T) {
+				for e in self.rows.iter_mut().flatten() { *e $op_tt rhs; }
+			}
+		}
+	};
+}
+macro_rules! define_math_matrix {
+	($name:ident[$size:literal],$vec:ident) => {
+		/// A square matrix, stored as rows.
+		///
+		/// Vectors are treated as columns, so `a * b` transforms by `b` first then `a`.
+		/// Like `VecMath`, the methods that aren't element by element are calculated with `f64`s.
+		#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+		pub struct $name<T> {
+			pub rows: [[T; $size]; $size],
+		}
+		impl<T: Copy> $name<T> {
+			#[inline(always)] pub const fn new(rows: [[T; $size]; $size]) -> Self { Self { rows } }
+			#[inline(always)] pub fn from_rows(rows: [$vec<T>; $size]) -> Self { Self::new(rows.map(|row| row.to_arr())) }
+			#[inline(always)] pub fn from_cols(cols: [$vec<T>; $size]) -> Self { Self::from_rows(cols).transpose() }
+
+			#[inline(always)] pub fn row(&self, i: usize) -> $vec<T> { $vec::from(self.rows[i]) }
+			#[inline(always)] pub fn col(&self, i: usize) -> $vec<T> { $vec::from(self.rows.map(|row| row[i])) }
+
+			/// This matrix flipped along its diagonal, so its rows become its columns.
+			#[inline(always)] pub fn transpose(self) -> Self {
+				Self::new(std::array::from_fn(|r| std::array::from_fn(|c| self.rows[c][r])))
+			}
+			#[inline(always)] pub fn map<E>(&self, f: impl Fn(T) -> E) -> $name<E> {
+				$name { rows: self.rows.map(|row| row.map(&f)) }
+			}
+		}
+		impl<T: ToFromF64 + Copy> $name<T> {
+			/// The matrix that leaves every vector unchanged, which is also the default.
+			#[inline(always)] pub fn identity() -> Self {
+				Self::new(std::array::from_fn(|r| std::array::from_fn(|c| T::from_f64(if r == c { 1.0 } else { 0.0 }))))
+			}
+			#[inline(always)] fn to_f64(self) -> [[f64; $size]; $size] { self.rows.map(|row| row.map(T::to_f64)) }
+			#[inline(always)] fn from_f64(rows: [[f64; $size]; $size]) -> Self { Self::new(rows.map(|row| row.map(T::from_f64))) }
+		}
+		impl<T: ToFromF64 + Copy> Default for $name<T> {
+			fn default() -> Self { Self::identity() }
+		}
+		impl<T: ToFromF64 + Copy> std::ops::Mul<Self> for $name<T> {
+			type Output = Self;
+			fn mul(self, rhs: Self) -> Self::Output {
+				let (a, b) = (self.to_f64(), rhs.to_f64());
+				Self::from_f64(std::array::from_fn(|r| std::array::from_fn(|c| (0..$size).map(|i| a[r][i] * b[i][c]).sum())))
+			}
+		}
+		impl<T: ToFromF64 + Copy> std::ops::MulAssign<Self> for $name<T> {
+			fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; }
+		}
+		impl<T: ToFromF64 + Copy> std::ops::Mul<$vec<T>> for $name<T> {
+			type Output = $vec<T>;
+			fn mul(self, rhs: $vec<T>) -> Self::Output {
+				let v = rhs.to_arr().map(T::to_f64);
+				$vec::from(self.to_f64().map(|row| T::from_f64((0..$size).map(|i| row[i] * v[i]).sum())))
+			}
+		}
+		impl_math_matrix_op!($name,Add,add,+);
+		impl_math_matrix_op!($name,Sub,sub,-);
+		impl_math_matrix_op!(scalar,$name,Mul,mul,*);
+		impl_math_matrix_op!(scalar,$name,Div,div,/);
+		impl_math_matrix_op!(asn,$name,AddAssign,add_assign,+=);
+		impl_math_matrix_op!(asn,$name,SubAssign,sub_assign,-=);
+		impl_math_matrix_op!(asn scalar,$name,MulAssign,mul_assign,*=);
+		impl_math_matrix_op!(asn scalar,$name,DivAssign,div_assign,/=);
+	};
+}
+
+define_math_matrix!(Mat2[2], Vec2);
+define_math_matrix!(Mat3[3], Vec3);
+define_math_matrix!(Mat4[4], Vec4);
+
+/// 2D transforms, where positive angles are clockwise when y points down (like everywhere else in 2D).
+impl<T: ToFromF64 + Copy> Mat2<T> {
+    #[inline(always)]
+    pub fn det(&self) -> f64 {
+        det2(self.to_f64())
+    }
+    /// The matrix that undoes this one, or `None` if it squashes everything onto a line (so its determinant is 0).
+    pub fn inverse(&self) -> Option<Self> {
+        inverse(self.to_f64(), |m: [[f64; 1]; 1]| m[0][0]).map(Self::from_f64)
+    }
+
+    pub fn scale(scale: Vec2<T>) -> Self {
+        let s = scale.map(T::to_f64);
+        Self::from_f64([[s.x, 0.0], [0.0, s.y]])
+    }
+    /// A rotation by `angle` radians.
+    pub fn rotate(angle: T) -> Self {
+        let (sin, cos) = angle.to_f64().sin_cos();
+        Self::from_f64([[cos, -sin], [sin, cos]])
+    }
+    /// A shear, which moves x by `shear.x` times y, and y by `shear.y` times x.
+    pub fn shear(shear: Vec2<T>) -> Self {
+        let s = shear.map(T::to_f64);
+        Self::from_f64([[1.0, s.x], [s.y, 1.0]])
+    }
+}
+
+/// 2D transforms that can also move points, with positive angles clockwise when y points down.
+impl<T: ToFromF64 + Copy> Mat3<T> {
+    #[inline(always)]
+    pub fn det(&self) -> f64 {
+        det3(self.to_f64())
+    }
+    /// The matrix that undoes this one, or `None` if it squashes everything flat (so its determinant is 0).
+    pub fn inverse(&self) -> Option<Self> {
+        inverse(self.to_f64(), det2).map(Self::from_f64)
+    }
+
+    pub fn translate(offset: Vec2<T>) -> Self {
+        let o = offset.map(T::to_f64);
+        Self::from_f64([[1.0, 0.0, o.x], [0.0, 1.0, o.y], [0.0, 0.0, 1.0]])
+    }
+    pub fn scale(scale: Vec2<T>) -> Self {
+        Self::from_mat2(Mat2::scale(scale))
+    }
+    /// A rotation by `angle` radians, around the origin.
+    pub fn rotate(angle: T) -> Self {
+        Self::from_mat2(Mat2::rotate(angle))
+    }
+    /// A shear, which moves x by `shear.x` times y, and y by `shear.y` times x.
+    pub fn shear(shear: Vec2<T>) -> Self {
+        Self::from_mat2(Mat2::shear(shear))
+    }
+    fn from_mat2(m: Mat2<T>) -> Self {
+        let [[a, b], [c, d]] = m.to_f64();
+        Self::from_f64([[a, b, 0.0], [c, d, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    /// Transforms a point, which has a w of 1 (so it's moved by translations).
+    #[inline(always)]
+    pub fn transform_point(&self, p: Vec2<T>) -> Vec2<T> {
+        let p = *self * Vec3::new(p.x, p.y, T::from_f64(1.0));
+        Vec2::new(p.x, p.y)
+    }
+}
+
+/// 3D transforms and projections, where positive angles are anti-clockwise when looking down an axis at the origin.
+impl<T: ToFromF64 + Copy> Mat4<T> {
+    #[inline(always)]
+    pub fn det(&self) -> f64 {
+        det4(self.to_f64())
+    }
+    /// The matrix that undoes this one, or `None` if it squashes everything flat (so its determinant is 0).
+    pub fn inverse(&self) -> Option<Self> {
+        inverse(self.to_f64(), det3).map(Self::from_f64)
+    }
+
+    pub fn translate(offset: Vec3<T>) -> Self {
+        let o = offset.map(T::to_f64);
+        Self::from_f64([
+            [1.0, 0.0, 0.0, o.x],
+            [0.0, 1.0, 0.0, o.y],
+            [0.0, 0.0, 1.0, o.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    pub fn scale(scale: Vec3<T>) -> Self {
+        let s = scale.map(T::to_f64);
+        Self::from_f64([
+            [s.x, 0.0, 0.0, 0.0],
+            [0.0, s.y, 0.0, 0.0],
+            [0.0, 0.0, s.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A rotation around the x axis, by `angle` radians.
+    pub fn rotate_x(angle: T) -> Self {
+        let (sin, cos) = angle.to_f64().sin_cos();
+        Self::from_f64([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A rotation around the y axis, by `angle` radians.
+    pub fn rotate_y(angle: T) -> Self {
+        let (sin, cos) = angle.to_f64().sin_cos();
+        Self::from_f64([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A rotation around the z axis, by `angle` radians.
+    pub fn rotate_z(angle: T) -> Self {
+        let (sin, cos) = angle.to_f64().sin_cos();
+        Self::from_f64([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A rotation around any axis through the origin, by `angle` radians.
+    pub fn rotate(axis: Vec3<T>, angle: T) -> Self {
+        let Vec3 { x, y, z } = axis.map(T::to_f64).norm_or_zero();
+        let (sin, cos) = angle.to_f64().sin_cos();
+        let t = 1.0 - cos;
+        Self::from_f64([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A shear, which moves x by `x.x` times y and `x.y` times z, y by `y.x` times x and `y.y` times z,
+    /// and z by `z.x` times x and `z.y` times y.
+    pub fn shear(x: Vec2<T>, y: Vec2<T>, z: Vec2<T>) -> Self {
+        let [x, y, z] = [x, y, z].map(|v| v.map(T::to_f64));
+        Self::from_f64([
+            [1.0, x.x, x.y, 0.0],
+            [y.x, 1.0, y.y, 0.0],
+            [z.x, z.y, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// An orthographic projection, for a camera looking down -z, which maps the box between
+    /// `left` and `right`, `bottom` and `top`, and `near` and `far` in front of the camera to -1 to 1 on each axis.
+    pub fn ortho(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Self {
+        let [l, r, b, t, n, f] = [left, right, bottom, top, near, far].map(T::to_f64);
+        Self::from_f64([
+            [2.0 / (r - l), 0.0, 0.0, -(r + l) / (r - l)],
+            [0.0, 2.0 / (t - b), 0.0, -(t + b) / (t - b)],
+            [0.0, 0.0, -2.0 / (f - n), -(f + n) / (f - n)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    /// A perspective projection, for a camera looking down -z with a vertical field of view of `fov_y` radians.
+    ///
+    /// Points between `near` and `far` in front of the camera end up with a z between -1 and 1 after dividing by w.
+    pub fn perspective(fov_y: T, aspect: T, near: T, far: T) -> Self {
+        let [fov_y, aspect, near, far] = [fov_y, aspect, near, far].map(T::to_f64);
+        let f = 1.0 / (fov_y * 0.5).tan();
+        let depth = near - far;
+        Self::from_f64([
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / depth, 2.0 * far * near / depth],
+            [0.0, 0.0, -1.0, 0.0],
+        ])
+    }
+    /// A view matrix for a camera at `eye` looking at `target`, with `up` being roughly upwards.
+    pub fn look_at(eye: Vec3<T>, target: Vec3<T>, up: Vec3<T>) -> Self {
+        let [eye, target, up] = [eye, target, up].map(|v| v.map(T::to_f64));
+        let f = (target - eye).norm_or_zero();
+        let r = f.cross(up).norm_or_zero();
+        let u = r.cross(f);
+        Self::from_f64([
+            [r.x, r.y, r.z, -r.dot(eye)],
+            [u.x, u.y, u.z, -u.dot(eye)],
+            [-f.x, -f.y, -f.z, f.dot(eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Transforms a point, which has a w of 1, keeping the w (which projections change).
+    #[inline(always)]
+    pub fn transform_point(&self, p: Vec3<T>) -> Vec4<T> {
+        *self * Vec4::new(p.x, p.y, p.z, T::from_f64(1.0))
+    }
+}
+
+#[inline(always)]
+fn det2(m: [[f64; 2]; 2]) -> f64 {
+    m[0][0] * m[1][1] - m[0][1] * m[1][0]
+}
+fn det3(m: [[f64; 3]; 3]) -> f64 {
+    (0..3)
+        .map(|c| cofactor_sign(0, c) * m[0][c] * det2(minor(&m, 0, c)))
+        .sum()
+}
+fn det4(m: [[f64; 4]; 4]) -> f64 {
+    (0..4)
+        .map(|c| cofactor_sign(0, c) * m[0][c] * det3(minor(&m, 0, c)))
+        .sum()
+}
+#[inline(always)]
+fn cofactor_sign(row: usize, col: usize) -> f64 {
+    match (row + col) % 2 {
+        0 => 1.0,
+        _ => -1.0,
+    }
+}
+/// The matrix left after removing a row and a column, where `M` is one less than `N`.
+fn minor<const N: usize, const M: usize>(
+    m: &[[f64; N]; N],
+    row: usize,
+    col: usize,
+) -> [[f64; M]; M] {
+    std::array::from_fn(|r| {
+        let r = if r < row { r } else { r + 1 };
+        std::array::from_fn(|c| m[r][if c < col { c } else { c + 1 }])
+    })
+}
+/// The inverse of a matrix, from its adjugate (the transposed cofactors) divided by its determinant,
+/// where `minor_det` is the determinant of a matrix one size smaller.
+fn inverse<const N: usize, const M: usize>(
+    m: [[f64; N]; N],
+    minor_det: impl Fn([[f64; M]; M]) -> f64,
+) -> Option<[[f64; N]; N]> {
+    let cofactors: [[f64; N]; N] = std::array::from_fn(|r| {
+        std::array::from_fn(|c| cofactor_sign(r, c) * minor_det(minor(&m, r, c)))
+    });
+    let det: f64 = (0..N).map(|c| m[0][c] * cofactors[0][c]).sum();
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    Some(std::array::from_fn(|r| {
+        std::array::from_fn(|c| cofactors[c][r] / det)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec4<f32>, b: Vec4<f32>) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+        assert!(
+            close(a.x, b.x) && close(a.y, b.y) && close(a.z, b.z) && close(a.w, b.w),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn transforms_compose_right_to_left() {
+        let p = Vec3::new(1.0, 0.0, 0.0);
+        let m =
+            Mat4::translate(Vec3::new(0.0, 0.0, 5.0)) * Mat4::rotate_z(std::f32::consts::FRAC_PI_2);
+        // rotated onto the y axis, then moved along z
+        assert_near(m.transform_point(p), Vec4::new(0.0, 1.0, 5.0, 1.0));
+        let scaled = Mat4::scale(Vec3::new(2.0, 3.0, 4.0)) * Mat4::identity();
+        assert_near(
+            scaled.transform_point(Vec3::new(1.0, 1.0, 1.0)),
+            Vec4::new(2.0, 3.0, 4.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn camera_matrices() {
+        let view = Mat4::look_at(
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        // the camera is at the origin of view space, looking down -z
+        assert_near(
+            view.transform_point(Vec3::new(0.0, 0.0, 10.0)),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        );
+        assert_near(
+            view.transform_point(Vec3::new(1.0, 2.0, 0.0)),
+            Vec4::new(1.0, 2.0, -10.0, 1.0),
+        );
+
+        let projection = Mat4::perspective(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 100.0);
+        let near = projection.transform_point(Vec3::new(0.0, 0.0, -1.0));
+        let far = projection.transform_point(Vec3::new(0.0, 0.0, -100.0));
+        assert!((near.z / near.w + 1.0).abs() < 1e-4);
+        assert!((far.z / far.w - 1.0).abs() < 1e-4);
+        // at 90 degrees, the top of the view is as high as it is far away
+        let top = projection.transform_point(Vec3::new(0.0, 5.0, -5.0));
+        assert!((top.y / top.w - 1.0).abs() < 1e-4);
+    }
+
+    fn assert_near_mat<const N: usize>(a: [[f32; N]; N], b: [[f32; N]; N]) {
+        let close = a
+            .iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < 1e-4);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn determinants_and_inverses() {
+        let m2 = Mat2::new([[2.0f32, 1.0], [4.0, 3.0]]);
+        assert_eq!(m2.det(), 2.0);
+        assert_near_mat((m2 * m2.inverse().unwrap()).rows, Mat2::identity().rows);
+
+        let m3 = Mat3::translate(Vec2::new(3.0f32, -2.0))
+            * Mat3::rotate(0.7)
+            * Mat3::scale(Vec2::new(2.0, 0.5));
+        assert!((m3.det() - 1.0).abs() < 1e-6);
+        assert_near_mat((m3.inverse().unwrap() * m3).rows, Mat3::identity().rows);
+
+        let m4 = Mat4::new([
+            [1.0f32, 2.0, 0.0, 1.0],
+            [0.0, 1.0, 3.0, 0.0],
+            [2.0, 0.0, 1.0, 4.0],
+            [0.0, 1.0, 0.0, 1.0],
+        ]);
+        assert!((m4.det() - 19.0).abs() < 1e-9);
+        assert_near_mat((m4 * m4.inverse().unwrap()).rows, Mat4::identity().rows);
+        let projection = Mat4::perspective(1.0f32, 1.5, 0.1, 50.0);
+        assert_near_mat(
+            (projection.inverse().unwrap() * projection).rows,
+            Mat4::identity().rows,
+        );
+
+        // squashed flat
+        assert_eq!(Mat2::new([[1.0f32, 2.0], [2.0, 4.0]]).inverse(), None);
+        assert_eq!(Mat4::<f32>::scale(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+        assert_eq!(Mat3::new([[1, 2, 3], [4, 5, 6], [7, 8, 9]]).det(), 0.0);
+    }
+
+    #[test]
+    fn matrix_operators() {
+        let a = Mat2::new([[1, 2], [3, 4]]);
+        let b = Mat2::new([[0, 1], [1, 0]]);
+        assert_eq!(a + b, Mat2::new([[1, 3], [4, 4]]));
+        assert_eq!(a - 1, Mat2::new([[0, 1], [2, 3]]));
+        assert_eq!(a * 2, Mat2::new([[2, 4], [6, 8]]));
+        // multiplying by a matrix multiplies rows by columns, so `b` swaps the columns of `a`
+        assert_eq!(a * b, Mat2::new([[2, 1], [4, 3]]));
+        assert_eq!(a * Vec2::new(1, 1), Vec2::new(3, 7));
+        assert_eq!(a.transpose(), Mat2::from_cols([a.row(0), a.row(1)]));
+        assert_eq!(a.col(1), Vec2::new(2, 4));
+        assert_eq!(Mat2::<i32>::default(), Mat2::new([[1, 0], [0, 1]]));
+
+        let mut c = a;
+        c += b;
+        c -= 1;
+        c *= 3;
+        c /= 3;
+        assert_eq!(c, a + b - 1);
+        c *= Mat2::identity();
+        assert_eq!(c, a + b - 1);
+        assert_eq!(a.map(|e| e as f32 * 0.5).rows, [[0.5, 1.0], [1.5, 2.0]]);
+    }
+
+    #[test]
+    fn transforms_2d() {
+        // clockwise, with y pointing down
+        let rotated = Mat2::rotate(std::f32::consts::FRAC_PI_2) * Vec2::new(1.0, 0.0);
+        assert!((rotated.x - 0.0).abs() < 1e-6 && (rotated.y - 1.0).abs() < 1e-6);
+        assert_eq!(
+            Mat2::shear(Vec2::new(2.0, 0.0)) * Vec2::new(1.0, 1.0),
+            Vec2::new(3.0, 1.0)
+        );
+
+        let m = Mat3::translate(Vec2::new(10.0, 5.0)) * Mat3::scale(Vec2::new(2.0, 3.0));
+        assert_eq!(m.transform_point(Vec2::new(1.0, 1.0)), Vec2::new(12.0, 8.0));
+        // directions, with a w of 0, aren't moved
+        assert_eq!(m * Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn transforms_3d() {
+        let axis = Mat4::rotate(Vec3::new(0.0, 0.0, 2.0), 0.8f32);
+        assert_near_mat(axis.rows, Mat4::rotate_z(0.8).rows);
+        let diagonal = Mat4::rotate(Vec3::new(1.0f32, 1.0, 1.0), std::f32::consts::TAU / 3.0);
+        assert_near(
+            diagonal.transform_point(Vec3::new(1.0, 0.0, 0.0)),
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+        );
+
+        let shear = Mat4::shear(
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 2.0),
+        );
+        assert_near(
+            shear.transform_point(Vec3::new(1.0f32, 1.0, 1.0)),
+            Vec4::new(2.0, 1.0, 3.0, 1.0),
+        );
+
+        let ortho = Mat4::ortho(0.0f32, 200.0, 100.0, 0.0, 1.0, 10.0);
+        assert_near(
+            ortho.transform_point(Vec3::new(0.0, 100.0, -1.0)),
+            Vec4::new(-1.0, -1.0, -1.0, 1.0),
+        );
+        assert_near(
+            ortho.transform_point(Vec3::new(200.0, 0.0, -10.0)),
+            Vec4::new(1.0, 1.0, 1.0, 1.0),
+        );
+
+        // works with other types of numbers too
+        let m = Mat4::<f64>::translate(Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            m.transform_point(Vec3::new(0.0, 0.0, 0.0)),
+            Vec4::new(1.0, 2.0, 3.0, 1.0)
+        );
+    }
+}
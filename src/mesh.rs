@@ -0,0 +1,897 @@
+use crate::graphics::{Color, Graphics};
+use crate::paint::Filter;
+use crate::pixel_buf::PixBufView;
+use crate::render3d::Renderer3d;
+use crate::vectors::{Vec2, Vec3};
+use std::collections::HashMap;
+
+/// A material from an MTL file, which the triangles of a `Mesh` are filled with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// The diffuse color (`Kd`), with its alpha from the dissolve (`d` or `Tr`).
+    pub diffuse: Color,
+    /// The path of the diffuse texture (`map_Kd`), as it's written in the file.
+    pub texture: Option<String>,
+}
+impl Material {
+    /// A plain white material.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: Color::WHITE,
+            texture: None,
+        }
+    }
+
+    /// Parses the materials from the text of an MTL file.
+    pub fn parse_mtl(src: &str) -> Result<Vec<Self>, String> {
+        let mut materials: Vec<Self> = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let line_num = i + 1;
+            let err = |msg: &str| format!("line {line_num}: {msg}");
+            let mut words = strip_comment(line).split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            if keyword == "newmtl" {
+                let name = words.next().ok_or_else(|| err("`newmtl` needs a name"))?;
+                materials.push(Self::new(name));
+                continue;
+            }
+            if !matches!(keyword, "Kd" | "d" | "Tr" | "map_Kd") {
+                continue;
+            }
+            let material = materials
+                .last_mut()
+                .ok_or_else(|| err(&format!("`{keyword}` is before any `newmtl`")))?;
+            if keyword == "map_Kd" {
+                // options can come before the path, which is last
+                let path = words.last().ok_or_else(|| err("`map_Kd` needs a path"))?;
+                material.texture = Some(path.to_string());
+                continue;
+            }
+            let nums = parse_floats(words).ok_or_else(|| err("invalid number"))?;
+            match (keyword, &nums[..]) {
+                ("Kd", [r, g, b]) => {
+                    let [r, g, b] = [r, g, b].map(|&e| unit_to_u8(e));
+                    material.diffuse = Color::rgba(r, g, b, material.diffuse.a);
+                }
+                ("Kd", _) => return Err(err("`Kd` needs 3 numbers")),
+                ("d", [d]) => material.diffuse.a = unit_to_u8(*d),
+                ("Tr", [t]) => material.diffuse.a = unit_to_u8(1.0 - t),
+                _ => return Err(err(&format!("`{keyword}` needs 1 number"))),
+            }
+        }
+        Ok(materials)
+    }
+}
+
+/// A mesh of triangles in 3D, which can be loaded from OBJ or PLY files and drawn with a `Renderer3d`.
+///
+/// Each vertex has a position, and optionally a normal, texture coordinates and a color,
+/// which are each either empty or have one for every vertex.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3<f32>>,
+    pub normals: Vec<Vec3<f32>>,
+    /// The texture coordinates of each vertex, with `(0.0, 0.0)` at the top-left of the texture.
+    pub uvs: Vec<Vec2<f32>>,
+    pub colors: Vec<Color>,
+    /// The indices of the vertices of each triangle.
+    pub tris: Vec<[usize; 3]>,
+    /// The index in `materials` of each triangle's material (if it has one), or empty if there are no materials.
+    pub tri_materials: Vec<Option<usize>>,
+    pub materials: Vec<Material>,
+}
+impl Mesh {
+    /// Loads a mesh from the text of a Wavefront OBJ file, with its materials from the text of its MTL file.
+    ///
+    /// Faces with more than 3 points are split into triangles. Texture coordinates are flipped vertically,
+    /// since OBJ has `(0.0, 0.0)` at the bottom-left of a texture. Without an MTL file, every material is plain white.
+    pub fn from_obj(obj: &str, mtl: Option<&str>) -> Result<Self, String> {
+        let mut mesh = Self::default();
+        if let Some(mtl) = mtl {
+            mesh.materials = Material::parse_mtl(mtl).map_err(|e| format!("MTL {e}"))?;
+        }
+        let (mut positions, mut colors, mut uvs, mut normals) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        // the position, texture coordinates and normal of each vertex, which OBJ indexes separately
+        let mut vertices: Vec<[Option<usize>; 3]> = Vec::new();
+        let mut vertex_indices: HashMap<[Option<usize>; 3], usize> = HashMap::new();
+        let mut material = None;
+
+        for (i, line) in obj.lines().enumerate() {
+            let line_num = i + 1;
+            let err = |msg: &str| format!("line {line_num}: {msg}");
+            let mut words = strip_comment(line).split_whitespace();
+            match words.next() {
+                Some("v") => {
+                    let nums = parse_floats(words).ok_or_else(|| err("invalid number"))?;
+                    let (pos, color) = match nums[..] {
+                        [x, y, z] | [x, y, z, _] => (Vec3::new(x, y, z), None),
+                        // a common extension for vertex colors
+                        [x, y, z, r, g, b] => {
+                            let [r, g, b] = [r, g, b].map(unit_to_u8);
+                            (Vec3::new(x, y, z), Some(Color::rgb(r, g, b)))
+                        }
+                        _ => return Err(err("`v` needs 3 numbers")),
+                    };
+                    positions.push(pos);
+                    colors.push(color);
+                }
+                Some("vt") => {
+                    let nums = parse_floats(words).ok_or_else(|| err("invalid number"))?;
+                    let uv = match nums[..] {
+                        [u] => Vec2::new(u, 0.0),
+                        [u, v] | [u, v, _] => Vec2::new(u, v),
+                        _ => return Err(err("`vt` needs 1 to 3 numbers")),
+                    };
+                    uvs.push(Vec2::new(uv.x, 1.0 - uv.y));
+                }
+                Some("vn") => {
+                    let nums = parse_floats(words).ok_or_else(|| err("invalid number"))?;
+                    let [x, y, z] = nums[..] else {
+                        return Err(err("`vn` needs 3 numbers"));
+                    };
+                    normals.push(Vec3::new(x, y, z));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for word in words {
+                        let mut parts = word.split('/');
+                        let mut vertex = [None; 3];
+                        for (index, count) in
+                            vertex
+                                .iter_mut()
+                                .zip([positions.len(), uvs.len(), normals.len()])
+                        {
+                            let part = parts.next().unwrap_or("");
+                            *index = obj_index(part, count).map_err(|msg| err(&msg))?;
+                        }
+                        if vertex[0].is_none() {
+                            return Err(err(&format!("face point `{word}` has no position")));
+                        }
+                        let index = *vertex_indices.entry(vertex).or_insert_with(|| {
+                            vertices.push(vertex);
+                            vertices.len() - 1
+                        });
+                        face.push(index);
+                    }
+                    if face.len() < 3 {
+                        return Err(err("a face needs at least 3 points"));
+                    }
+                    for i in 1..face.len() - 1 {
+                        mesh.tris.push([face[0], face[i], face[i + 1]]);
+                        mesh.tri_materials.push(material);
+                    }
+                }
+                Some("usemtl") => {
+                    let name = words.next().ok_or_else(|| err("`usemtl` needs a name"))?;
+                    material = match mesh.materials.iter().position(|m| m.name == name) {
+                        Some(index) => Some(index),
+                        None if mtl.is_some() => {
+                            return Err(err(&format!("unknown material `{name}`")));
+                        }
+                        None => {
+                            mesh.materials.push(Material::new(name));
+                            Some(mesh.materials.len() - 1)
+                        }
+                    };
+                }
+                // groups, smoothing, lines and the rest don't affect the triangles
+                _ => {}
+            }
+        }
+
+        mesh.positions = vertices.iter().map(|v| positions[v[0].unwrap()]).collect();
+        if colors.iter().any(Option::is_some) {
+            mesh.colors = vertices
+                .iter()
+                .map(|v| colors[v[0].unwrap()].unwrap_or(Color::WHITE))
+                .collect();
+        }
+        if vertices.iter().any(|v| v[1].is_some()) {
+            mesh.uvs = vertices
+                .iter()
+                .map(|v| v[1].map_or(Vec2::new(0.0, 0.0), |i| uvs[i]))
+                .collect();
+        }
+        if vertices.iter().any(|v| v[2].is_some()) {
+            mesh.normals = vertices
+                .iter()
+                .map(|v| v[2].map_or(Vec3::new(0.0, 0.0, 0.0), |i| normals[i]))
+                .collect();
+        }
+        if mesh.materials.is_empty() {
+            mesh.tri_materials.clear();
+        }
+        Ok(mesh)
+    }
+
+    /// Loads a mesh from a PLY file, either ASCII or binary.
+    ///
+    /// Vertices can have a position (`x`, `y` and `z`), a normal (`nx`, `ny` and `nz`),
+    /// texture coordinates (`u` and `v`, `s` and `t`, or `texture_u` and `texture_v`, flipped vertically like OBJ's)
+    /// and a color (`red`, `green`, `blue` and optionally `alpha`).
+    /// Faces with more than 3 points are split into triangles, and elements other than `vertex` and `face` are skipped.
+    pub fn from_ply(bytes: &[u8]) -> Result<Self, String> {
+        // the header ends at the first line that's just `end_header`, since comments and names can contain it
+        let mut line_start = 0;
+        let (header_end, data_start) = loop {
+            let line_end = bytes[line_start..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |i| line_start + i);
+            let line = std::str::from_utf8(&bytes[line_start..line_end]);
+            if line.map(str::trim) == Ok("end_header") {
+                break (line_start, (line_end + 1).min(bytes.len()));
+            }
+            if line_end == bytes.len() {
+                return Err(String::from("PLY has no end_header"));
+            }
+            line_start = line_end + 1;
+        };
+        let header =
+            std::str::from_utf8(&bytes[..header_end]).map_err(|_| "PLY header isn't text")?;
+
+        let mut lines = header.lines().enumerate().map(|(i, line)| (i + 1, line));
+        if lines.next().map(|(_, line)| line.trim()) != Some("ply") {
+            return Err(String::from("not a PLY file"));
+        }
+        let mut format = None;
+        let mut elements: Vec<PlyElement> = Vec::new();
+        for (line_num, line) in lines {
+            let err = |msg: &str| format!("line {line_num}: {msg}");
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                ["format", name, _] => {
+                    format = Some(match name {
+                        "ascii" => None,
+                        "binary_little_endian" => Some(false),
+                        "binary_big_endian" => Some(true),
+                        _ => return Err(err(&format!("unknown PLY format `{name}`"))),
+                    });
+                }
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| err(&format!("invalid count `{count}`")))?,
+                    properties: Vec::new(),
+                }),
+                ["property", ..] => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| err("`property` is before any `element`"))?;
+                    let ty = |name: &str| {
+                        PlyType::parse(name).ok_or_else(|| err(&format!("unknown type `{name}`")))
+                    };
+                    let property = match words[1..] {
+                        ["list", count, item, name] => PlyProperty {
+                            name: name.to_string(),
+                            ty: ty(item)?,
+                            list_count: Some(ty(count)?),
+                        },
+                        [item, name] => PlyProperty {
+                            name: name.to_string(),
+                            ty: ty(item)?,
+                            list_count: None,
+                        },
+                        _ => return Err(err("invalid property")),
+                    };
+                    element.properties.push(property);
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => return Err(err("invalid header line")),
+            }
+        }
+        let format = format.ok_or("PLY has no format")?;
+
+        let mut data = match format {
+            None => {
+                let text = std::str::from_utf8(&bytes[data_start..])
+                    .map_err(|_| "ASCII PLY data isn't text")?;
+                let first_line = header.lines().count() + 2;
+                PlyData::Ascii(Box::new(text.lines().enumerate().flat_map(
+                    move |(i, line)| line.split_whitespace().map(move |t| (first_line + i, t)),
+                )))
+            }
+            Some(big_endian) => PlyData::Binary {
+                bytes: &bytes[data_start..],
+                big_endian,
+            },
+        };
+
+        let mut mesh = Self::default();
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => read_ply_vertices(&mut mesh, element, &mut data)?,
+                "face" => read_ply_faces(&mut mesh, element, &mut data)?,
+                // an element with no properties has no data to skip, however many of it there are
+                _ if element.properties.is_empty() => {}
+                _ => {
+                    for _ in 0..element.count {
+                        element.read(&mut data)?;
+                    }
+                }
+            }
+        }
+        let count = mesh.positions.len();
+        if let Some(index) = mesh.tris.iter().flatten().find(|&&i| i >= count) {
+            return Err(format!(
+                "PLY face has vertex index {index}, but there are only {count} vertices"
+            ));
+        }
+        Ok(mesh)
+    }
+
+    /// The positions of the vertices of a triangle.
+    #[inline(always)]
+    pub fn tri_points(&self, tri: [usize; 3]) -> [Vec3<f32>; 3] {
+        tri.map(|i| self.positions[i])
+    }
+
+    /// Draws the edges of every triangle that isn't culled (see `Renderer3d::draw_tri`).
+    pub fn draw_wireframe(&self, g: &mut Graphics, renderer: &Renderer3d, color: Color) {
+        for &tri in &self.tris {
+            renderer.draw_tri(g, self.tri_points(tri), color);
+        }
+    }
+
+    /// Fills every triangle, with its vertices' colors if the mesh has them,
+    /// otherwise with its material's diffuse color, otherwise with `color`.
+    pub fn fill(&self, g: &mut Graphics, renderer: &Renderer3d, color: Color) {
+        self.fill_textured(g, renderer, color, &[], Filter::Nearest);
+    }
+    /// Like `fill`, but triangles whose material has a texture are filled with it,
+    /// where `textures` has the texture of each material (loaded from its `texture`) by its index in `materials`.
+    pub fn fill_textured(
+        &self,
+        g: &mut Graphics,
+        renderer: &Renderer3d,
+        color: Color,
+        textures: &[Option<PixBufView>],
+        filter: Filter,
+    ) {
+        for (i, &tri) in self.tris.iter().enumerate() {
+            let points = self.tri_points(tri);
+            let material = self.tri_materials.get(i).copied().flatten();
+            let texture = material.and_then(|m| textures.get(m).copied().flatten());
+            match texture {
+                Some(texture) if !self.uvs.is_empty() => {
+                    let uvs = tri.map(|i| self.uvs[i]);
+                    renderer.fill_tri_textured(g, points, uvs, texture, filter);
+                }
+                _ if !self.colors.is_empty() => {
+                    renderer.fill_tri_colored(g, points, tri.map(|i| self.colors[i]));
+                }
+                _ => {
+                    let color = material.map_or(color, |m| self.materials[m].diffuse);
+                    renderer.fill_tri(g, points, color);
+                }
+            }
+        }
+    }
+}
+
+/// Removes a `#` comment from the end of a line.
+#[inline(always)]
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or("")
+}
+
+fn parse_floats<'s>(words: impl Iterator<Item = &'s str>) -> Option<Vec<f32>> {
+    words.map(|w| w.parse().ok()).collect()
+}
+
+/// Converts from 0 to 1 into 0 to 255.
+#[inline(always)]
+fn unit_to_u8(e: f32) -> u8 {
+    (e.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Resolves an OBJ index, which starts at 1, or counts back from the last element if negative.
+fn obj_index(part: &str, count: usize) -> Result<Option<usize>, String> {
+    if part.is_empty() {
+        return Ok(None);
+    }
+    let index: i64 = part
+        .parse()
+        .map_err(|_| format!("invalid index `{part}`"))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {index} is out of range"));
+    }
+    Ok(Some(resolved as usize))
+}
+
+/// The types of numbers in a PLY file.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+    #[inline(always)]
+    fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+struct PlyProperty {
+    name: String,
+    ty: PlyType,
+    /// The type of the count, if the property is a list.
+    list_count: Option<PlyType>,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+impl PlyElement {
+    /// Reads the properties of one element, with the items of lists one after another.
+    fn read(&self, data: &mut PlyData) -> Result<Vec<f64>, String> {
+        let mut values = Vec::with_capacity(self.properties.len());
+        for property in &self.properties {
+            if let Some(count_ty) = property.list_count {
+                let count = data.read_count(count_ty)?;
+                for _ in 0..count {
+                    values.push(data.read(property.ty)?);
+                }
+            } else {
+                values.push(data.read(property.ty)?);
+            }
+        }
+        Ok(values)
+    }
+    /// The index of a property in the values read (for elements without lists), by the first of `names` that it has.
+    fn find(&self, names: &[&str]) -> Option<(usize, PlyType)> {
+        names.iter().find_map(|name| {
+            self.properties
+                .iter()
+                .position(|p| p.name == *name && p.list_count.is_none())
+                .map(|i| (i, self.properties[i].ty))
+        })
+    }
+}
+
+/// The data after the header of a PLY file.
+enum PlyData<'a> {
+    /// Numbers separated by whitespace, with the line that each is on.
+    Ascii(Box<dyn Iterator<Item = (usize, &'a str)> + 'a>),
+    Binary {
+        bytes: &'a [u8],
+        big_endian: bool,
+    },
+}
+impl PlyData<'_> {
+    fn read(&mut self, ty: PlyType) -> Result<f64, String> {
+        match self {
+            Self::Ascii(tokens) => {
+                let (line_num, token) = tokens.next().ok_or("PLY data is cut short")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("line {line_num}: invalid number `{token}`"))
+            }
+            Self::Binary { bytes, big_endian } => {
+                if bytes.len() < ty.size() {
+                    return Err(String::from("PLY data is cut short"));
+                }
+                let (value, rest) = bytes.split_at(ty.size());
+                *bytes = rest;
+                macro_rules! from_bytes {
+                    ($t:ty) => {{
+                        let value = value.try_into().unwrap();
+                        (if *big_endian {
+                            <$t>::from_be_bytes(value)
+                        } else {
+                            <$t>::from_le_bytes(value)
+                        }) as f64
+                    }};
+                }
+                Ok(match ty {
+                    PlyType::I8 => from_bytes!(i8),
+                    PlyType::U8 => from_bytes!(u8),
+                    PlyType::I16 => from_bytes!(i16),
+                    PlyType::U16 => from_bytes!(u16),
+                    PlyType::I32 => from_bytes!(i32),
+                    PlyType::U32 => from_bytes!(u32),
+                    PlyType::F32 => from_bytes!(f32),
+                    PlyType::F64 => from_bytes!(f64),
+                })
+            }
+        }
+    }
+    /// Reads the number of items in a list, or the index of a vertex.
+    fn read_count(&mut self, ty: PlyType) -> Result<usize, String> {
+        let count = self.read(ty)?;
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(format!("invalid PLY list count or index {count}"));
+        }
+        Ok(count as usize)
+    }
+}
+
+fn read_ply_vertices(
+    mesh: &mut Mesh,
+    element: &PlyElement,
+    data: &mut PlyData,
+) -> Result<(), String> {
+    if element.properties.iter().any(|p| p.list_count.is_some()) {
+        return Err(String::from("PLY vertices can't have lists"));
+    }
+    let find_all = |names: [&str; 3]| -> Option<[(usize, PlyType); 3]> {
+        let [a, b, c] = names.map(|name| element.find(&[name]));
+        Some([a?, b?, c?])
+    };
+    let position = find_all(["x", "y", "z"]).ok_or("PLY vertices have no position")?;
+    let normal = find_all(["nx", "ny", "nz"]);
+    let uv = element
+        .find(&["u", "s", "texture_u"])
+        .zip(element.find(&["v", "t", "texture_v"]));
+    let color = find_all(["red", "green", "blue"]);
+    let alpha = element.find(&["alpha"]);
+
+    for _ in 0..element.count {
+        let values = element.read(data)?;
+        let vec3 = |[x, y, z]: [(usize, PlyType); 3]| {
+            Vec3::new(values[x.0] as f32, values[y.0] as f32, values[z.0] as f32)
+        };
+        // colors are usually bytes, but can be from 0 to 1
+        let channel = |(i, ty): (usize, PlyType)| {
+            if ty.is_float() {
+                unit_to_u8(values[i] as f32)
+            } else {
+                values[i].clamp(0.0, 255.0) as u8
+            }
+        };
+        mesh.positions.push(vec3(position));
+        if let Some(normal) = normal {
+            mesh.normals.push(vec3(normal));
+        }
+        if let Some(((u, _), (v, _))) = uv {
+            mesh.uvs
+                .push(Vec2::new(values[u] as f32, 1.0 - values[v] as f32));
+        }
+        if let Some([r, g, b]) = color {
+            let a = alpha.map_or(255, channel);
+            mesh.colors
+                .push(Color::rgba(channel(r), channel(g), channel(b), a));
+        }
+    }
+    Ok(())
+}
+
+fn read_ply_faces(mesh: &mut Mesh, element: &PlyElement, data: &mut PlyData) -> Result<(), String> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|p| {
+            p.list_count.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+        })
+        .ok_or("PLY faces have no vertex_indices")?;
+    for face in 0..element.count {
+        let mut points = Vec::new();
+        for (i, property) in element.properties.iter().enumerate() {
+            match property.list_count {
+                Some(count_ty) if i == indices => {
+                    let count = data.read_count(count_ty)?;
+                    for _ in 0..count {
+                        points.push(data.read_count(property.ty)?);
+                    }
+                }
+                Some(count_ty) => {
+                    for _ in 0..data.read_count(count_ty)? {
+                        data.read(property.ty)?;
+                    }
+                }
+                None => {
+                    data.read(property.ty)?;
+                }
+            }
+        }
+        if points.len() < 3 {
+            return Err(format!("PLY face {face} needs at least 3 points"));
+        }
+        for i in 1..points.len() - 1 {
+            mesh.tris.push([points[0], points[i], points[i + 1]]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Image;
+    use crate::matrix::Mat4;
+
+    const OBJ: &str = "# a square, made of a triangle and a quad
+mtllib square.mtl
+v -1 -1 0
+v 1 -1 0
+v 1 1 0
+v -1 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1
+usemtl brick
+f -4/-4/-1 -2/-2/-1 -1/-1/-1  # relative to the end
+";
+    const MTL: &str = "newmtl red
+Kd 1 0 0
+d 0.5
+newmtl brick
+Kd 0.8 0.8 0.8
+map_Kd -s 2 2 1 textures/brick.png
+";
+
+    #[test]
+    fn load_obj() {
+        let mesh = Mesh::from_obj(OBJ, Some(MTL)).unwrap();
+        // the vertices on the diagonal are shared
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.tris, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.tri_points(mesh.tris[1])[2], Vec3::new(-1.0, 1.0, 0.0));
+        // flipped, so the top-left of the square is the top-left of the texture
+        assert_eq!(mesh.uvs[3], Vec2::new(0.0, 0.0));
+        assert_eq!(mesh.uvs[1], Vec2::new(1.0, 1.0));
+        assert_eq!(mesh.normals, [Vec3::new(0.0, 0.0, 1.0); 4]);
+        assert!(mesh.colors.is_empty());
+
+        assert_eq!(mesh.tri_materials, [Some(0), Some(1)]);
+        assert_eq!(mesh.materials[0].diffuse, Color::rgba(255, 0, 0, 128));
+        assert_eq!(mesh.materials[1].diffuse, Color::rgb(204, 204, 204));
+        assert_eq!(
+            mesh.materials[1].texture.as_deref(),
+            Some("textures/brick.png")
+        );
+
+        // without an MTL file, the materials are white
+        let mesh = Mesh::from_obj(OBJ, None).unwrap();
+        assert_eq!(
+            mesh.materials,
+            [Material::new("red"), Material::new("brick")]
+        );
+
+        // a pentagon with only positions, and vertex colors
+        let mesh = Mesh::from_obj(
+            "v 0 0 0 1 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 0\nf 1 2 3 4 5\nf 1 2 3",
+            None,
+        )
+        .unwrap();
+        assert_eq!(mesh.tris.len(), 4);
+        assert!(mesh.uvs.is_empty() && mesh.tri_materials.is_empty());
+        assert_eq!(&mesh.colors[..2], [Color::RED, Color::WHITE]);
+    }
+
+    #[test]
+    fn obj_errors() {
+        let error = |obj: &str, mtl: Option<&str>| Mesh::from_obj(obj, mtl).unwrap_err();
+        assert_eq!(error("v 1 2 x", None), "line 1: invalid number");
+        assert_eq!(error("v 1 2", None), "line 1: `v` needs 3 numbers");
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\n\nf 1 2 3", None),
+            "line 4: index 3 is out of range"
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1 1/x 1", None),
+            "line 2: invalid index `x`"
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1 1", None),
+            "line 2: a face needs at least 3 points"
+        );
+        assert_eq!(
+            error("usemtl blue", Some(MTL)),
+            "line 1: unknown material `blue`"
+        );
+        assert_eq!(
+            error(OBJ, Some("Kd 1 1 1")),
+            "MTL line 1: `Kd` is before any `newmtl`"
+        );
+        assert_eq!(
+            error(OBJ, Some("newmtl a\nKd 1 1")),
+            "MTL line 2: `Kd` needs 3 numbers"
+        );
+    }
+
+    const PLY_HEADER: &str = "ply
+format FORMAT 1.0
+comment a square, with a colored corner
+element vertex 4
+property float x
+property float y
+property float z
+property float s
+property float t
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property uchar intensity
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+";
+    const PLY_VERTICES: [[f32; 5]; 4] = [
+        [-1.0, -1.0, 0.0, 0.0, 0.0],
+        [1.0, -1.0, 0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0, 1.0, 1.0],
+        [-1.0, 1.0, 0.0, 0.0, 1.0],
+    ];
+
+    fn binary_ply(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut bytes = PLY_HEADER.replace("FORMAT", format).into_bytes();
+        for (i, vertex) in PLY_VERTICES.iter().enumerate() {
+            for e in vertex {
+                bytes.extend(if big_endian {
+                    e.to_be_bytes()
+                } else {
+                    e.to_le_bytes()
+                });
+            }
+            bytes.extend(if i == 0 { [255, 0, 0] } else { [255; 3] });
+        }
+        bytes.extend([7, 4]);
+        for e in [0i32, 1, 2, 3, 0, 1] {
+            bytes.extend(if big_endian {
+                e.to_be_bytes()
+            } else {
+                e.to_le_bytes()
+            });
+        }
+        bytes
+    }
+
+    #[test]
+    fn load_ply() {
+        let ascii = PLY_HEADER.replace("FORMAT", "ascii")
+            + "-1 -1 0 0 0 255 0 0\n1 -1 0 1 0 255 255 255\n1 1 0 1 1 255 255 255\n-1 1 0 0 1 255 255 255\n7 4 0 1 2 3\n0 1\n";
+        let mesh = Mesh::from_ply(ascii.as_bytes()).unwrap();
+        assert_eq!(mesh.positions[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.uvs[3], Vec2::new(0.0, 0.0));
+        assert_eq!(&mesh.colors[..2], [Color::RED, Color::WHITE]);
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.tris, [[0, 1, 2], [0, 2, 3]]);
+
+        assert_eq!(Mesh::from_ply(&binary_ply(false)).unwrap(), mesh);
+        assert_eq!(Mesh::from_ply(&binary_ply(true)).unwrap(), mesh);
+
+        // only a line that's just `end_header` ends the header
+        let mut commented = b"ply\ncomment the header ends at end_header\n".to_vec();
+        commented.extend(&binary_ply(false)[4..]);
+        assert_eq!(Mesh::from_ply(&commented).unwrap(), mesh);
+
+        // other elements with no properties are skipped right away
+        let mut empty = b"ply\nelement junk 18446744073709551615".to_vec();
+        empty.extend(&binary_ply(false)[3..]);
+        assert_eq!(Mesh::from_ply(&empty).unwrap(), mesh);
+    }
+
+    #[test]
+    fn ply_errors() {
+        let error = |ply: &[u8]| Mesh::from_ply(ply).unwrap_err();
+        assert_eq!(error(b"obj\nend_header\n"), "not a PLY file");
+        assert_eq!(error(b"ply\nformat ascii 1.0\n"), "PLY has no end_header");
+        assert_eq!(
+            error(b"ply\nformat utf8 1.0\nend_header\n"),
+            "line 2: unknown PLY format `utf8`"
+        );
+        assert_eq!(
+            error(b"ply\nformat ascii 1.0\nproperty float x\nend_header\n"),
+            "line 3: `property` is before any `element`"
+        );
+        let binary = binary_ply(false);
+        assert_eq!(error(&binary[..binary.len() - 2]), "PLY data is cut short");
+
+        let ascii = PLY_HEADER.replace("FORMAT", "ascii");
+        assert_eq!(
+            error(format!("{ascii}-1 -1 0 0 0 255 0 zero\n").as_bytes()),
+            "line 20: invalid number `zero`"
+        );
+        // the face and edge are the last 26 bytes, replaced by a triangle with an index past the vertices
+        let mut bad_index = binary[..binary.len() - 26].to_vec();
+        bad_index.extend([7, 3]);
+        for e in [0i32, 1, 9, 0, 1] {
+            bad_index.extend(e.to_le_bytes());
+        }
+        assert_eq!(
+            error(&bad_index),
+            "PLY face has vertex index 9, but there are only 4 vertices"
+        );
+    }
+
+    #[test]
+    fn draw_meshes() {
+        let mesh = Mesh::from_obj(OBJ, Some(MTL)).unwrap();
+        let renderer = Renderer3d::new()
+            .with_view(Mat4::look_at(
+                Vec3::new(0.0, 0.0, 3.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ))
+            .with_projection(Mat4::perspective(
+                std::f32::consts::FRAC_PI_2,
+                1.0,
+                0.1,
+                100.0,
+            ));
+        let mut texture = Image::empty(Vec2::new(2, 2));
+        texture.bytes.fill(255);
+        texture.bytes[..4].copy_from_slice(&[0, 0, 255, 255]);
+
+        let mut image = Image::empty(Vec2::new(30, 30));
+        {
+            let mut g = image.create_graphics();
+            mesh.fill_textured(
+                &mut g,
+                &renderer,
+                Color::BLACK,
+                &[None, Some(texture.pixels())],
+                Filter::Nearest,
+            );
+        }
+        let pixel =
+            |image: &Image, x: usize, y: usize| image.bytes[(y * 30 + x) * 4..][..4].to_vec();
+        // the square covers the middle third, with the red triangle at the bottom-right
+        // and the textured one at the top-left, with the blue texel at its top-left
+        assert_eq!(pixel(&image, 18, 18), [255, 0, 0, 128]);
+        assert_eq!(pixel(&image, 11, 11), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 11, 16), [255; 4]);
+        assert_eq!(pixel(&image, 5, 5), [0; 4]);
+
+        let mut image = Image::empty(Vec2::new(30, 30));
+        {
+            let mut g = image.create_graphics();
+            mesh.draw_wireframe(&mut g, &renderer, Color::WHITE);
+        }
+        assert_eq!(pixel(&image, 15, 15), [255; 4]);
+        assert_eq!(pixel(&image, 12, 14), [0; 4]);
+    }
+}
@@ -0,0 +1,576 @@
+use crate::graphics::Color;
+use crate::pixel_buf::PixBufView;
+use crate::vectors::Vec2;
+use std::f32::consts::TAU;
+
+/// How a gradient continues before its start and past its end.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Spread {
+    /// The colors of the first and last stops carry on forever.
+    #[default]
+    Pad,
+    /// The gradient starts over from the beginning.
+    Repeat,
+    /// The gradient goes back and forth, mirrored every other time.
+    Reflect,
+}
+
+/// A color at a position along a gradient, from 0 (the start) to 1 (the end).
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: Color,
+}
+impl ColorStop {
+    pub const fn new(offset: f32, color: Color) -> Self {
+        Self { offset, color }
+    }
+}
+
+/// A ramp of colors that blends smoothly from each stop to the next.
+///
+/// Colors are blended with their alpha premultiplied, so a transparent stop fades a color out
+/// instead of darkening it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<ColorStop>,
+    spread: Spread,
+}
+impl Gradient {
+    /// Creates a gradient with the given stops, which are sorted by their offsets (clamped to 0 to 1).
+    ///
+    /// Two stops at the same offset make a hard edge between their colors.
+    /// A gradient without any stops is transparent.
+    pub fn new(stops: &[ColorStop]) -> Self {
+        let mut stops: Vec<ColorStop> = stops
+            .iter()
+            .map(|s| ColorStop::new(s.offset.clamp(0.0, 1.0), s.color))
+            .collect();
+        // the sort is stable, so stops at the same offset stay in the order they were given
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        Self {
+            stops,
+            spread: Spread::default(),
+        }
+    }
+    /// Creates a gradient with the colors spread out evenly, from the start to the end.
+    pub fn even(colors: &[Color]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        let stops: Vec<ColorStop> = colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| ColorStop::new(i as f32 / last, color))
+            .collect();
+        Self::new(&stops)
+    }
+    pub fn with_spread(mut self, spread: Spread) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    #[inline(always)]
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+    #[inline(always)]
+    pub fn spread(&self) -> Spread {
+        self.spread
+    }
+
+    /// The color at `t` along the gradient, where 0 is the start and 1 is the end.
+    pub fn color_at(&self, t: f32) -> Color {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return Color::rgba(0, 0, 0, 0);
+        };
+        let t = if t.is_finite() { t } else { 0.0 };
+        let t = match self.spread {
+            Spread::Pad => t.clamp(0.0, 1.0),
+            Spread::Repeat => t - t.floor(),
+            Spread::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 {
+                    2.0 - t
+                } else {
+                    t
+                }
+            }
+        };
+        if t < first.offset {
+            return first.color;
+        }
+        if t >= last.offset {
+            return last.color;
+        }
+        // the stops either side of `t`, where `a.offset <= t < b.offset`
+        let next = self.stops.partition_point(|s| s.offset <= t);
+        let (a, b) = (self.stops[next - 1], self.stops[next]);
+        lerp_color(a.color, b.color, (t - a.offset) / (b.offset - a.offset))
+    }
+}
+
+/// How a pattern continues past the edges of its image.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Wrap {
+    /// The image is tiled.
+    #[default]
+    Repeat,
+    /// The pixels along the edges of the image carry on forever.
+    Clamp,
+    /// The image is tiled, mirrored every other time.
+    Mirror,
+}
+
+/// How the pixels of a pattern's image are sampled.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Filter {
+    /// Takes the nearest pixel, which keeps the image sharp (and blocky when scaled up).
+    #[default]
+    Nearest,
+    /// Blends the 4 nearest pixels, which keeps the image smooth.
+    Bilinear,
+}
+
+/// An image placed in pixel space, used to fill shapes.
+///
+/// The top-left corner of the image is at `origin`, and it's scaled by `scale` then rotated
+/// clockwise by `rotation` (in radians) around `origin`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Pattern<'a> {
+    pub pixels: PixBufView<'a>,
+    pub origin: Vec2<f32>,
+    pub scale: Vec2<f32>,
+    pub rotation: f32,
+    pub wrap: Wrap,
+    pub filter: Filter,
+}
+impl<'a> Pattern<'a> {
+    /// Creates a pattern with the image tiled from `(0.0, 0.0)` at its own size.
+    pub fn new(pixels: PixBufView<'a>) -> Self {
+        Self {
+            pixels,
+            origin: Vec2::new(0.0, 0.0),
+            scale: Vec2::new(1.0, 1.0),
+            rotation: 0.0,
+            wrap: Wrap::default(),
+            filter: Filter::default(),
+        }
+    }
+    pub fn with_origin(mut self, origin: Vec2<f32>) -> Self {
+        self.origin = origin;
+        self
+    }
+    pub fn with_scale(mut self, scale: Vec2<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The color of this pattern at the point `p`, in pixel space.
+    pub fn color_at(&self, p: Vec2<f32>) -> Color {
+        if self.scale.x == 0.0 || self.scale.y == 0.0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+        // undo the rotation then the scale, to find the point in the image
+        let offset = p - self.origin;
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let rotated = Vec2::new(
+            offset.x * cos - offset.y * sin,
+            offset.x * sin + offset.y * cos,
+        );
+        let q = Vec2::new(rotated.x / self.scale.x, rotated.y / self.scale.y);
+        sample(self.pixels, q, self.wrap, self.filter)
+    }
+}
+
+/// The color of `pixels` at the point `p`, where `(0.0, 0.0)` is the top-left corner of the top-left pixel.
+pub(crate) fn sample(pixels: PixBufView, p: Vec2<f32>, wrap: Wrap, filter: Filter) -> Color {
+    let size = pixels.size.map(|e| e as i32);
+    if size.x <= 0 || size.y <= 0 {
+        return Color::rgba(0, 0, 0, 0);
+    }
+    let texel = |x: i32, y: i32| {
+        let pos = Vec2::new(wrap_index(x, size.x, wrap), wrap_index(y, size.y, wrap));
+        // SAFETY: `wrap_index` always returns an index within the image
+        unsafe { pixels.get_pixel_unchecked(pos) }
+    };
+    match filter {
+        Filter::Nearest => texel(p.x.floor() as i32, p.y.floor() as i32),
+        Filter::Bilinear => {
+            // the pixels whose centers surround the point
+            let (sx, sy) = (p.x - 0.5, p.y - 0.5);
+            let (x0, y0) = (sx.floor(), sy.floor());
+            let (fx, fy) = (sx - x0, sy - y0);
+            let (x0, y0) = (x0 as i32, y0 as i32);
+            let top = lerp_color(texel(x0, y0), texel(x0 + 1, y0), fx);
+            let bot = lerp_color(texel(x0, y0 + 1), texel(x0 + 1, y0 + 1), fx);
+            lerp_color(top, bot, fy)
+        }
+    }
+}
+
+/// What the inside of a shape is filled with: a single color, a gradient, or an image.
+///
+/// The positions of gradients and patterns are in pixel space, where `(0.0, 0.0)` is the top-left corner
+/// of the top-left pixel, and each pixel is colored by the paint at its center.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Paint<'a> {
+    Solid(Color),
+    /// A gradient along the line from `start` to `end`, with the same color across the line.
+    Linear {
+        start: Vec2<f32>,
+        end: Vec2<f32>,
+        gradient: Gradient,
+    },
+    /// A gradient outwards from `center`, which ends at `radius` pixels from it.
+    Radial {
+        center: Vec2<f32>,
+        radius: f32,
+        gradient: Gradient,
+    },
+    /// A gradient going once clockwise around `center`, starting at `start_angle` (in radians, from the right).
+    Conic {
+        center: Vec2<f32>,
+        start_angle: f32,
+        gradient: Gradient,
+    },
+    Pattern(Pattern<'a>),
+}
+impl<'a> Paint<'a> {
+    pub fn linear(start: Vec2<f32>, end: Vec2<f32>, gradient: Gradient) -> Self {
+        Self::Linear {
+            start,
+            end,
+            gradient,
+        }
+    }
+    pub fn radial(center: Vec2<f32>, radius: f32, gradient: Gradient) -> Self {
+        Self::Radial {
+            center,
+            radius,
+            gradient,
+        }
+    }
+    pub fn conic(center: Vec2<f32>, start_angle: f32, gradient: Gradient) -> Self {
+        Self::Conic {
+            center,
+            start_angle,
+            gradient,
+        }
+    }
+
+    /// The color of this paint at the point `p`, in pixel space.
+    pub fn color_at(&self, p: Vec2<f32>) -> Color {
+        match self {
+            Self::Solid(color) => *color,
+            Self::Linear {
+                start,
+                end,
+                gradient,
+            } => {
+                let dir = *end - *start;
+                let len_sq = dir.x * dir.x + dir.y * dir.y;
+                if len_sq == 0.0 {
+                    return gradient.color_at(0.0);
+                }
+                let offset = p - *start;
+                gradient.color_at((offset.x * dir.x + offset.y * dir.y) / len_sq)
+            }
+            Self::Radial {
+                center,
+                radius,
+                gradient,
+            } => {
+                let offset = p - *center;
+                let dist = (offset.x * offset.x + offset.y * offset.y).sqrt();
+                if *radius <= 0.0 {
+                    return gradient.color_at(if dist > 0.0 { 1.0 } else { 0.0 });
+                }
+                gradient.color_at(dist / radius)
+            }
+            Self::Conic {
+                center,
+                start_angle,
+                gradient,
+            } => {
+                let offset = p - *center;
+                let angle = offset.y.atan2(offset.x) - start_angle;
+                gradient.color_at(angle.rem_euclid(TAU) / TAU)
+            }
+            Self::Pattern(pattern) => pattern.color_at(p),
+        }
+    }
+    /// The color of this paint at the center of the pixel at `pos`.
+    #[inline(always)]
+    pub fn color_at_pixel(&self, pos: Vec2<i32>) -> Color {
+        match self {
+            Self::Solid(color) => *color,
+            _ => self.color_at(pos.map(|e| e as f32 + 0.5)),
+        }
+    }
+
+    /// The color of this paint, if it's the same everywhere.
+    pub fn as_solid(&self) -> Option<Color> {
+        match self {
+            Self::Solid(color) => Some(*color),
+            _ => None,
+        }
+    }
+}
+impl Default for Paint<'_> {
+    fn default() -> Self {
+        Self::Solid(Color::BLACK)
+    }
+}
+impl From<Color> for Paint<'_> {
+    #[inline(always)]
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+impl<'a> From<&Paint<'a>> for Paint<'a> {
+    fn from(paint: &Paint<'a>) -> Self {
+        paint.clone()
+    }
+}
+impl<'a> From<Pattern<'a>> for Paint<'a> {
+    #[inline(always)]
+    fn from(pattern: Pattern<'a>) -> Self {
+        Self::Pattern(pattern)
+    }
+}
+
+/// Wraps the index of a pixel in a row or column of `len` pixels, so it's within `0..len`.
+fn wrap_index(i: i32, len: i32, wrap: Wrap) -> i32 {
+    match wrap {
+        Wrap::Repeat => i.rem_euclid(len),
+        Wrap::Clamp => i.clamp(0, len - 1),
+        Wrap::Mirror => {
+            let i = i.rem_euclid(len * 2);
+            if i >= len {
+                len * 2 - 1 - i
+            } else {
+                i
+            }
+        }
+    }
+}
+
+/// Blends from `a` to `b` by `t` (0 to 1), with premultiplied alpha.
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let (aa, ba) = (a.a as f32, b.a as f32);
+    let alpha = aa + (ba - aa) * t;
+    if alpha <= 0.0 {
+        return Color::rgba(0, 0, 0, 0);
+    }
+    let mix = |ac: u8, bc: u8| {
+        let (ac, bc) = (ac as f32 * aa, bc as f32 * ba);
+        ((ac + (bc - ac) * t) / alpha).round().clamp(0.0, 255.0) as u8
+    };
+    Color::rgba(
+        mix(a.r, b.r),
+        mix(a.g, b.g),
+        mix(a.b, b.b),
+        alpha.round() as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Image;
+    use crate::shapes::{Rect, Tri};
+
+    #[test]
+    fn gradient_stops() {
+        let gradient = Gradient::new(&[
+            ColorStop::new(1.0, Color::BLUE),
+            ColorStop::new(0.0, Color::RED),
+            ColorStop::new(0.5, Color::GREEN),
+        ]);
+        assert_eq!(gradient.color_at(0.0), Color::RED);
+        assert_eq!(gradient.color_at(0.5), Color::GREEN);
+        assert_eq!(gradient.color_at(1.0), Color::BLUE);
+        assert_eq!(gradient.color_at(0.25), Color::rgb(128, 128, 0));
+        assert_eq!(gradient.color_at(0.75), Color::rgb(0, 128, 128));
+        assert_eq!(
+            gradient,
+            Gradient::even(&[Color::RED, Color::GREEN, Color::BLUE])
+        );
+
+        // stops at the same offset make a hard edge
+        let hard = Gradient::new(&[
+            ColorStop::new(0.0, Color::RED),
+            ColorStop::new(0.5, Color::RED),
+            ColorStop::new(0.5, Color::BLUE),
+            ColorStop::new(1.0, Color::BLUE),
+        ]);
+        assert_eq!(hard.color_at(0.49), Color::RED);
+        assert_eq!(hard.color_at(0.5), Color::BLUE);
+
+        assert_eq!(Gradient::new(&[]).color_at(0.5).a, 0);
+        assert_eq!(Gradient::even(&[Color::RED]).color_at(0.5), Color::RED);
+    }
+
+    #[test]
+    fn transparent_stops_fade_without_darkening() {
+        let gradient = Gradient::even(&[Color::WHITE, Color::rgba(0, 0, 0, 0)]);
+        assert_eq!(gradient.color_at(0.5), Color::rgba(255, 255, 255, 128));
+    }
+
+    #[test]
+    fn spread_modes() {
+        let gradient = Gradient::even(&[Color::BLACK, Color::WHITE]);
+        let shade = |g: &Gradient, t: f32| g.color_at(t).r;
+        assert_eq!((shade(&gradient, -0.5), shade(&gradient, 1.5)), (0, 255));
+
+        let repeat = gradient.clone().with_spread(Spread::Repeat);
+        assert_eq!(shade(&repeat, 1.25), shade(&gradient, 0.25));
+        assert_eq!(shade(&repeat, -0.25), shade(&gradient, 0.75));
+
+        let reflect = gradient.with_spread(Spread::Reflect);
+        assert_eq!(shade(&reflect, 1.25), shade(&reflect, 0.75));
+        assert_eq!(shade(&reflect, -0.25), shade(&reflect, 0.25));
+        assert_eq!(shade(&reflect, 2.25), shade(&reflect, 0.25));
+    }
+
+    #[test]
+    fn paint_shapes() {
+        let gradient = Gradient::even(&[Color::BLACK, Color::WHITE]);
+        let shade = |paint: &Paint, x: f32, y: f32| paint.color_at(Vec2::new(x, y)).r;
+
+        let linear = Paint::linear(
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 20.0),
+            gradient.clone(),
+        );
+        assert_eq!(shade(&linear, 0.0, 10.0), shade(&linear, 50.0, 10.0));
+        assert_eq!(
+            (shade(&linear, 3.0, 0.0), shade(&linear, 3.0, 20.0)),
+            (0, 255)
+        );
+
+        let radial = Paint::radial(Vec2::new(10.0, 10.0), 5.0, gradient.clone());
+        assert_eq!(shade(&radial, 10.0, 10.0), 0);
+        assert_eq!(shade(&radial, 13.0, 14.0), 255);
+        assert_eq!(shade(&radial, 12.5, 10.0), shade(&radial, 10.0, 7.5));
+
+        // a quarter of the way round, clockwise from the top
+        let conic = Paint::conic(Vec2::new(0.0, 0.0), -std::f32::consts::FRAC_PI_2, gradient);
+        assert_eq!(shade(&conic, 5.0, 0.0), 64);
+        assert_eq!(shade(&conic, 0.0, 5.0), 128);
+    }
+
+    /// A 2x2 checkerboard, with white in the top-left and bottom-right.
+    fn checker() -> Image {
+        let mut image = Image::empty(Vec2::new(2, 2));
+        for (i, pixel) in image.bytes.chunks_mut(4).enumerate() {
+            let shade = if i == 0 || i == 3 { 255 } else { 0 };
+            pixel.copy_from_slice(&[shade, shade, shade, 255]);
+        }
+        image
+    }
+
+    #[test]
+    fn pattern_wrapping() {
+        let image = checker();
+        let shade = |pattern: &Pattern, x: f32, y: f32| pattern.color_at(Vec2::new(x, y)).r;
+
+        let repeat = Pattern::new(image.pixels());
+        assert_eq!(
+            (shade(&repeat, 0.5, 0.5), shade(&repeat, 1.5, 0.5)),
+            (255, 0)
+        );
+        assert_eq!(
+            (shade(&repeat, 2.5, 0.5), shade(&repeat, -0.5, 0.5)),
+            (255, 0)
+        );
+
+        let clamp = repeat.with_wrap(Wrap::Clamp);
+        assert_eq!(
+            (shade(&clamp, 5.5, 0.5), shade(&clamp, -5.5, 0.5)),
+            (0, 255)
+        );
+
+        let mirror = repeat.with_wrap(Wrap::Mirror);
+        assert_eq!(
+            (shade(&mirror, 2.5, 0.5), shade(&mirror, 3.5, 0.5)),
+            (0, 255)
+        );
+        assert_eq!(shade(&mirror, -0.5, 0.5), 255);
+    }
+
+    #[test]
+    fn pattern_transform_and_filter() {
+        let image = checker();
+        let shade = |pattern: &Pattern, x: f32, y: f32| pattern.color_at(Vec2::new(x, y)).r;
+
+        let scaled = Pattern::new(image.pixels())
+            .with_origin(Vec2::new(10.0, 10.0))
+            .with_scale(Vec2::new(4.0, 4.0));
+        assert_eq!(shade(&scaled, 13.5, 13.5), 255);
+        assert_eq!(shade(&scaled, 14.5, 13.5), 0);
+        assert_eq!(shade(&scaled, 9.5, 13.5), 0);
+
+        // a quarter turn clockwise moves the top-left pixel to the left of the origin
+        let rotated = scaled.with_rotation(std::f32::consts::FRAC_PI_2);
+        assert_eq!(shade(&rotated, 8.0, 12.0), 255);
+        assert_eq!(shade(&rotated, 12.0, 12.0), 0);
+
+        // bilinear filtering blends between the centers of pixels
+        let bilinear = scaled.with_filter(Filter::Bilinear);
+        assert_eq!(shade(&bilinear, 12.0, 12.0), 255);
+        assert_eq!(shade(&bilinear, 14.0, 12.0), 128);
+        assert_eq!(shade(&bilinear, 16.0, 12.0), 0);
+    }
+
+    #[test]
+    fn fill_with_pattern() {
+        let texture = checker();
+        let mut image = Image::empty(Vec2::new(8, 8));
+        {
+            let mut g = image.create_graphics();
+            let pattern = Pattern::new(texture.pixels()).with_scale(Vec2::new(2.0, 2.0));
+            g.fill_circle(Vec2::new(4, 4), 3, pattern);
+        }
+        let pixel = |x: usize, y: usize| &image.bytes[(y * 8 + x) * 4..][..4];
+        assert_eq!(pixel(1, 4), &[255, 255, 255, 255]);
+        assert_eq!(pixel(2, 4), &[0, 0, 0, 255]);
+        assert_eq!(pixel(0, 0), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn fills_accept_paints() {
+        let mut image = Image::empty(Vec2::new(20, 20));
+        let paint = Paint::linear(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(20.0, 0.0),
+            Gradient::even(&[Color::BLACK, Color::RED]),
+        );
+        let red = |image: &Image, x: usize, y: usize| image.bytes[(y * 20 + x) * 4];
+        {
+            let mut g = image.create_graphics();
+            g.fill_rect(&Rect::new(0, 0, 20, 5), &paint);
+            g.fill_tri(
+                &Tri(Vec2::new(0, 5), Vec2::new(19, 5), Vec2::new(0, 10)),
+                &paint,
+            );
+            g.fill_circle(Vec2::new(10, 15), 4, &paint);
+        }
+        for y in [2, 6, 15] {
+            assert!(red(&image, 2, y) < red(&image, 8, y), "row {y}");
+        }
+        assert_eq!(red(&image, 0, 0), 6);
+        assert_eq!(red(&image, 19, 0), 249);
+    }
+}
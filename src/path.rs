@@ -0,0 +1,512 @@
+use crate::shapes::Poly;
+use crate::vectors::{Vec2, VecMath};
+use rusttype::OutlineBuilder;
+
+/// The default largest distance, in pixels, between a flattened curve and the true curve.
+pub const DEFAULT_TOLERANCE: f32 = 0.25;
+
+/// The most line segments a single curve is flattened into.
+const MAX_CURVE_SEGMENTS: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathCmd {
+    MoveTo(Vec2<f32>),
+    LineTo(Vec2<f32>),
+    /// A quadratic Bézier curve, with a control point then an end point.
+    QuadTo(Vec2<f32>, Vec2<f32>),
+    /// A cubic Bézier curve, with two control points then an end point.
+    CubicTo(Vec2<f32>, Vec2<f32>, Vec2<f32>),
+    Close,
+}
+
+/// A shape made of lines and curves, built up like a path on an HTML canvas.
+///
+/// The coordinates are in pixel space, where `(0.0, 0.0)` is the top-left corner of the top-left pixel.
+/// Curves are kept exact until the path is flattened (when it is filled or stroked).
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Path {
+    cmds: Vec<PathCmd>,
+    /// where the current sub-path started
+    start: Vec2<f32>,
+    /// the end of the last command
+    current: Vec2<f32>,
+}
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The commands that make up this path, in the order they were added.
+    #[inline(always)]
+    pub fn cmds(&self) -> &[PathCmd] {
+        &self.cmds
+    }
+    /// The end of the last command, where the next command will start from.
+    #[inline(always)]
+    pub fn current_point(&self) -> Vec2<f32> {
+        self.current
+    }
+
+    /// Starts a new sub-path at `p`.
+    pub fn move_to(&mut self, p: Vec2<f32>) {
+        self.cmds.push(PathCmd::MoveTo(p));
+        self.start = p;
+        self.current = p;
+    }
+    pub fn line_to(&mut self, p: Vec2<f32>) {
+        self.cmds.push(PathCmd::LineTo(p));
+        self.current = p;
+    }
+    pub fn quad_to(&mut self, ctrl: Vec2<f32>, p: Vec2<f32>) {
+        self.cmds.push(PathCmd::QuadTo(ctrl, p));
+        self.current = p;
+    }
+    pub fn cubic_to(&mut self, ctrl1: Vec2<f32>, ctrl2: Vec2<f32>, p: Vec2<f32>) {
+        self.cmds.push(PathCmd::CubicTo(ctrl1, ctrl2, p));
+        self.current = p;
+    }
+    /// Connects the current point back to the start of the sub-path.
+    pub fn close(&mut self) {
+        self.cmds.push(PathCmd::Close);
+        self.current = self.start;
+    }
+
+    /// Adds a circular arc, like `arcTo` on an HTML canvas: the arc has the given radius,
+    /// and touches both the line from the current point to `p1`, and the line from `p1` to `p2`.
+    /// A straight line is added from the current point to the start of the arc.
+    pub fn arc_to(&mut self, p1: Vec2<f32>, p2: Vec2<f32>, radius: f32) {
+        let p0 = self.current;
+        let (d0, len0) = ((p0 - p1).norm_or_zero(), (p0 - p1).len());
+        let (d1, len1) = ((p2 - p1).norm_or_zero(), (p2 - p1).len());
+        let cross = d0.x * d1.y - d0.y * d1.x;
+        if self.cmds.is_empty() {
+            self.move_to(p1);
+            return;
+        }
+        if len0 == 0.0 || len1 == 0.0 || radius <= 0.0 || cross == 0.0 {
+            self.line_to(p1);
+            return;
+        }
+
+        // the arc touches both lines at the same distance from the corner
+        let cos = d0.x * d1.x + d0.y * d1.y;
+        let half_angle = cos.clamp(-1.0, 1.0).acos() * 0.5;
+        let tangent_dist = radius / half_angle.tan();
+        let t0 = p1 + d0 * tangent_dist;
+        let t1 = p1 + d1 * tangent_dist;
+
+        let bisector = (d0 + d1).norm_or_zero();
+        let center = p1 + bisector * (radius / half_angle.sin());
+        let start_angle = angle_of(t0 - center);
+        let mut sweep = angle_of(t1 - center) - start_angle;
+        // the arc always takes the short way around
+        if sweep > std::f32::consts::PI {
+            sweep -= std::f32::consts::TAU;
+        } else if sweep < -std::f32::consts::PI {
+            sweep += std::f32::consts::TAU;
+        }
+        self.arc(center, radius, start_angle, sweep);
+    }
+
+    /// Adds a circular arc around `center`, from `start_angle` and sweeping by `sweep` (both in radians).
+    /// Positive angles are clockwise, since y points down.
+    ///
+    /// A straight line is added from the current point to the start of the arc,
+    /// or if this path is empty, a new sub-path is started there.
+    pub fn arc(&mut self, center: Vec2<f32>, radius: f32, start_angle: f32, sweep: f32) {
+        let start = center + Vec2::new(start_angle.cos(), start_angle.sin()) * radius;
+        if self.cmds.is_empty() {
+            self.move_to(start);
+        } else {
+            self.line_to(start);
+        }
+        if sweep == 0.0 {
+            return;
+        }
+
+        // each quarter (or less) of a circle is closely approximated by one cubic curve
+        let segments = (sweep.abs() / std::f32::consts::FRAC_PI_2).ceil() as usize;
+        let step = sweep / segments as f32;
+        let handle = radius * 4.0 / 3.0 * (step / 4.0).tan();
+
+        let mut angle = start_angle;
+        for _ in 0..segments {
+            let next = angle + step;
+            let (from_dir, to_dir) = (
+                Vec2::new(angle.cos(), angle.sin()),
+                Vec2::new(next.cos(), next.sin()),
+            );
+            let from = center + from_dir * radius;
+            let to = center + to_dir * radius;
+            self.cubic_to(
+                from + Vec2::new(-from_dir.y, from_dir.x) * handle,
+                to - Vec2::new(-to_dir.y, to_dir.x) * handle,
+                to,
+            );
+            angle = next;
+        }
+    }
+
+    /// Adds an ellipse as a closed sub-path, made of four cubic curves, going clockwise from the right-most point.
+    pub fn ellipse(&mut self, center: Vec2<f32>, radii: Vec2<f32>) {
+        // the distance of the control points from the ends of each quarter
+        const KAPPA: f32 = 0.552_284_8;
+        let (rx, ry) = (radii.x, radii.y);
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        let c = center;
+
+        self.move_to(c + Vec2::new(rx, 0.0));
+        self.cubic_to(
+            c + Vec2::new(rx, ky),
+            c + Vec2::new(kx, ry),
+            c + Vec2::new(0.0, ry),
+        );
+        self.cubic_to(
+            c + Vec2::new(-kx, ry),
+            c + Vec2::new(-rx, ky),
+            c + Vec2::new(-rx, 0.0),
+        );
+        self.cubic_to(
+            c + Vec2::new(-rx, -ky),
+            c + Vec2::new(-kx, -ry),
+            c + Vec2::new(0.0, -ry),
+        );
+        self.cubic_to(
+            c + Vec2::new(kx, -ry),
+            c + Vec2::new(rx, -ky),
+            c + Vec2::new(rx, 0.0),
+        );
+        self.close();
+    }
+
+    /// Adds a rectangle with rounded corners as a closed sub-path, going clockwise from the top-left corner.
+    ///
+    /// `radii` are the radius of each corner, in the order: top-left, top-right, bottom-right, bottom-left.
+    pub fn round_rect(&mut self, pos: Vec2<f32>, size: Vec2<f32>, radii: [f32; 4]) {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let [tl, tr, br, bl] = radii.map(|r| r.max(0.0));
+        let (l, t) = (pos.x, pos.y);
+        let (r, b) = (pos.x + size.x, pos.y + size.y);
+
+        self.move_to(Vec2::new(l, t + tl));
+        let corners = [
+            (Vec2::new(l + tl, t + tl), tl, PI),
+            (Vec2::new(r - tr, t + tr), tr, -FRAC_PI_2),
+            (Vec2::new(r - br, b - br), br, 0.0),
+            (Vec2::new(l + bl, b - bl), bl, FRAC_PI_2),
+        ];
+        for (center, radius, start_angle) in corners {
+            if radius > 0.0 {
+                self.arc(center, radius, start_angle, FRAC_PI_2);
+            } else {
+                self.line_to(center);
+            }
+        }
+        self.close();
+    }
+
+    /// Flattens the curves of this path into lines, so that no point on a curve
+    /// is further than `tolerance` pixels from the lines.
+    pub fn flatten(&self, tolerance: f32) -> Vec<SubPath> {
+        let mut sub_paths = Vec::new();
+        let mut current = SubPath::default();
+        let mut last = Vec2::new(0.0, 0.0);
+
+        for cmd in &self.cmds {
+            match *cmd {
+                PathCmd::MoveTo(p) => {
+                    if current.points.len() > 1 {
+                        sub_paths.push(std::mem::take(&mut current));
+                    }
+                    current.points.clear();
+                    current.points.push(p);
+                    last = p;
+                    continue;
+                }
+                PathCmd::Close => {
+                    current.closed = true;
+                    let start = current.points.first().copied().unwrap_or(last);
+                    if current.points.len() > 1 {
+                        sub_paths.push(std::mem::take(&mut current));
+                    }
+                    // anything after a close continues from the start of the closed sub-path
+                    current = SubPath::default();
+                    current.points.push(start);
+                    last = start;
+                    continue;
+                }
+                _ => {}
+            }
+            if current.points.is_empty() {
+                current.points.push(last);
+            }
+            match *cmd {
+                PathCmd::LineTo(p) => current.points.push(p),
+                PathCmd::QuadTo(c, p) => flatten_quad(last, c, p, tolerance, &mut current.points),
+                PathCmd::CubicTo(c1, c2, p) => {
+                    flatten_cubic(last, c1, c2, p, tolerance, &mut current.points)
+                }
+                PathCmd::MoveTo(_) | PathCmd::Close => unreachable!(),
+            }
+            last = *current.points.last().unwrap();
+        }
+        if current.points.len() > 1 {
+            sub_paths.push(current);
+        }
+        sub_paths
+    }
+
+    /// Flattens this path with `DEFAULT_TOLERANCE`, then rounds each sub-path into a `Poly`.
+    pub fn to_polys(&self) -> Vec<Poly> {
+        self.flatten(DEFAULT_TOLERANCE)
+            .iter()
+            .map(|sub_path| Poly {
+                points: sub_path
+                    .points
+                    .iter()
+                    .map(|p| p.map(|e| e.round() as i32))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Paths can be built directly from the outlines of glyphs.
+impl OutlineBuilder for Path {
+    fn move_to(&mut self, x: f32, y: f32) {
+        Path::move_to(self, Vec2::new(x, y));
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        Path::line_to(self, Vec2::new(x, y));
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        Path::quad_to(self, Vec2::new(x1, y1), Vec2::new(x, y));
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        Path::cubic_to(self, Vec2::new(x1, y1), Vec2::new(x2, y2), Vec2::new(x, y));
+    }
+    fn close(&mut self) {
+        Path::close(self);
+    }
+}
+
+/// A flattened piece of a `Path`, made of only straight lines.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SubPath {
+    pub points: Vec<Vec2<f32>>,
+    /// If the last point connects back to the first.
+    pub closed: bool,
+}
+impl AsRef<[Vec2<f32>]> for SubPath {
+    fn as_ref(&self) -> &[Vec2<f32>] {
+        &self.points
+    }
+}
+
+/// Flattens a quadratic Bézier curve into lines, pushing every point after `from` into `out`.
+///
+/// Enough points are used so that no point on the curve is further than `tolerance` from the lines.
+pub fn flatten_quad(
+    from: Vec2<f32>,
+    ctrl: Vec2<f32>,
+    to: Vec2<f32>,
+    tolerance: f32,
+    out: &mut Vec<Vec2<f32>>,
+) {
+    // the lines stray furthest from the curve by (|from - 2ctrl + to| / 4) / n^2
+    let dd = (from - ctrl * 2.0 + to).len() as f32;
+    let n = segment_count(dd / (4.0 * tolerance));
+
+    for i in 1..=n {
+        let t = i as f32 / n as f32;
+        let mt = 1.0 - t;
+        out.push(from * (mt * mt) + ctrl * (2.0 * mt * t) + to * (t * t));
+    }
+}
+
+/// Flattens a cubic Bézier curve into lines, pushing every point after `from` into `out`.
+///
+/// Enough points are used so that no point on the curve is further than `tolerance` from the lines.
+pub fn flatten_cubic(
+    from: Vec2<f32>,
+    ctrl1: Vec2<f32>,
+    ctrl2: Vec2<f32>,
+    to: Vec2<f32>,
+    tolerance: f32,
+    out: &mut Vec<Vec2<f32>>,
+) {
+    // Wang's formula: the lines stray at most (3/4 * max second difference) / n^2 from the curve
+    let dd = (from - ctrl1 * 2.0 + ctrl2)
+        .len()
+        .max((ctrl1 - ctrl2 * 2.0 + to).len()) as f32;
+    let n = segment_count(dd * 0.75 / tolerance);
+
+    for i in 1..=n {
+        let t = i as f32 / n as f32;
+        let mt = 1.0 - t;
+        out.push(
+            from * (mt * mt * mt)
+                + ctrl1 * (3.0 * mt * mt * t)
+                + ctrl2 * (3.0 * mt * t * t)
+                + to * (t * t * t),
+        );
+    }
+}
+
+/// The number of segments needed for the error of a curve (`error / n^2`) to be within tolerance.
+fn segment_count(error: f32) -> usize {
+    if !error.is_finite() {
+        return 1;
+    }
+    (error.sqrt().ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS)
+}
+
+#[inline(always)]
+fn angle_of(v: Vec2<f32>) -> f32 {
+    v.y.atan2(v.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> Vec2<f32> {
+        Vec2::new(x, y)
+    }
+
+    /// The distance from `p` to the closest of the lines through `points`.
+    fn distance_to_lines(p: Vec2<f32>, points: &[Vec2<f32>]) -> f32 {
+        points
+            .windows(2)
+            .map(|line| {
+                let (a, b) = (line[0], line[1]);
+                let ab = b - a;
+                let t = ((p - a).dot(ab) / ab.len_sq()).clamp(0.0, 1.0) as f32;
+                (p - (a + ab * t)).len() as f32
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn curves_are_flattened_within_tolerance() {
+        let (from, ctrl1, ctrl2, to) =
+            (v(0.0, 0.0), v(30.0, 120.0), v(90.0, -60.0), v(100.0, 20.0));
+        let quad =
+            |t: f32| from * ((1.0 - t) * (1.0 - t)) + ctrl1 * (2.0 * (1.0 - t) * t) + to * (t * t);
+        let cubic = |t: f32| {
+            let mt = 1.0 - t;
+            from * (mt * mt * mt)
+                + ctrl1 * (3.0 * mt * mt * t)
+                + ctrl2 * (3.0 * mt * t * t)
+                + to * (t * t * t)
+        };
+
+        let mut last_len = (0, 0);
+        for tolerance in [4.0, 1.0, 0.25, 0.05] {
+            let mut quad_points = vec![from];
+            flatten_quad(from, ctrl1, to, tolerance, &mut quad_points);
+            let mut cubic_points = vec![from];
+            flatten_cubic(from, ctrl1, ctrl2, to, tolerance, &mut cubic_points);
+            assert_eq!(quad_points.last(), Some(&to));
+            assert_eq!(cubic_points.last(), Some(&to));
+
+            for i in 0..=200 {
+                let t = i as f32 / 200.0;
+                assert!(
+                    distance_to_lines(quad(t), &quad_points) <= tolerance,
+                    "{tolerance} {t}"
+                );
+                assert!(
+                    distance_to_lines(cubic(t), &cubic_points) <= tolerance,
+                    "{tolerance} {t}"
+                );
+            }
+            // a smaller tolerance takes more lines
+            assert!(quad_points.len() > last_len.0 && cubic_points.len() > last_len.1);
+            last_len = (quad_points.len(), cubic_points.len());
+        }
+
+        // curves that are really straight lines take only one
+        let mut straight = Vec::new();
+        flatten_cubic(
+            from,
+            v(10.0, 10.0),
+            v(20.0, 20.0),
+            v(30.0, 30.0),
+            0.25,
+            &mut straight,
+        );
+        assert_eq!(straight, [v(30.0, 30.0)]);
+    }
+
+    #[test]
+    fn huge_curves_are_capped() {
+        let mut points = Vec::new();
+        flatten_quad(v(0.0, 0.0), v(1e7, 1e7), v(2e7, 0.0), 0.25, &mut points);
+        assert_eq!(points.len(), MAX_CURVE_SEGMENTS);
+        points.clear();
+        flatten_cubic(
+            v(0.0, 0.0),
+            v(0.0, 1e7),
+            v(1e7, 1e7),
+            v(1e7, 0.0),
+            0.01,
+            &mut points,
+        );
+        assert_eq!(points.len(), MAX_CURVE_SEGMENTS);
+
+        // so is a whole path, one curve at a time
+        let mut path = Path::new();
+        path.move_to(v(0.0, 0.0));
+        path.quad_to(v(1e7, 1e7), v(2e7, 0.0));
+        path.quad_to(v(1e7, -1e7), v(0.0, 0.0));
+        let sub_paths = path.flatten(0.25);
+        assert_eq!(sub_paths.len(), 1);
+        assert_eq!(sub_paths[0].points.len(), 1 + 2 * MAX_CURVE_SEGMENTS);
+    }
+
+    #[test]
+    fn arc_to_a_corner() {
+        let mut path = Path::new();
+        path.move_to(v(0.0, 0.0));
+        path.arc_to(v(10.0, 0.0), v(10.0, 10.0), 4.0);
+        // a line to where the arc starts, then the arc around the corner
+        let PathCmd::LineTo(start) = path.cmds()[1] else {
+            panic!("{:?}", path.cmds());
+        };
+        assert!((start - v(6.0, 0.0)).len() < 1e-4, "{start:?}");
+        let end = path.current_point();
+        assert!((end - v(10.0, 4.0)).len() < 1e-4, "{end:?}");
+
+        let center = v(6.0, 4.0);
+        let points = &path.flatten(0.01)[0].points;
+        for p in &points[2..] {
+            assert!(((*p - center).len() - 4.0).abs() < 0.02, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn degenerate_arc_to() {
+        let lines_to_corner = |p2: Vec2<f32>, radius: f32| {
+            let mut path = Path::new();
+            path.move_to(v(0.0, 0.0));
+            path.arc_to(v(10.0, 0.0), p2, radius);
+            path.cmds().to_vec()
+        };
+        let line = [PathCmd::MoveTo(v(0.0, 0.0)), PathCmd::LineTo(v(10.0, 0.0))];
+        // with no radius
+        assert_eq!(lines_to_corner(v(10.0, 10.0), 0.0), line);
+        assert_eq!(lines_to_corner(v(10.0, 10.0), -3.0), line);
+        // with the points in a line, going on or turning back
+        assert_eq!(lines_to_corner(v(20.0, 0.0), 5.0), line);
+        assert_eq!(lines_to_corner(v(-5.0, 0.0), 5.0), line);
+        // with the corner on one of the other points
+        assert_eq!(lines_to_corner(v(10.0, 0.0), 5.0), line);
+
+        // an empty path just starts at the corner
+        let mut path = Path::new();
+        path.arc_to(v(10.0, 0.0), v(10.0, 10.0), 5.0);
+        assert_eq!(path.cmds(), [PathCmd::MoveTo(v(10.0, 0.0))]);
+        assert_eq!(path.current_point(), v(10.0, 0.0));
+    }
+}
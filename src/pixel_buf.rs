@@ -106,10 +106,18 @@ impl<'a> PixBufMutView<'a> {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct PixBufView<'a> {
     pub bytes: &'a [u8],
     pub size: Vec2<u32>,
 }
+impl std::fmt::Debug for PixBufView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PixBufView")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
 impl<'a> PixBufView<'a> {
     #[inline(always)]
     pub fn rect_at(&self, pos: Vec2<i32>) -> Rect {
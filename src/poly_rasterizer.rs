@@ -0,0 +1,287 @@
+use crate::graphics::Graphics;
+use crate::paint::Paint;
+use crate::vectors::Vec2;
+
+// note: like the triangle rasterizer, this expects (0, 0) to be the top-left,
+// with the points of each contour being the corners of pixels, not their centers
+
+/// The number of scanlines sampled per row of pixels when anti-aliasing.
+const AA_SUBSCANLINES: usize = 8;
+
+/// Decides which parts of a shape are inside it, for shapes with overlapping or nested contours.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum FillRule {
+    /// A point is inside if the contours wind around it a non-zero number of times,
+    /// so contours wound in opposite directions cut holes in each other.
+    #[default]
+    NonZero,
+    /// A point is inside if a ray from it crosses an odd number of edges,
+    /// so any contour nested inside another is a hole.
+    EvenOdd,
+}
+impl FillRule {
+    #[inline(always)]
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+struct Edge {
+    x0: f32,
+    y0: f32,
+    y1: f32,
+    /// the change in x for every step in y
+    slope: f32,
+    /// +1 if the edge goes down, -1 if it goes up
+    dir: i32,
+}
+
+fn build_edges<C: AsRef<[Vec2<f32>]>>(contours: &[C]) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for contour in contours {
+        let points = contour.as_ref();
+        for i in 0..points.len() {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            if a.y == b.y {
+                // horizontal edges never cross a scanline
+                continue;
+            }
+            let (top, bot, dir) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            edges.push(Edge {
+                x0: top.x,
+                y0: top.y,
+                y1: bot.y,
+                slope: (bot.x - top.x) / (bot.y - top.y),
+                dir,
+            });
+        }
+    }
+    edges
+}
+
+/// Finds the spans of a scanline at `y` that are inside the shape, and calls `f` with the start and end x of each.
+fn scanline_spans(
+    edges: &[Edge],
+    y: f32,
+    rule: FillRule,
+    crossings: &mut Vec<(f32, i32)>,
+    mut f: impl FnMut(f32, f32),
+) {
+    crossings.clear();
+    for edge in edges {
+        // edges include their top, but not their bottom, so a shared vertex is only counted once
+        if y >= edge.y0 && y < edge.y1 {
+            crossings.push((edge.x0 + (y - edge.y0) * edge.slope, edge.dir));
+        }
+    }
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut winding = 0;
+    let mut span_start = 0.0;
+    for &(x, dir) in crossings.iter() {
+        let was_inside = rule.is_inside(winding);
+        winding += dir;
+        match (was_inside, rule.is_inside(winding)) {
+            (false, true) => span_start = x,
+            (true, false) => f(span_start, x),
+            _ => {}
+        }
+    }
+}
+
+/// Rasters the shape made by one or more contours, with each contour being a closed loop of points.
+///
+/// Pixels are filled if their centers are inside the shape, decided by `rule`.
+pub fn raster_polys<C: AsRef<[Vec2<f32>]>>(
+    g: &mut Graphics,
+    contours: &[C],
+    rule: FillRule,
+    paint: &Paint,
+) {
+    let edges = build_edges(contours);
+    let Some((y0, y1)) = row_range(g, &edges) else {
+        return;
+    };
+    let width = g.size().x as i32;
+    let mut crossings = Vec::new();
+
+    for y in y0..y1 {
+        scanline_spans(
+            &edges,
+            y as f32 + 0.5,
+            rule,
+            &mut crossings,
+            |start, end| {
+                // the first and last pixels whose centers are within the span
+                let x0 = (start - 0.5).ceil() as i32;
+                let x1 = (end - 0.5).ceil() as i32 - 1;
+                if x1 >= x0 {
+                    g.paint_row(y, x0.max(0), x1.min(width - 1), paint);
+                }
+            },
+        );
+    }
+}
+
+/// Rasters the shape made by one or more contours, with each contour being a closed loop of points.
+///
+/// Each pixel is blended by how much of it is inside the shape, which is found by sampling
+/// several scanlines per row, and measuring exactly how much of each pixel every span covers.
+pub fn raster_polys_aa<C: AsRef<[Vec2<f32>]>>(
+    g: &mut Graphics,
+    contours: &[C],
+    rule: FillRule,
+    paint: &Paint,
+) {
+    let edges = build_edges(contours);
+    let Some((y0, y1)) = row_range(g, &edges) else {
+        return;
+    };
+    let width = g.size().x as usize;
+    let mut crossings = Vec::new();
+    let mut coverage = vec![0.0f32; width];
+    let sample_weight = 1.0 / AA_SUBSCANLINES as f32;
+
+    for y in y0..y1 {
+        let (mut min_x, mut max_x) = (width, 0);
+
+        for sub in 0..AA_SUBSCANLINES {
+            let sy = y as f32 + (sub as f32 + 0.5) * sample_weight;
+            scanline_spans(&edges, sy, rule, &mut crossings, |start, end| {
+                let start = start.clamp(0.0, width as f32);
+                let end = end.clamp(0.0, width as f32);
+                if end <= start {
+                    return;
+                }
+                let (first, last) = (start as usize, (end.ceil() as usize).min(width) - 1);
+                min_x = min_x.min(first);
+                max_x = max_x.max(last);
+
+                if first == last {
+                    coverage[first] += (end - start) * sample_weight;
+                    return;
+                }
+                coverage[first] += (first as f32 + 1.0 - start) * sample_weight;
+                for c in &mut coverage[first + 1..last] {
+                    *c += sample_weight;
+                }
+                coverage[last] += (end - last as f32) * sample_weight;
+            });
+        }
+
+        if min_x > max_x {
+            continue;
+        }
+        for (x, c) in coverage[min_x..=max_x].iter_mut().enumerate() {
+            if *c > 0.0 {
+                g.paint_pixel_coverage(Vec2::new((min_x + x) as i32, y), paint, *c);
+            }
+            *c = 0.0;
+        }
+    }
+}
+
+/// The range of rows that the edges could cover, clipped to the graphics' buffer.
+fn row_range(g: &Graphics, edges: &[Edge]) -> Option<(i32, i32)> {
+    if edges.is_empty() || g.size().x == 0 {
+        return None;
+    }
+    let top = edges.iter().map(|e| e.y0).fold(f32::INFINITY, f32::min);
+    let bot = edges.iter().map(|e| e.y1).fold(f32::NEG_INFINITY, f32::max);
+    let y0 = (top.floor() as i32).max(0);
+    let y1 = (bot.ceil() as i32).min(g.size().y as i32);
+    (y0 < y1).then_some((y0, y1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Color, Image};
+
+    /// Fills the contours in white (with or without anti-aliasing), and returns the alpha of each pixel.
+    fn fill(contours: &[Vec<Vec2<f32>>], rule: FillRule, aa: bool) -> Vec<Vec<u8>> {
+        let mut image = Image::empty(Vec2::new(20, 20));
+        {
+            let mut g = image.create_graphics();
+            let paint = Paint::from(Color::WHITE);
+            if aa {
+                raster_polys_aa(&mut g, contours, rule, &paint);
+            } else {
+                raster_polys(&mut g, contours, rule, &paint);
+            }
+        }
+        image
+            .bytes
+            .chunks(20 * 4)
+            .map(|row| row.chunks(4).map(|p| p[3]).collect())
+            .collect()
+    }
+
+    fn square(from: f32, to: f32, clockwise: bool) -> Vec<Vec2<f32>> {
+        let mut points = vec![
+            Vec2::new(from, from),
+            Vec2::new(to, from),
+            Vec2::new(to, to),
+            Vec2::new(from, to),
+        ];
+        if !clockwise {
+            points.reverse();
+        }
+        points
+    }
+
+    #[test]
+    fn self_intersecting_star() {
+        // a five-pointed star drawn in one stroke, so the pentagon in the middle is wound around twice
+        let star: Vec<Vec2<f32>> = (0..5)
+            .map(|i| {
+                let angle = (i as f32 * 0.8 - 0.5) * std::f32::consts::PI;
+                Vec2::new(10.0 + angle.cos() * 9.5, 10.0 + angle.sin() * 9.5)
+            })
+            .collect();
+        let contours = [star];
+        for aa in [false, true] {
+            let non_zero = fill(&contours, FillRule::NonZero, aa);
+            let even_odd = fill(&contours, FillRule::EvenOdd, aa);
+            // the middle is only filled with the non-zero rule
+            assert_eq!((non_zero[10][10], even_odd[10][10]), (255, 0), "{aa}");
+            // the top and left points are filled either way
+            assert_eq!((non_zero[4][9], even_odd[4][9]), (255, 255), "{aa}");
+            assert_eq!((non_zero[8][5], even_odd[8][5]), (255, 255), "{aa}");
+            // and the corners of the image aren't filled at all
+            assert_eq!((non_zero[0][0], even_odd[19][0]), (0, 0), "{aa}");
+        }
+    }
+
+    #[test]
+    fn nested_contours() {
+        let (outer, inner) = ((2, 18), (6, 14));
+        for aa in [false, true] {
+            for same_winding in [true, false] {
+                let contours = [square(2.0, 18.0, true), square(6.0, 14.0, same_winding)];
+                let non_zero = fill(&contours, FillRule::NonZero, aa);
+                let even_odd = fill(&contours, FillRule::EvenOdd, aa);
+                for y in 0..20 {
+                    for x in 0..20 {
+                        let inside = |(from, to): (usize, usize)| {
+                            (from..to).contains(&x) && (from..to).contains(&y)
+                        };
+                        let (in_outer, in_inner) = (inside(outer), inside(inner));
+                        // with the even-odd rule, the inner square is always a hole
+                        let expected = in_outer && !in_inner;
+                        assert_eq!(even_odd[y][x] == 255, expected, "({x}, {y})");
+                        // with the non-zero rule, it's only a hole if it's wound the other way
+                        let expected = in_outer && (same_winding || !in_inner);
+                        assert_eq!(non_zero[y][x] == 255, expected, "({x}, {y})");
+                        // the squares are on pixel corners, so no pixels are partly covered
+                        assert!(matches!(non_zero[y][x], 0 | 255));
+                    }
+                }
+            }
+        }
+    }
+}
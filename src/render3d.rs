@@ -0,0 +1,496 @@
+use crate::graphics::{Color, Graphics};
+use crate::matrix::Mat4;
+use crate::paint::{self, Filter, Wrap};
+use crate::pixel_buf::PixBufView;
+use crate::shapes::Line;
+use crate::tri_rasterizer;
+use crate::vectors::{Vec2, Vec3, Vec4};
+
+/// Which triangles are skipped by `Renderer3d`, by which way they face the camera.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum CullMode {
+    /// Every triangle is drawn.
+    None,
+    /// Triangles facing away from the camera are skipped.
+    #[default]
+    Back,
+    /// Triangles facing the camera are skipped.
+    Front,
+}
+
+/// Draws triangles in 3D onto a `Graphics`, entirely in software.
+///
+/// Points are transformed by the model, view then projection matrices, clipped to what the camera can see,
+/// then divided by their w and mapped onto the whole buffer of the graphics.
+/// The front of a triangle is the side that its points go anti-clockwise around, like in OpenGL.
+///
+/// Filled triangles are tested against the graphics' depth buffer (see `Graphics::set_depth_buffer`),
+/// with depths from -1 at the near plane to 1 at the far plane. Wireframes aren't depth tested.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Renderer3d {
+    pub model: Mat4<f32>,
+    pub view: Mat4<f32>,
+    pub projection: Mat4<f32>,
+    pub cull: CullMode,
+}
+impl Renderer3d {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_model(mut self, model: Mat4<f32>) -> Self {
+        self.model = model;
+        self
+    }
+    pub fn with_view(mut self, view: Mat4<f32>) -> Self {
+        self.view = view;
+        self
+    }
+    pub fn with_projection(mut self, projection: Mat4<f32>) -> Self {
+        self.projection = projection;
+        self
+    }
+    pub fn with_cull(mut self, cull: CullMode) -> Self {
+        self.cull = cull;
+        self
+    }
+
+    /// The model, view and projection matrices combined.
+    #[inline(always)]
+    pub fn mvp(&self) -> Mat4<f32> {
+        self.projection * self.view * self.model
+    }
+
+    /// Where a point is drawn on a buffer of the given size, with its depth (from -1 at the near plane to 1 at the far plane),
+    /// or `None` if the camera can't see it.
+    pub fn project_point(&self, p: Vec3<f32>, size: Vec2<u32>) -> Option<Vec3<f32>> {
+        let pos = self.mvp().transform_point(p);
+        if planes(pos).iter().any(|&d| d < 0.0) {
+            return None;
+        }
+        let v = to_screen(&ClipVertex::new(pos), size);
+        Some(Vec3::new(v.pos.x, v.pos.y, v.depth))
+    }
+
+    /// Fills a triangle with a single color.
+    pub fn fill_tri(&self, g: &mut Graphics, points: [Vec3<f32>; 3], color: Color) {
+        self.raster(
+            g,
+            points,
+            [[0.0; 4]; 3],
+            [Vec2::<f32>::zero(); 3],
+            Fill::Solid(color),
+        );
+    }
+    /// Fills a triangle with a color at each of its points, blending between them across the triangle's surface.
+    pub fn fill_tri_colored(&self, g: &mut Graphics, points: [Vec3<f32>; 3], colors: [Color; 3]) {
+        let colors = colors.map(|c| [c.r, c.g, c.b, c.a].map(|e| e as f32));
+        self.raster(g, points, colors, [Vec2::<f32>::zero(); 3], Fill::Colors);
+    }
+    /// Fills a triangle with part of a texture, where `uvs` are the texture coordinates of each point
+    /// (see `Graphics::fill_tri_textured`). The texture is always mapped with perspective.
+    pub fn fill_tri_textured(
+        &self,
+        g: &mut Graphics,
+        points: [Vec3<f32>; 3],
+        uvs: [Vec2<f32>; 3],
+        texture: PixBufView,
+        filter: Filter,
+    ) {
+        self.raster(
+            g,
+            points,
+            [[0.0; 4]; 3],
+            uvs,
+            Fill::Texture(texture, filter),
+        );
+    }
+
+    /// Draws the edges of a triangle, with `Graphics::draw_line` (so with the current stroke and anti-aliasing).
+    pub fn draw_tri(&self, g: &mut Graphics, points: [Vec3<f32>; 3], color: Color) {
+        let mvp = self.mvp();
+        let clip = points.map(|p| ClipVertex::new(mvp.transform_point(p)));
+        if self.cull != CullMode::None {
+            let poly = clip_polygon(&clip);
+            if poly.len() < 3 || self.is_culled(&poly, g.size()) {
+                return;
+            }
+        }
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            if let Some((a, b)) = clip_line(clip[a], clip[b]) {
+                // rounded, like the corners of `Graphics::fill_tri_colored` are whole pixels
+                let [a, b] = [a, b].map(|v| to_screen(&v, g.size()).pos.map(|e| e.round() as i32));
+                g.draw_line(&Line(a, b), color);
+            }
+        }
+    }
+
+    fn raster(
+        &self,
+        g: &mut Graphics,
+        points: [Vec3<f32>; 3],
+        colors: [[f32; 4]; 3],
+        uvs: [Vec2<f32>; 3],
+        fill: Fill,
+    ) {
+        let mvp = self.mvp();
+        let clip = [0, 1, 2].map(|i| ClipVertex {
+            pos: mvp.transform_point(points[i]),
+            color: colors[i],
+            uv: uvs[i],
+        });
+        let poly = clip_polygon(&clip);
+        if poly.len() < 3 || self.is_culled(&poly, g.size()) {
+            return;
+        }
+        let size = g.size();
+        let screen: Vec<ScreenVertex> = poly.iter().map(|v| to_screen(v, size)).collect();
+        // the clipped polygon is convex, so it's drawn as a fan of triangles
+        for i in 1..screen.len() - 1 {
+            raster_screen_tri(g, [&screen[0], &screen[i], &screen[i + 1]], &fill);
+        }
+    }
+
+    /// If a (clipped) polygon is facing a way that is culled.
+    fn is_culled(&self, poly: &[ClipVertex], size: Vec2<u32>) -> bool {
+        if self.cull == CullMode::None {
+            return false;
+        }
+        let points: Vec<Vec2<f32>> = poly.iter().map(|v| to_screen(v, size).pos).collect();
+        // twice the signed area, which is negative for the front, since it goes anti-clockwise
+        // whichever way y points
+        let area: f32 = (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.x * b.y - a.y * b.x
+            })
+            .sum();
+        match self.cull {
+            CullMode::None => false,
+            CullMode::Back => area >= 0.0,
+            CullMode::Front => area <= 0.0,
+        }
+    }
+}
+
+/// What the inside of a triangle is filled with.
+enum Fill<'p> {
+    Solid(Color),
+    Colors,
+    Texture(PixBufView<'p>, Filter),
+}
+
+/// A point after it's been transformed, with the values that are interpolated across a triangle.
+#[derive(Clone, Copy, Debug)]
+struct ClipVertex {
+    pos: Vec4<f32>,
+    color: [f32; 4],
+    uv: Vec2<f32>,
+}
+impl ClipVertex {
+    fn new(pos: Vec4<f32>) -> Self {
+        Self {
+            pos,
+            color: [0.0; 4],
+            uv: Vec2::<f32>::zero(),
+        }
+    }
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        let mut color = a.color;
+        for (c, b) in color.iter_mut().zip(b.color) {
+            *c += (b - *c) * t;
+        }
+        Self {
+            pos: a.pos + (b.pos - a.pos) * t,
+            color,
+            uv: a.uv + (b.uv - a.uv) * t,
+        }
+    }
+}
+
+/// A point on the screen, after the perspective divide.
+struct ScreenVertex {
+    pos: Vec2<f32>,
+    depth: f32,
+    /// 1 / w, which (unlike w) changes evenly across the screen
+    inv_w: f32,
+    color: [f32; 4],
+    uv: Vec2<f32>,
+}
+
+/// Divides a point by its w, then maps it from -1 to 1 onto a buffer of the given size (with y flipped to point down).
+fn to_screen(v: &ClipVertex, size: Vec2<u32>) -> ScreenVertex {
+    let inv_w = 1.0 / v.pos.w;
+    let ndc = Vec3::new(v.pos.x, v.pos.y, v.pos.z) * inv_w;
+    ScreenVertex {
+        pos: Vec2::new(
+            (ndc.x + 1.0) * 0.5 * size.x as f32,
+            (1.0 - ndc.y) * 0.5 * size.y as f32,
+        ),
+        depth: ndc.z,
+        inv_w,
+        color: v.color,
+        uv: v.uv,
+    }
+}
+
+/// The distance of a point inside each plane of the view frustum (negative if outside it), before the perspective divide.
+#[inline(always)]
+fn planes(p: Vec4<f32>) -> [f32; 6] {
+    [
+        p.w + p.x,
+        p.w - p.x,
+        p.w + p.y,
+        p.w - p.y,
+        p.w + p.z,
+        p.w - p.z,
+    ]
+}
+
+/// Clips a triangle to the view frustum, using the Sutherland-Hodgman algorithm, which leaves a convex polygon.
+fn clip_polygon(tri: &[ClipVertex; 3]) -> Vec<ClipVertex> {
+    let mut poly = tri.to_vec();
+    let mut next = Vec::with_capacity(9);
+    for plane in 0..6 {
+        next.clear();
+        for i in 0..poly.len() {
+            let (prev, cur) = (poly[(i + poly.len() - 1) % poly.len()], poly[i]);
+            let (d_prev, d_cur) = (planes(prev.pos)[plane], planes(cur.pos)[plane]);
+            if (d_prev >= 0.0) != (d_cur >= 0.0) {
+                next.push(ClipVertex::lerp(prev, cur, d_prev / (d_prev - d_cur)));
+            }
+            if d_cur >= 0.0 {
+                next.push(cur);
+            }
+        }
+        std::mem::swap(&mut poly, &mut next);
+        if poly.is_empty() {
+            break;
+        }
+    }
+    poly
+}
+
+/// Clips a line to the view frustum, or returns `None` if none of it is inside.
+fn clip_line(a: ClipVertex, b: ClipVertex) -> Option<(ClipVertex, ClipVertex)> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (da, db) in planes(a.pos).into_iter().zip(planes(b.pos)) {
+        if da < 0.0 && db < 0.0 {
+            return None;
+        }
+        if da < 0.0 {
+            t0 = t0.max(da / (da - db));
+        } else if db < 0.0 {
+            t1 = t1.min(da / (da - db));
+        }
+    }
+    (t0 <= t1).then(|| (ClipVertex::lerp(a, b, t0), ClipVertex::lerp(a, b, t1)))
+}
+
+fn raster_screen_tri(g: &mut Graphics, verts: [&ScreenVertex; 3], fill: &Fill) {
+    let size = g.size().map(|e| e as i32);
+    let points = verts.map(|v| v.pos);
+    tri_rasterizer::tri_pixels(points, size, |pos, weights| {
+        // depth after the perspective divide changes evenly across the screen, so it uses the weights as they are
+        let depth: f32 = (0..3).map(|i| weights[i] * verts[i].depth).sum();
+        if !g.depth_test(pos, depth) {
+            return;
+        }
+        // the other values are corrected for perspective with 1 / w
+        let mut weights = [0, 1, 2].map(|i| weights[i] * verts[i].inv_w);
+        let sum: f32 = weights.iter().sum();
+        for w in &mut weights {
+            *w /= sum;
+        }
+
+        let color = match fill {
+            Fill::Solid(color) => *color,
+            Fill::Colors => {
+                let mut color = [0.0f32; 4];
+                for (i, v) in verts.iter().enumerate() {
+                    for (c, vc) in color.iter_mut().zip(v.color) {
+                        *c += vc * weights[i];
+                    }
+                }
+                Color::from(color.map(|e| e.round().clamp(0.0, 255.0) as u8))
+            }
+            Fill::Texture(texture, filter) => {
+                let mut uv = Vec2::<f32>::zero();
+                for (i, v) in verts.iter().enumerate() {
+                    uv += v.uv * weights[i];
+                }
+                let size = texture.size.map(|e| e as f32);
+                let texel = Vec2::new(uv.x * size.x, uv.y * size.y);
+                paint::sample(*texture, texel, Wrap::Repeat, *filter)
+            }
+        };
+        g.draw_pixel(pos, color);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blend::BlendMode;
+    use crate::depth::DepthBuffer;
+    use crate::graphics::Image;
+
+    fn renderer() -> Renderer3d {
+        Renderer3d::new()
+            .with_view(Mat4::look_at(
+                Vec3::new(0.0, 0.0, 3.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ))
+            .with_projection(Mat4::perspective(
+                std::f32::consts::FRAC_PI_2,
+                1.0,
+                0.1,
+                100.0,
+            ))
+    }
+
+    /// Renders with additive blending of a tiny color, and returns how many times each pixel was drawn.
+    fn render(size: u32, draw: impl FnOnce(&mut Graphics)) -> Vec<u8> {
+        let mut image = Image::empty(Vec2::new(size, size));
+        {
+            let mut g = image.create_graphics();
+            g.fill(Color::BLACK);
+            g.set_blend_mode(BlendMode::Add);
+            draw(&mut g);
+        }
+        image.bytes.chunks(4).map(|p| p[0]).collect()
+    }
+    const ONE: Color = Color::rgb(1, 1, 1);
+
+    // anti-clockwise, so facing the camera
+    const FRONT: [Vec3<f32>; 3] = [
+        Vec3::new(-1.0, -1.0, 0.0),
+        Vec3::new(1.0, -1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ];
+
+    #[test]
+    fn back_faces_are_culled() {
+        let back = [FRONT[0], FRONT[2], FRONT[1]];
+        let r = renderer();
+        let front = render(30, |g| r.fill_tri(g, FRONT, ONE));
+        // the triangle is in the middle third of the screen
+        assert_eq!(front[15 * 30 + 15], 1);
+        assert_eq!(front[2 * 30 + 15], 0);
+        assert!(render(30, |g| r.fill_tri(g, back, ONE))
+            .iter()
+            .all(|&c| c == 0));
+
+        let r = r.with_cull(CullMode::Front);
+        assert!(render(30, |g| r.fill_tri(g, FRONT, ONE))
+            .iter()
+            .all(|&c| c == 0));
+        assert_eq!(render(30, |g| r.fill_tri(g, back, ONE)), front);
+        let r = r.with_cull(CullMode::None);
+        assert_eq!(render(30, |g| r.fill_tri(g, back, ONE)), front);
+    }
+
+    #[test]
+    fn meshes_have_no_gaps_or_overlaps() {
+        // a spinning square, split into two triangles
+        let quad = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ];
+        for angle in [0.0f32, 0.3, 1.0, 2.5] {
+            let r = renderer().with_model(Mat4::rotate_y(angle * 0.5) * Mat4::rotate_z(angle));
+            let counts = render(40, |g| {
+                r.fill_tri(g, [quad[0], quad[1], quad[2]], ONE);
+                r.fill_tri(g, [quad[0], quad[2], quad[3]], ONE);
+            });
+            assert!(counts.iter().all(|&c| c <= 1), "{angle}");
+            assert_eq!(counts[20 * 40 + 20], 1);
+        }
+    }
+
+    #[test]
+    fn triangles_are_clipped_to_the_camera() {
+        let r = renderer();
+        // a huge floor that goes behind the camera
+        let floor = [
+            Vec3::new(-100.0, -1.0, 100.0),
+            Vec3::new(100.0, -1.0, 100.0),
+            Vec3::new(0.0, -1.0, -100.0),
+        ];
+        let counts = render(30, |g| r.fill_tri(g, floor, ONE));
+        // only the bottom half of the screen, below the horizon
+        assert_eq!(counts[25 * 30 + 15], 1);
+        assert_eq!(counts[5 * 30 + 15], 0);
+        assert!(counts.iter().all(|&c| c <= 1));
+
+        // entirely behind the camera
+        let behind = FRONT.map(|p| p + Vec3::new(0.0, 0.0, 5.0));
+        assert!(render(30, |g| r.fill_tri(g, behind, ONE))
+            .iter()
+            .all(|&c| c == 0));
+        assert_eq!(r.project_point(behind[0], Vec2::new(30, 30)), None);
+        let center = r
+            .project_point(Vec3::new(0.0, 0.0, 0.0), Vec2::new(30, 30))
+            .unwrap();
+        assert_eq!((center.x, center.y), (15.0, 15.0));
+    }
+
+    #[test]
+    fn wireframes_and_attributes() {
+        let r = renderer();
+        let wire = render(30, |g| r.draw_tri(g, FRONT, ONE));
+        let filled = render(30, |g| r.fill_tri(g, FRONT, ONE));
+        // the outline is around the edge of the filled triangle (which is thinner than a pixel at its corners)
+        assert_eq!(wire[15 * 30 + 15], 0);
+        assert!(wire.iter().filter(|&&c| c > 0).count() > 20);
+        for (i, &c) in wire.iter().enumerate() {
+            let (x, y) = (i % 30, i / 30);
+            if c > 0 {
+                let near_fill = (y.saturating_sub(2)..=y + 2)
+                    .flat_map(|y| (x.saturating_sub(2)..=x + 2).map(move |x| (x, y)))
+                    .any(|(x, y)| x < 30 && y < 30 && filled[y * 30 + x] > 0);
+                assert!(near_fill, "({x}, {y})");
+            }
+        }
+
+        let mut image = Image::empty(Vec2::new(30, 30));
+        {
+            let mut g = image.create_graphics();
+            r.fill_tri_colored(&mut g, FRONT, [Color::RED, Color::GREEN, Color::BLUE]);
+        }
+        let pixel = |x: usize, y: usize| &image.bytes[(y * 30 + x) * 4..][..4];
+        // red at the bottom-left, blue at the top
+        assert!(pixel(10, 19)[0] > 200 && pixel(15, 11)[2] > 200);
+    }
+
+    #[test]
+    fn nearer_triangles_cover_farther_ones() {
+        let r = renderer();
+        let near = FRONT;
+        let far = FRONT.map(|p| p * 2.0 - Vec3::new(0.0, 0.0, 1.0));
+        let mut depth = DepthBuffer::new(Vec2::new(30, 30));
+        for near_first in [true, false] {
+            depth.clear(DepthBuffer::FAR);
+            let mut image = Image::empty(Vec2::new(30, 30));
+            {
+                let mut g = image.create_graphics();
+                g.set_depth_buffer(&mut depth);
+                let (near, far) = ((near, Color::RED), (far, Color::BLUE));
+                let order = if near_first { [near, far] } else { [far, near] };
+                for (tri, color) in order {
+                    r.fill_tri(&mut g, tri, color);
+                }
+            }
+            let pixel = |x: usize, y: usize| &image.bytes[(y * 30 + x) * 4..][..4];
+            assert_eq!(pixel(15, 15), [255, 0, 0, 255], "{near_first}");
+            // the far triangle is bigger on screen, so shows around the near one
+            assert_eq!(pixel(15, 21), [0, 0, 255, 255], "{near_first}");
+        }
+        let center = r
+            .project_point(Vec3::new(0.0, 0.0, 0.0), Vec2::new(30, 30))
+            .unwrap();
+        assert!((depth.get(Vec2::new(15, 15)).unwrap() - center.z).abs() < 0.01);
+    }
+}
@@ -0,0 +1,522 @@
+use crate::fonts::{Font, TextBuilder, TextLayout};
+use crate::glyph_cache::{FontId, ShelfPacker};
+use crate::graphics::{Color, Graphics, Image};
+use crate::shapes::Rect;
+use crate::vectors::Vec2;
+use rusttype::{GlyphId, Point, PositionedGlyph, Scale};
+use std::collections::HashMap;
+
+// note: a signed distance field stores, for each pixel, the distance from its center to the nearest edge
+// of the glyph (positive inside, negative outside), which can be sampled smoothly at any size
+
+/// A glyph's distance field in the atlas of an `SdfCache`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SdfGlyph {
+    /// Where the distance field is in the atlas.
+    pub rect: Rect,
+    /// Where the top-left of the field is, relative to the glyph's origin, in pixels at the cache's base size.
+    pub offset: Vec2<f32>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct SdfCacheStats {
+    /// Lookups of glyphs that were already in the cache.
+    pub hits: u64,
+    /// Glyphs whose distance fields had to be generated.
+    pub misses: u64,
+    /// Times the atlas ran out of space, and was cleared.
+    pub clears: u64,
+}
+
+/// Caches the signed distance fields of glyphs in a single atlas.
+///
+/// Fields are generated once, at `base_size`, then scaled to draw text at any size.
+pub struct SdfCache {
+    /// The distance fields, with the distance in the alpha channel of each pixel.
+    /// 255 is `spread` pixels inside, 0 is `spread` pixels outside, and the edge is halfway between.
+    atlas: Image,
+    entries: HashMap<(FontId, GlyphId), Option<SdfGlyph>>,
+    packer: ShelfPacker,
+    base_size: f32,
+    spread: f32,
+    stats: SdfCacheStats,
+}
+impl SdfCache {
+    pub const DEFAULT_ATLAS_SIZE: Vec2<u32> = Vec2::new(1024, 1024);
+    pub const DEFAULT_BASE_SIZE: f32 = 48.0;
+    pub const DEFAULT_SPREAD: f32 = 8.0;
+
+    pub fn new() -> Self {
+        Self::with_size(
+            Self::DEFAULT_ATLAS_SIZE,
+            Self::DEFAULT_BASE_SIZE,
+            Self::DEFAULT_SPREAD,
+        )
+    }
+    /// Constructs a cache with an atlas of `atlas_size` pixels, that generates fields for glyphs `base_size` pixels high,
+    /// measuring distances up to `spread` pixels (at the base size) from the edges of glyphs.
+    ///
+    /// Effects (outlines, glows and shadows) can't reach further than the spread, scaled to the size of the text.
+    pub fn with_size(atlas_size: Vec2<u32>, base_size: f32, spread: f32) -> Self {
+        Self {
+            atlas: Image::empty(atlas_size),
+            entries: HashMap::new(),
+            packer: ShelfPacker::new(atlas_size),
+            base_size,
+            spread,
+            stats: SdfCacheStats::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn atlas(&self) -> &Image {
+        &self.atlas
+    }
+    #[inline(always)]
+    pub fn base_size(&self) -> f32 {
+        self.base_size
+    }
+    #[inline(always)]
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+    #[inline(always)]
+    pub fn stats(&self) -> SdfCacheStats {
+        self.stats
+    }
+    /// The number of glyphs currently cached.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every glyph.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.packer = ShelfPacker::new(self.atlas.size);
+    }
+
+    /// Finds the distance field of a glyph, generating it if it isn't cached.
+    ///
+    /// Returns `None` if the glyph has no outline, or its field is too big for the atlas.
+    pub fn get_or_insert(&mut self, font: &Font, glyph: GlyphId) -> Option<SdfGlyph> {
+        let key = (FontId::of(font), glyph);
+        if let Some(entry) = self.entries.get(&key) {
+            self.stats.hits += 1;
+            return entry.clone();
+        }
+        self.stats.misses += 1;
+
+        let positioned = font
+            .glyph(glyph)
+            .scaled(Scale::uniform(self.base_size))
+            .positioned(Point { x: 0.0, y: 0.0 });
+        let Some(bb) = positioned.pixel_bounding_box() else {
+            self.entries.insert(key, None);
+            return None;
+        };
+        let pad = self.spread.ceil() as i32;
+        let size = Vec2::new(
+            (bb.width() + pad * 2) as u32,
+            (bb.height() + pad * 2) as u32,
+        );
+        let pos = match self.packer.alloc(size) {
+            Some(pos) => pos,
+            None => {
+                // fields don't depend on the size of text, so only a few are ever needed at once
+                self.stats.clears += 1;
+                self.clear();
+                self.packer.alloc(size)?
+            }
+        };
+
+        let field = glyph_field(&positioned, size, pad as f32, self.spread);
+        let width = self.atlas.size.x as usize;
+        for y in 0..size.y as usize {
+            for x in 0..size.x as usize {
+                let index = ((pos.y as usize + y) * width + pos.x as usize + x) * 4;
+                self.atlas.bytes[index..index + 4].copy_from_slice(&[
+                    255,
+                    255,
+                    255,
+                    field[y * size.x as usize + x],
+                ]);
+            }
+        }
+
+        let entry = SdfGlyph {
+            rect: Rect::new(pos.x as i32, pos.y as i32, size.x as i32, size.y as i32),
+            offset: Vec2::new((bb.min.x - pad) as f32, (bb.min.y - pad) as f32),
+        };
+        self.entries.insert(key, Some(entry.clone()));
+        Some(entry)
+    }
+
+    /// The distance (in pixels at the base size, positive inside the glyph) at a point in the atlas,
+    /// interpolated between the centers of the pixels around it.
+    pub fn sample(&self, glyph: &SdfGlyph, pos: Vec2<f32>) -> f32 {
+        let x = pos.x - 0.5;
+        let y = pos.y - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let texel = |dx: i32, dy: i32| -> f32 {
+            let (px, py) = (x0 as i32 + dx, y0 as i32 + dy);
+            if px < 0 || py < 0 || px >= glyph.rect.w || py >= glyph.rect.h {
+                return 0.0;
+            }
+            let index = ((glyph.rect.y + py) as usize * self.atlas.size.x as usize
+                + (glyph.rect.x + px) as usize)
+                * 4;
+            self.atlas.bytes[index + 3] as f32
+        };
+        let top = texel(0, 0) * (1.0 - tx) + texel(1, 0) * tx;
+        let bot = texel(0, 1) * (1.0 - tx) + texel(1, 1) * tx;
+        let value = top * (1.0 - ty) + bot * ty;
+        (value / 127.5 - 1.0) * self.spread
+    }
+}
+impl Default for SdfCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generates the distance field of a glyph, from its outline built with a `TextBuilder`,
+/// with the glyph `pad` pixels from the top-left of the field.
+fn glyph_field(glyph: &PositionedGlyph, size: Vec2<u32>, pad: f32, spread: f32) -> Vec<u8> {
+    let mut builder = TextBuilder::new().with_tolerance(0.1);
+    builder.glyph_pos = Vec2::new(pad, pad);
+    glyph.build_outline(&mut builder);
+    let contours = builder.finish();
+
+    let edges: Vec<(Vec2<f32>, Vec2<f32>)> = contours
+        .iter()
+        .flat_map(|contour| {
+            let points = &contour.points;
+            (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+        })
+        .collect();
+
+    let mut field = Vec::with_capacity((size.x * size.y) as usize);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let mut dist_sq = f32::INFINITY;
+            let mut winding = 0;
+            for &(a, b) in &edges {
+                dist_sq = dist_sq.min(segment_dist_sq(p, a, b));
+                // the winding of a ray going right from `p`
+                if (a.y <= p.y) != (b.y <= p.y) {
+                    let cross_x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                    if cross_x > p.x {
+                        winding += if b.y > a.y { 1 } else { -1 };
+                    }
+                }
+            }
+            let dist = if winding != 0 {
+                dist_sq.sqrt()
+            } else {
+                -dist_sq.sqrt()
+            };
+            field.push(((dist / spread + 1.0) * 127.5).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    field
+}
+
+fn segment_dist_sq(p: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    let ab = b - a;
+    let ap = p - a;
+    let len_sq = ab.x * ab.x + ab.y * ab.y;
+    let t = if len_sq == 0.0 {
+        0.0
+    } else {
+        ((ap.x * ab.x + ap.y * ab.y) / len_sq).clamp(0.0, 1.0)
+    };
+    let d = ap - ab * t;
+    d.x * d.x + d.y * d.y
+}
+
+/// How text drawn from distance fields looks.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SdfStyle {
+    pub color: Color,
+    /// The width (in pixels) and color of a line around the outside of each glyph.
+    pub outline: Option<(f32, Color)>,
+    /// The radius (in pixels) and color of a glow that fades out around each glyph.
+    pub glow: Option<(f32, Color)>,
+    pub shadow: Option<SdfShadow>,
+}
+impl SdfStyle {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            outline: None,
+            glow: None,
+            shadow: None,
+        }
+    }
+
+    pub fn with_outline(mut self, width: f32, color: Color) -> Self {
+        self.outline = Some((width, color));
+        self
+    }
+    pub fn with_glow(mut self, radius: f32, color: Color) -> Self {
+        self.glow = Some((radius, color));
+        self
+    }
+    pub fn with_shadow(mut self, offset: Vec2<f32>, softness: f32, color: Color) -> Self {
+        self.shadow = Some(SdfShadow {
+            offset,
+            softness,
+            color,
+        });
+        self
+    }
+}
+
+/// A copy of the text drawn behind it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SdfShadow {
+    /// How far the shadow is from the text, in pixels.
+    pub offset: Vec2<f32>,
+    /// How many pixels the edge of the shadow fades over (0 is a sharp edge).
+    pub softness: f32,
+    pub color: Color,
+}
+
+/// The layers of SDF text, drawn one after another (for every glyph) so each layer is behind the next.
+#[derive(Clone, Copy)]
+enum Layer {
+    Shadow(SdfShadow),
+    Glow(f32, Color),
+    Outline(f32, Color),
+    Fill(Color),
+}
+impl Layer {
+    /// The coverage of a pixel by this layer, from the distance (in pixels) of the pixel to the glyph.
+    fn coverage(self, dist: f32) -> f32 {
+        match self {
+            Self::Shadow(shadow) => (0.5 + dist / (1.0 + shadow.softness)).clamp(0.0, 1.0),
+            Self::Glow(radius, _) => {
+                let t = (1.0 + dist / radius.max(0.001)).clamp(0.0, 1.0);
+                t * t
+            }
+            Self::Outline(width, _) => (dist + width + 0.5).clamp(0.0, 1.0),
+            Self::Fill(_) => (dist + 0.5).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Draws `text` from distance fields, with `pos` being the top-left of the first line.
+pub(crate) fn draw_sdf_text(
+    g: &mut Graphics,
+    cache: &mut SdfCache,
+    text: &str,
+    font: &Font,
+    size: f32,
+    pos: Vec2<f32>,
+    style: &SdfStyle,
+) {
+    let laid_out = TextLayout::new(size).layout(text, font, pos);
+    let scale = size / cache.base_size;
+
+    let mut layers = Vec::new();
+    if let Some(shadow) = style.shadow {
+        layers.push(Layer::Shadow(shadow));
+    }
+    if let Some((radius, color)) = style.glow {
+        layers.push(Layer::Glow(radius, color));
+    }
+    if let Some((width, color)) = style.outline {
+        layers.push(Layer::Outline(width, color));
+    }
+    layers.push(Layer::Fill(style.color));
+
+    for layer in layers {
+        let (color, shift) = match layer {
+            Layer::Shadow(shadow) => (shadow.color, shadow.offset),
+            Layer::Glow(_, color) | Layer::Outline(_, color) | Layer::Fill(color) => {
+                (color, Vec2::new(0.0, 0.0))
+            }
+        };
+        for positioned in &laid_out.glyphs {
+            // each glyph is looked up right before it's drawn, since looking up another glyph
+            // could clear the atlas (if the text has more glyphs than fit in it)
+            let Some(glyph) = cache.get_or_insert(font, positioned.id()) else {
+                continue;
+            };
+            let origin = positioned.position();
+            let origin = Vec2::new(origin.x, origin.y) + shift;
+            let top_left = origin + glyph.offset * scale;
+            let bot_right = top_left + Vec2::new(glyph.rect.w as f32, glyph.rect.h as f32) * scale;
+
+            let x0 = (top_left.x.floor() as i32).max(0);
+            let y0 = (top_left.y.floor() as i32).max(0);
+            let x1 = (bot_right.x.ceil() as i32).min(g.size().x as i32);
+            let y1 = (bot_right.y.ceil() as i32).min(g.size().y as i32);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let field_pos = (center - top_left) / scale;
+                    let dist = cache.sample(&glyph, field_pos) * scale;
+                    let coverage = layer.coverage(dist);
+                    if coverage > 0.0 {
+                        g.draw_pixel_coverage(Vec2::new(x, y), color, coverage);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts;
+    use crate::path;
+    use crate::poly_rasterizer::FillRule;
+
+    const FONT: &[u8] = include_bytes!("../tests/fonts/Cantarell-VF.otf");
+
+    fn draw(size: f32, style: SdfStyle, cache: &mut SdfCache) -> Image {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut image = Image::empty(Vec2::new(120, 80));
+        {
+            let mut g = image.create_graphics();
+            g.draw_sdf_text(cache, "Ho", &font, size, Vec2::new(10.0, 10.0), &style);
+        }
+        image
+    }
+
+    fn count(image: &Image, f: impl Fn(&[u8]) -> bool) -> usize {
+        image.bytes.chunks(4).filter(|p| f(p)).count()
+    }
+
+    #[test]
+    fn field_of_a_ring() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut cache = SdfCache::new();
+        let glyph = cache.get_or_insert(&font, font.glyph('o').id()).unwrap();
+        let size = Vec2::new(glyph.rect.w as f32, glyph.rect.h as f32);
+
+        // the middle of an 'o' is a hole, so it's outside the glyph
+        assert!(cache.sample(&glyph, size * 0.5) < -1.0);
+        // the corners of the field are `spread` away, or further
+        assert_eq!(cache.sample(&glyph, Vec2::new(0.5, 0.5)), -cache.spread());
+        // the left of the ring is inside
+        let left_of_ring = (0..glyph.rect.w)
+            .map(|x| cache.sample(&glyph, Vec2::new(x as f32 + 0.5, size.y * 0.5)))
+            .fold(f32::NEG_INFINITY, f32::max);
+        assert!(left_of_ring > 0.5);
+    }
+
+    #[test]
+    fn fields_are_cached_across_sizes() {
+        let mut cache = SdfCache::new();
+        draw(20.0, SdfStyle::new(Color::WHITE), &mut cache);
+        draw(60.0, SdfStyle::new(Color::WHITE), &mut cache);
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().hits, 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn matches_rasterized_text() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let mut cache = SdfCache::new();
+        for size in [16.0, 48.0] {
+            let sdf = draw(size, SdfStyle::new(Color::WHITE), &mut cache);
+            // the outlines filled directly (rusttype's own rasterizer can't fill unclosed CFF2 contours)
+            let mut raster = Image::empty(Vec2::new(120, 80));
+            let laid_out = TextLayout::new(size).layout("Ho", &font, Vec2::new(10.0, 10.0));
+            let contours = fonts::build_laid_out_text(&laid_out, path::DEFAULT_TOLERANCE);
+            let mut g = raster.create_graphics();
+            g.set_anti_alias(true);
+            g.fill_polys_f32(&contours, FillRule::NonZero, Color::WHITE);
+
+            let ink = |image: &Image| {
+                image
+                    .bytes
+                    .iter()
+                    .skip(3)
+                    .step_by(4)
+                    .map(|&a| a as f32)
+                    .sum::<f32>()
+            };
+            let ratio = ink(&sdf) / ink(&raster);
+            assert!((0.95..1.05).contains(&ratio), "{size}: {ratio}");
+        }
+    }
+
+    #[test]
+    fn effects() {
+        let mut cache = SdfCache::new();
+        let red = Color::rgb(255, 0, 0);
+        let blue = Color::rgb(0, 0, 255);
+        let plain = draw(30.0, SdfStyle::new(Color::WHITE), &mut cache);
+        let is_red = |p: &[u8]| p == [255, 0, 0, 255];
+
+        let outlined = draw(
+            30.0,
+            SdfStyle::new(Color::WHITE).with_outline(2.0, red),
+            &mut cache,
+        );
+        assert!(count(&outlined, is_red) > 0);
+        // the outline is around the text, not over it
+        assert_eq!(
+            count(&outlined, |p| p == [255, 255, 255, 255]),
+            count(&plain, |p| p == [255, 255, 255, 255])
+        );
+
+        let shadowed = draw(
+            30.0,
+            SdfStyle::new(Color::WHITE).with_shadow(Vec2::new(3.0, 3.0), 0.0, blue),
+            &mut cache,
+        );
+        // the shadow is the same shape as the text, partly covered by it
+        let shadow = count(&shadowed, |p| p[2] == 255 && p[0] == 0 && p[3] == 255);
+        assert!(shadow > 0 && shadow < count(&plain, |p| p[3] == 255));
+
+        let glowing = draw(
+            30.0,
+            SdfStyle::new(Color::WHITE).with_glow(4.0, red),
+            &mut cache,
+        );
+        // the glow fades, so there are translucent red pixels around the text
+        assert!(
+            count(&glowing, |p| p[0] == 255
+                && p[1] == 0
+                && p[3] > 0
+                && p[3] < 255)
+                > 0
+        );
+        assert!(count(&glowing, |p| p[3] > 0) > count(&plain, |p| p[3] > 0));
+    }
+
+    #[test]
+    fn text_with_more_glyphs_than_fit_in_the_atlas() {
+        let font = Font::try_from_bytes(FONT).unwrap();
+        let style = SdfStyle::new(Color::WHITE).with_outline(1.5, Color::rgb(255, 0, 0));
+        let draw_with = |cache: &mut SdfCache| {
+            let mut image = Image::empty(Vec2::new(160, 60));
+            {
+                let mut g = image.create_graphics();
+                g.draw_sdf_text(cache, "Hoxa", &font, 30.0, Vec2::new(4.0, 10.0), &style);
+            }
+            image
+        };
+        let expected = draw_with(&mut SdfCache::new());
+
+        // an atlas with only room for one field at a time, so it's cleared partway through the text
+        let mut small = SdfCache::with_size(Vec2::new(64, 64), 48.0, 8.0);
+        let image = draw_with(&mut small);
+        assert!(small.stats().clears > 0);
+        assert!(image.bytes == expected.bytes);
+    }
+}
@@ -231,6 +231,53 @@ impl From<&Rect> for [i32; 4] {
     }
 }
 
+/// The radius of each corner of a rounded rectangle, in pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CornerRadii {
+    pub tl: i32,
+    pub tr: i32,
+    pub br: i32,
+    pub bl: i32,
+}
+impl CornerRadii {
+    pub const fn new(tl: i32, tr: i32, br: i32, bl: i32) -> Self {
+        Self { tl, tr, br, bl }
+    }
+    /// The same radius for every corner.
+    pub const fn all(radius: i32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    /// Returns these radii shrunk to fit a rectangle of the given size.
+    ///
+    /// Negative radii become 0, and like in CSS, if the two corners along any side would overlap,
+    /// all of the radii are scaled down by the same amount so that they don't.
+    pub fn clamped(self, size: Vec2<i32>) -> Self {
+        let [tl, tr, br, bl] = [self.tl, self.tr, self.br, self.bl].map(|r| r.max(0));
+        let (w, h) = (size.x.max(0), size.y.max(0));
+
+        let mut scale = 1.0f32;
+        for (side, a, b) in [(w, tl, tr), (h, tr, br), (w, br, bl), (h, bl, tl)] {
+            if a + b > side {
+                scale = scale.min(side as f32 / (a + b) as f32);
+            }
+        }
+        let scaled = |r: i32| (r as f32 * scale).floor() as i32;
+        Self::new(scaled(tl), scaled(tr), scaled(br), scaled(bl))
+    }
+}
+impl From<i32> for CornerRadii {
+    fn from(radius: i32) -> Self {
+        Self::all(radius)
+    }
+}
+impl From<[i32; 4]> for CornerRadii {
+    /// The radii in the order: top-left, top-right, bottom-right, bottom-left.
+    fn from([tl, tr, br, bl]: [i32; 4]) -> Self {
+        Self::new(tl, tr, br, bl)
+    }
+}
+
 pub fn remove_dup_points<T: PartialEq>(points: &mut Vec<Vec2<T>>) {
     for i in (0..points.len()).rev() {
         let mut dup = None;
@@ -0,0 +1,584 @@
+use crate::vectors::{Vec2, VecMath};
+
+/// The largest distance, in pixels, that the triangles of a round join or cap may be from the true curve.
+const ROUND_TOLERANCE: f32 = 0.25;
+
+/// How two segments of a stroke are connected.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum LineJoin {
+    /// The outer edges are extended until they meet (see `Stroke::miter_limit`).
+    #[default]
+    Miter,
+    /// The outer corner is filled with a circular arc.
+    Round,
+    /// The outer corners are connected with a straight line.
+    Bevel,
+}
+
+/// How the ends of an open stroke are drawn.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum LineCap {
+    /// The stroke ends exactly at its end points.
+    #[default]
+    Butt,
+    /// The stroke ends with a half circle around its end points.
+    Round,
+    /// The stroke extends past its end points by half its width.
+    Square,
+}
+
+/// Describes how the outline of a shape is drawn.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Stroke {
+    /// The width of the stroke, in pixels.
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// The longest a miter join can be, relative to the stroke's width,
+    /// before it is drawn as a bevel join instead.
+    pub miter_limit: f32,
+    /// Alternating lengths of the dashes and the gaps between them, in pixels.
+    /// An empty pattern draws a solid line.
+    ///
+    /// A pattern with an odd number of lengths is repeated to make it even, so `[4.0]` is the same as `[4.0, 4.0]`.
+    pub dash: Vec<f32>,
+    /// How far into the dash pattern the stroke starts.
+    /// Increasing this every frame makes the dashes move along the line ("marching ants").
+    pub dash_offset: f32,
+}
+impl Stroke {
+    pub const fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 10.0,
+            dash: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    /// Sets the dash pattern (see `Stroke::dash`) and where in the pattern the stroke starts.
+    pub fn with_dash(mut self, dash: &[f32], offset: f32) -> Self {
+        self.dash = dash.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+    pub fn with_dash_offset(mut self, offset: f32) -> Self {
+        self.dash_offset = offset;
+        self
+    }
+
+    /// If this stroke is no wider than a pixel, in which case it (or each of its dashes) is drawn with 1px lines.
+    #[inline(always)]
+    pub fn is_hairline(&self) -> bool {
+        self.width <= 1.0
+    }
+    /// If this stroke has a dash pattern that would make any gaps.
+    pub fn is_dashed(&self) -> bool {
+        let total: f32 = self.dash.iter().sum();
+        total > 0.0 && total.is_finite() && self.dash.iter().all(|len| *len >= 0.0)
+    }
+    /// If this stroke is a solid hairline, so it can be drawn with plain 1px lines.
+    #[inline(always)]
+    pub fn is_plain(&self) -> bool {
+        self.is_hairline() && !self.is_dashed()
+    }
+}
+impl Default for Stroke {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// Expands the stroke of a line through `points` into triangles, which together cover the stroke.
+///
+/// If `closed` is true, the last point is joined back to the first, and no caps are added.
+/// The triangles of neighboring segments and joins overlap a little, but they are all wound the same way,
+/// so filling them together with `FillRule::NonZero` covers each pixel once.
+pub fn stroke_to_tris(points: &[Vec2<f32>], closed: bool, stroke: &Stroke) -> Vec<[Vec2<f32>; 3]> {
+    let mut points = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    let hw = stroke.width * 0.5;
+    let mut tris = Vec::new();
+
+    if points.len() == 1 {
+        // a stroke with no length only shows its caps
+        let p = points[0];
+        let right = Vec2::new(hw, 0.0);
+        let down = Vec2::new(0.0, hw);
+        match stroke.cap {
+            LineCap::Butt => {}
+            LineCap::Round => push_arc_fan(&mut tris, p, right, std::f32::consts::TAU),
+            LineCap::Square => push_quad(
+                &mut tris,
+                [
+                    p - right - down,
+                    p + right - down,
+                    p + right + down,
+                    p - right + down,
+                ],
+            ),
+        }
+        return tris;
+    }
+    if points.len() < 2 {
+        return tris;
+    }
+
+    let segment_count = if closed && points.len() > 2 {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+    let direction = |i: usize| {
+        let (from, to) = segment(i);
+        (to - from).norm_or_zero()
+    };
+
+    for i in 0..segment_count {
+        let (from, to) = segment(i);
+        let n = perp(direction(i)) * hw;
+        push_quad(&mut tris, [from + n, to + n, to - n, from - n]);
+    }
+
+    // joins between each pair of connected segments
+    let joins = if closed && segment_count == points.len() {
+        0..segment_count
+    } else {
+        1..segment_count
+    };
+    for i in joins {
+        let prev = (i + segment_count - 1) % segment_count;
+        push_join(
+            &mut tris,
+            points[i],
+            direction(prev),
+            direction(i),
+            hw,
+            stroke,
+        );
+    }
+
+    if !(closed && segment_count == points.len()) {
+        let first_dir = direction(0);
+        let last_dir = direction(segment_count - 1);
+        push_cap(&mut tris, points[0], first_dir * -1.0, hw, stroke.cap);
+        push_cap(&mut tris, points[segment_count], last_dir, hw, stroke.cap);
+    }
+    tris
+}
+
+/// Splits a line through `points` into its dashes, by walking along its length through the `dash` pattern.
+///
+/// Each dash is returned as the points of an open line. A dash of length 0 has a single point.
+/// If `closed` is true, the last point is connected back to the first.
+pub fn dash_polyline(
+    points: &[Vec2<f32>],
+    closed: bool,
+    dash: &[f32],
+    offset: f32,
+) -> Vec<Vec<Vec2<f32>>> {
+    let mut points = points.to_vec();
+    if closed && points.len() > 2 {
+        points.push(points[0]);
+    }
+    let pattern = if dash.len() % 2 == 1 {
+        dash.repeat(2)
+    } else {
+        dash.to_vec()
+    };
+    // patterns that wouldn't make any gaps (like `Stroke::is_dashed`) draw the line solid
+    let total: f32 = pattern.iter().sum();
+    if points.is_empty()
+        || !(total > 0.0 && total.is_finite())
+        || pattern.iter().any(|len| *len < 0.0)
+    {
+        return vec![points];
+    }
+
+    // find where in the pattern the offset starts
+    let mut index = 0;
+    let mut phase = offset.rem_euclid(total);
+    while phase >= pattern[index] && phase > 0.0 {
+        phase -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+    let mut remaining = pattern[index] - phase;
+    let mut on = index % 2 == 0;
+
+    let mut dashes = Vec::new();
+    let mut current = Vec::new();
+    if on {
+        current.push(points[0]);
+    }
+    for i in 1..points.len() {
+        let (from, to) = (points[i - 1], points[i]);
+        let len = (to - from).len() as f32;
+        let dir = (to - from).norm_or_zero();
+
+        // switch between dashes and gaps for as long as the current pattern entry ends within this segment
+        let mut dist = 0.0;
+        while remaining <= len - dist {
+            dist += remaining;
+            current.push(from + dir * dist);
+            if on {
+                dashes.push(std::mem::take(&mut current));
+            }
+            on = !on;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= len - dist;
+        if on {
+            current.push(to);
+        }
+    }
+    if on && !current.is_empty() {
+        dashes.push(current);
+    }
+    for dash in &mut dashes {
+        dash.dedup();
+    }
+    dashes
+}
+
+fn push_join(
+    tris: &mut Vec<[Vec2<f32>; 3]>,
+    p: Vec2<f32>,
+    dir_in: Vec2<f32>,
+    dir_out: Vec2<f32>,
+    hw: f32,
+    stroke: &Stroke,
+) {
+    let cross = dir_in.x * dir_out.y - dir_in.y * dir_out.x;
+    let dot = dir_in.x * dir_out.x + dir_in.y * dir_out.y;
+    if cross.abs() < 1e-6 && dot > 0.0 {
+        // the segments continue in a straight line
+        return;
+    }
+
+    // the join is only needed on the outside of the turn
+    let side = if cross > 0.0 { -1.0 } else { 1.0 };
+    let n_in = perp(dir_in) * (hw * side);
+    let n_out = perp(dir_out) * (hw * side);
+
+    match stroke.join {
+        LineJoin::Bevel => push_tri(tris, [p, p + n_in, p + n_out]),
+        LineJoin::Round => {
+            let sweep = cross.atan2(dot).abs() * -side;
+            push_arc_fan(tris, p, n_in, sweep);
+        }
+        LineJoin::Miter => {
+            let mid = (n_in + n_out).norm_or_zero();
+            // cos of half the angle between the segments' outer edges
+            let cos_half = (mid.x * n_in.x + mid.y * n_in.y) / hw;
+            if cos_half <= 1e-6 || 1.0 / cos_half > stroke.miter_limit {
+                push_tri(tris, [p, p + n_in, p + n_out]);
+            } else {
+                let tip = p + mid * (hw / cos_half);
+                push_tri(tris, [p, p + n_in, tip]);
+                push_tri(tris, [p, tip, p + n_out]);
+            }
+        }
+    }
+}
+
+fn push_cap(tris: &mut Vec<[Vec2<f32>; 3]>, p: Vec2<f32>, dir: Vec2<f32>, hw: f32, cap: LineCap) {
+    let n = perp(dir) * hw;
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let ext = dir * hw;
+            push_quad(tris, [p + n, p + n + ext, p - n + ext, p - n]);
+        }
+        LineCap::Round => push_arc_fan(tris, p, n, -std::f32::consts::PI),
+    }
+}
+
+/// Pushes triangles fanning out from `center`, which cover the arc from `center + start`
+/// sweeping by `sweep` radians.
+fn push_arc_fan(tris: &mut Vec<[Vec2<f32>; 3]>, center: Vec2<f32>, start: Vec2<f32>, sweep: f32) {
+    let radius = start.len() as f32;
+    let steps = arc_steps(radius, sweep);
+    let start_angle = start.y.atan2(start.x);
+
+    let mut prev = center + start;
+    for i in 1..=steps {
+        let angle = start_angle + sweep * (i as f32 / steps as f32);
+        let next = center + Vec2::new(angle.cos(), angle.sin()) * radius;
+        push_tri(tris, [center, prev, next]);
+        prev = next;
+    }
+}
+
+/// The number of line segments needed to approximate an arc to within `ROUND_TOLERANCE`.
+pub fn arc_steps(radius: f32, sweep: f32) -> usize {
+    if radius <= ROUND_TOLERANCE {
+        return 1;
+    }
+    let step = 2.0 * (1.0 - ROUND_TOLERANCE / radius).acos();
+    ((sweep.abs() / step).ceil() as usize).max(1)
+}
+
+/// Pushes a triangle, swapping its points if needed so every triangle has the same winding order.
+#[inline(always)]
+fn push_tri(tris: &mut Vec<[Vec2<f32>; 3]>, tri: [Vec2<f32>; 3]) {
+    let [a, b, c] = tri;
+    let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+    if area < 0.0 {
+        tris.push([a, c, b]);
+    } else if area > 0.0 {
+        tris.push(tri);
+    }
+}
+
+#[inline(always)]
+fn push_quad(tris: &mut Vec<[Vec2<f32>; 3]>, quad: [Vec2<f32>; 4]) {
+    push_tri(tris, [quad[0], quad[1], quad[2]]);
+    push_tri(tris, [quad[0], quad[2], quad[3]]);
+}
+
+#[inline(always)]
+fn perp(v: Vec2<f32>) -> Vec2<f32> {
+    Vec2::new(-v.y, v.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> Vec2<f32> {
+        Vec2::new(x, y)
+    }
+
+    /// If `p` is inside any of the triangles, which must all be wound the same way.
+    fn covers(tris: &[[Vec2<f32>; 3]], p: Vec2<f32>) -> bool {
+        let side =
+            |a: Vec2<f32>, b: Vec2<f32>| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+        for &[a, b, c] in tris {
+            let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+            assert!(area > 0.0, "{a:?} {b:?} {c:?}");
+        }
+        tris.iter()
+            .any(|&[a, b, c]| side(a, b) >= 0.0 && side(b, c) >= 0.0 && side(c, a) >= 0.0)
+    }
+
+    /// A right angle turning from going right to going down, stroked 2px wide.
+    fn corner(stroke: Stroke) -> Vec<[Vec2<f32>; 3]> {
+        stroke_to_tris(&[v(0.0, 0.0), v(10.0, 0.0), v(10.0, 10.0)], false, &stroke)
+    }
+
+    #[test]
+    fn joins() {
+        // the outside of the corner is at the top right
+        // (round joins and caps are within `ROUND_TOLERANCE` of the true curve, so the arc is tested just inside it)
+        let (tip, arc, bevel) = (v(10.9, -0.9), v(10.52, -0.52), v(10.3, -0.3));
+
+        let miter = corner(Stroke::new(2.0));
+        assert!(covers(&miter, tip) && covers(&miter, arc) && covers(&miter, bevel));
+        let round = corner(Stroke::new(2.0).with_join(LineJoin::Round));
+        assert!(!covers(&round, tip) && covers(&round, arc) && covers(&round, bevel));
+        let beveled = corner(Stroke::new(2.0).with_join(LineJoin::Bevel));
+        assert!(!covers(&beveled, tip) && !covers(&beveled, arc) && covers(&beveled, bevel));
+
+        // the inside of the corner is covered by the segments themselves
+        for tris in [&miter, &round, &beveled] {
+            assert!(covers(tris, v(9.1, 0.9)) && !covers(tris, v(8.9, 1.1)));
+        }
+    }
+
+    #[test]
+    fn miters_past_the_limit_are_beveled() {
+        // a right angle's miter is sqrt(2) times the stroke's width
+        let under = corner(Stroke::new(2.0).with_miter_limit(1.5));
+        assert!(covers(&under, v(10.9, -0.9)));
+        let over = corner(Stroke::new(2.0).with_miter_limit(1.4));
+        assert_eq!(over, corner(Stroke::new(2.0).with_join(LineJoin::Bevel)));
+
+        // a sharp turn would have a very long miter, so the default limit bevels it
+        let sharp = [v(0.0, 0.0), v(20.0, 0.0), v(0.0, 2.0)];
+        let tris = stroke_to_tris(&sharp, false, &Stroke::new(2.0));
+        assert!(tris.iter().flatten().all(|p| p.x < 21.1));
+        let unlimited = Stroke::new(2.0).with_miter_limit(100.0);
+        let tris = stroke_to_tris(&sharp, false, &unlimited);
+        assert!(tris.iter().flatten().any(|p| p.x > 30.0));
+    }
+
+    #[test]
+    fn caps() {
+        let line = [v(0.0, 0.0), v(10.0, 0.0)];
+        let capped = |cap: LineCap| stroke_to_tris(&line, false, &Stroke::new(2.0).with_cap(cap));
+
+        let butt = capped(LineCap::Butt);
+        assert!(covers(&butt, v(0.1, 0.9)) && covers(&butt, v(9.9, -0.9)));
+        assert!(!covers(&butt, v(-0.1, 0.0)) && !covers(&butt, v(10.1, 0.0)));
+
+        let square = capped(LineCap::Square);
+        assert!(covers(&square, v(-0.9, 0.9)) && covers(&square, v(10.9, -0.9)));
+        assert!(!covers(&square, v(-1.1, 0.0)) && !covers(&square, v(11.1, 0.0)));
+
+        let round = capped(LineCap::Round);
+        assert!(covers(&round, v(-0.74, 0.0)) && covers(&round, v(10.52, 0.52)));
+        assert!(!covers(&round, v(-0.9, 0.9)) && !covers(&round, v(11.1, 0.0)));
+
+        // closed lines have no caps
+        let closed = [v(0.0, 0.0), v(10.0, 0.0), v(10.0, 10.0)];
+        let tris = stroke_to_tris(&closed, true, &Stroke::new(2.0).with_cap(LineCap::Square));
+        assert_eq!(tris, stroke_to_tris(&closed, true, &Stroke::new(2.0)));
+    }
+
+    fn dashes(
+        points: &[Vec2<f32>],
+        closed: bool,
+        dash: &[f32],
+        offset: f32,
+    ) -> Vec<Vec<(f32, f32)>> {
+        dash_polyline(points, closed, dash, offset)
+            .iter()
+            .map(|dash| dash.iter().map(|p| (p.x, p.y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn dash_patterns() {
+        let line = [v(0.0, 0.0), v(10.0, 0.0)];
+        assert_eq!(
+            dashes(&line, false, &[2.0, 3.0], 0.0),
+            [
+                vec![(0.0, 0.0), (2.0, 0.0)],
+                vec![(5.0, 0.0), (7.0, 0.0)],
+                vec![(10.0, 0.0)],
+            ]
+        );
+        // an odd pattern is repeated, so its lengths are used for the dashes and the gaps
+        assert_eq!(
+            dashes(&line, false, &[4.0], 0.0),
+            dashes(&line, false, &[4.0, 4.0], 0.0)
+        );
+    }
+
+    #[test]
+    fn dash_offsets() {
+        let line = [v(0.0, 0.0), v(10.0, 0.0)];
+        // starting partway through the first dash
+        assert_eq!(
+            dashes(&line, false, &[2.0, 3.0], 1.0),
+            [
+                vec![(0.0, 0.0), (1.0, 0.0)],
+                vec![(4.0, 0.0), (6.0, 0.0)],
+                vec![(9.0, 0.0), (10.0, 0.0)],
+            ]
+        );
+        // offsets wrap around the pattern, in both directions
+        let shifted = dashes(&line, false, &[2.0, 3.0], -1.0);
+        assert_eq!(
+            shifted,
+            [vec![(1.0, 0.0), (3.0, 0.0)], vec![(6.0, 0.0), (8.0, 0.0)]]
+        );
+        assert_eq!(dashes(&line, false, &[2.0, 3.0], 49.0), shifted);
+    }
+
+    #[test]
+    fn dashes_carry_across_points() {
+        // the first dash turns the corner, and the pattern carries on from where it was
+        let corner = [v(0.0, 0.0), v(3.0, 0.0), v(3.0, 10.0)];
+        assert_eq!(
+            dashes(&corner, false, &[5.0, 2.0], 0.0),
+            [
+                vec![(0.0, 0.0), (3.0, 0.0), (3.0, 2.0)],
+                vec![(3.0, 4.0), (3.0, 9.0)],
+            ]
+        );
+        // a closed line carries on back to its first point
+        let square = [v(0.0, 0.0), v(4.0, 0.0), v(4.0, 4.0), v(0.0, 4.0)];
+        let closed = dashes(&square, true, &[6.0, 4.0], 0.0);
+        assert_eq!(
+            closed,
+            [
+                vec![(0.0, 0.0), (4.0, 0.0), (4.0, 2.0)],
+                vec![(2.0, 4.0), (0.0, 4.0), (0.0, 0.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn patterns_without_gaps_are_solid() {
+        let line = [v(0.0, 0.0), v(10.0, 0.0)];
+        let solid = vec![line.to_vec()];
+        for pattern in [
+            &[][..],
+            &[0.0],
+            &[0.0, 0.0],
+            &[f32::NAN, 1.0],
+            &[f32::INFINITY, 1.0],
+            &[3.0, -1.0],
+        ] {
+            for offset in [0.0, 2.5, -7.0] {
+                assert_eq!(
+                    dash_polyline(&line, false, pattern, offset),
+                    solid,
+                    "{pattern:?}"
+                );
+            }
+        }
+
+        // zero length dashes are single points
+        let dots = dashes(&line, false, &[0.0, 5.0], 0.0);
+        assert_eq!(
+            dots,
+            [vec![(0.0, 0.0)], vec![(5.0, 0.0)], vec![(10.0, 0.0)]]
+        );
+        // and lines with no length have at most one dash
+        let point = [v(1.0, 1.0)];
+        assert_eq!(dashes(&point, false, &[1.0, 1.0], 0.0), [vec![(1.0, 1.0)]]);
+        assert_eq!(
+            dashes(&point, false, &[1.0, 1.0], 1.0),
+            Vec::<Vec<_>>::new()
+        );
+        assert_eq!(dash_polyline(&[], false, &[1.0, 1.0], 0.0), vec![vec![]]);
+    }
+
+    #[test]
+    fn degenerate_lines() {
+        let stroke = Stroke::new(2.0).with_cap(LineCap::Round);
+        assert!(stroke_to_tris(&[], false, &stroke).is_empty());
+        assert!(stroke_to_tris(&[], true, &stroke).is_empty());
+
+        // repeated points are ignored
+        let line = [v(0.0, 0.0), v(10.0, 0.0)];
+        let repeated = [line[0], line[0], line[1], line[1], line[1]];
+        assert_eq!(
+            stroke_to_tris(&repeated, false, &stroke),
+            stroke_to_tris(&line, false, &stroke)
+        );
+
+        // a line with no length is only its caps
+        for points in [&[v(5.0, 5.0)][..], &[v(5.0, 5.0); 3]] {
+            let dot = stroke_to_tris(points, false, &stroke);
+            assert!(covers(&dot, v(5.74, 5.0)) && !covers(&dot, v(5.9, 5.9)));
+            let square = stroke_to_tris(points, false, &stroke.clone().with_cap(LineCap::Square));
+            assert!(covers(&square, v(5.9, 5.9)) && !covers(&square, v(6.1, 5.0)));
+            let butt = stroke_to_tris(points, false, &Stroke::new(2.0));
+            assert!(butt.is_empty());
+        }
+    }
+}
@@ -1,24 +1,26 @@
 use crate::graphics::{Color, Graphics};
-use crate::vectors::Vec2;
+use crate::paint::{self, Filter, Paint, Wrap};
+use crate::pixel_buf::PixBufView;
+use crate::vectors::{Vec2, Vec3};
 
 // note: this rasterizer expects (0, 0) to be the top-left
 
 /// Rasters a triangle represented by 3 points
-pub fn raster_tri(g: &mut Graphics, mut points: [Vec2<i32>; 3], color: Color) {
+pub fn raster_tri(g: &mut Graphics, mut points: [Vec2<i32>; 3], paint: &Paint) {
     points.sort_by(|a, b| a.y.cmp(&b.y));
-    raster_tri_sorted_y(g, points, color);
+    raster_tri_sorted_y(g, points, paint);
 }
 /// Rasters a triangle represented by 3 points, where the points are sorted by their y-coordinate's.
-pub fn raster_tri_sorted_y(g: &mut Graphics, points: [Vec2<i32>; 3], color: Color) {
+pub fn raster_tri_sorted_y(g: &mut Graphics, points: [Vec2<i32>; 3], paint: &Paint) {
     let [top, mid, bot] = points;
 
     // if the middle's y matches the bottom's y, the bottom face is flat
     if mid.y == bot.y {
-        raster_ffd_tri(g, points, color);
+        raster_ffd_tri(g, points, paint);
     }
     // if the middle's y matches the top's y, the top face is flat
     else if mid.y == top.y {
-        raster_ffu_tri(g, points, color);
+        raster_ffu_tri(g, points, paint);
     } else {
         let other = Vec2 {
             x: (top.x as f32
@@ -26,13 +28,13 @@ pub fn raster_tri_sorted_y(g: &mut Graphics, points: [Vec2<i32>; 3], color: Colo
                 as i32,
             y: mid.y,
         };
-        raster_ffd_tri(g, [top, mid, other], color);
-        raster_ffu_tri(g, [mid, other, bot], color);
+        raster_ffd_tri(g, [top, mid, other], paint);
+        raster_ffu_tri(g, [mid, other, bot], paint);
     }
 }
 
 /// Rasters a flat-face-down-triangle represented by 3 points
-pub fn raster_ffd_tri(g: &mut Graphics, points: [Vec2<i32>; 3], color: Color) {
+pub fn raster_ffd_tri(g: &mut Graphics, points: [Vec2<i32>; 3], paint: &Paint) {
     let [top, b1, b2] = points;
     let r = if b1.x > b2.x { b1 } else { b2 };
     let l = if b1.x < b2.x { b1 } else { b2 };
@@ -45,12 +47,12 @@ pub fn raster_ffd_tri(g: &mut Graphics, points: [Vec2<i32>; 3], color: Color) {
     let mut y = top.y;
 
     for i in 0..len {
-        g.fill_row(y, xl[i], xr[i], color);
+        g.paint_row(y, xl[i], xr[i], paint);
         y += 1;
     }
 }
 /// Rasters a flat-face-up-triangle represented by 3 points
-pub fn raster_ffu_tri(g: &mut Graphics, points: [Vec2<i32>; 3], color: Color) {
+pub fn raster_ffu_tri(g: &mut Graphics, points: [Vec2<i32>; 3], paint: &Paint) {
     let [t1, t2, bot] = points;
     let r = if t2.x > t1.x { t2 } else { t1 };
     let l = if t2.x < t1.x { t2 } else { t1 };
@@ -63,11 +65,181 @@ pub fn raster_ffu_tri(g: &mut Graphics, points: [Vec2<i32>; 3], color: Color) {
     let mut y = bot.y - 1;
 
     for i in 0..len {
-        g.fill_row(y, xl[i], xr[i], color);
+        g.paint_row(y, xl[i], xr[i], paint);
         y -= 1;
     }
 }
 
+/// Rasters a triangle with anti-aliased edges.
+///
+/// Each pixel is blended by an estimate of how much of it the triangle covers,
+/// found from the distance between the pixel's center and the nearest edge.
+/// The points are in pixel space, so `(0.0, 0.0)` is the top-left corner of the top-left pixel.
+pub fn raster_tri_aa(g: &mut Graphics, points: [Vec2<f32>; 3], paint: &Paint) {
+    let [a, mut b, mut c] = points;
+
+    // with a consistent winding order, the inside of the triangle is on the positive side of every edge
+    let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+    }
+
+    // each edge as the coefficients of its normalized line equation,
+    // so evaluating it at a point gives the signed distance from the edge
+    let edges = [(a, b), (b, c), (c, a)].map(|(from, to)| {
+        let dir = to - from;
+        let len = (dir.x * dir.x + dir.y * dir.y).sqrt();
+        let (nx, ny) = (-dir.y / len, dir.x / len);
+        (nx, ny, -(nx * from.x + ny * from.y))
+    });
+
+    let size = g.size().map(|e| e as i32);
+    let x0 = (a.x.min(b.x).min(c.x).floor() as i32 - 1).max(0);
+    let y0 = (a.y.min(b.y).min(c.y).floor() as i32 - 1).max(0);
+    let x1 = (a.x.max(b.x).max(c.x).ceil() as i32 + 1).min(size.x - 1);
+    let y1 = (a.y.max(b.y).max(c.y).ceil() as i32 + 1).min(size.y - 1);
+
+    for y in y0..=y1 {
+        let py = y as f32 + 0.5;
+        for x in x0..=x1 {
+            let px = x as f32 + 0.5;
+            let dist = edges
+                .iter()
+                .map(|(nx, ny, d)| nx * px + ny * py + d)
+                .fold(f32::INFINITY, f32::min);
+            let coverage = dist + 0.5;
+            if coverage > 0.0 {
+                g.paint_pixel_coverage(Vec2::new(x, y), paint, coverage);
+            }
+        }
+    }
+}
+
+/// Rasters a triangle with a color at each point, blending between them across the triangle (Gouraud shading).
+///
+/// Pixels are filled if their centers are inside the triangle. Centers exactly on an edge are only filled
+/// if it's a top or left edge, so triangles that share an edge never overlap or leave a gap between them.
+pub fn raster_tri_colored(g: &mut Graphics, points: [Vec2<i32>; 3], colors: [Color; 3]) {
+    let channels = colors.map(|c| [c.r, c.g, c.b, c.a].map(|e| e as f32));
+    let size = g.size().map(|e| e as i32);
+    tri_pixels(points.map(|p| p.map(|e| e as f32)), size, |pos, weights| {
+        let mut color = [0.0; 4];
+        for (channel, value) in color.iter_mut().enumerate() {
+            *value = (0..3).map(|i| channels[i][channel] * weights[i]).sum();
+        }
+        let color = color.map(|e| e.round().clamp(0.0, 255.0) as u8);
+        g.draw_pixel(pos, Color::from(color));
+    });
+}
+
+/// How texture coordinates are interpolated across a triangle.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum TexMapping {
+    /// Evenly across the triangle on screen, which suits flat shapes like sprites and warped images.
+    #[default]
+    Affine,
+    /// Evenly across the triangle in 3D, given the depth (the `w` after projection) of each point,
+    /// so textures on surfaces seen at an angle aren't warped.
+    Perspective([f32; 3]),
+}
+
+/// Rasters a triangle filled with part of a texture, where `uvs` are the texture coordinates of each point
+/// (from `(0.0, 0.0)` at the top-left of the texture to `(1.0, 1.0)` at the bottom-right, repeating past them).
+///
+/// The points are in pixel space, so `(0.0, 0.0)` is the top-left corner of the top-left pixel.
+/// Like `raster_tri_colored`, triangles that share an edge never overlap or leave a gap between them.
+pub fn raster_tri_textured(
+    g: &mut Graphics,
+    points: [Vec2<f32>; 3],
+    uvs: [Vec2<f32>; 3],
+    texture: PixBufView,
+    mapping: TexMapping,
+    filter: Filter,
+) {
+    let tex_size = texture.size.map(|e| e as f32);
+    // with perspective, `u / w`, `v / w` and `1 / w` are what change evenly across the screen
+    let inv_w = match mapping {
+        TexMapping::Affine => [1.0; 3],
+        TexMapping::Perspective(w) => w.map(|w| 1.0 / w),
+    };
+    let size = g.size().map(|e| e as i32);
+    tri_pixels(points, size, |pos, weights| {
+        let (mut uv, mut inv_w_sum) = (Vec2::new(0.0, 0.0), 0.0);
+        for i in 0..3 {
+            uv += uvs[i] * (weights[i] * inv_w[i]);
+            inv_w_sum += weights[i] * inv_w[i];
+        }
+        let uv = uv * (1.0 / inv_w_sum);
+        let texel = Vec2::new(uv.x * tex_size.x, uv.y * tex_size.y);
+        g.draw_pixel(pos, paint::sample(texture, texel, Wrap::Repeat, filter));
+    });
+}
+
+/// Rasters a triangle whose points each have a depth (in `z`), filling the pixels whose depth,
+/// blended from the points' depths, passes the graphics' depth test.
+pub fn raster_tri_depth(g: &mut Graphics, points: [Vec3<f32>; 3], paint: &Paint) {
+    let depths = points.map(|p| p.z);
+    let size = g.size().map(|e| e as i32);
+    tri_pixels(points.map(|p| Vec2::new(p.x, p.y)), size, |pos, weights| {
+        let depth = weights[0] * depths[0] + weights[1] * depths[1] + weights[2] * depths[2];
+        if g.depth_test(pos, depth) {
+            g.draw_pixel(pos, paint.color_at_pixel(pos));
+        }
+    });
+}
+
+/// Calls `f` for every pixel whose center is inside a triangle, with the weight of each point at that center
+/// (its barycentric coordinates, which add up to 1). Only pixels within `size` are included.
+///
+/// Centers exactly on an edge are only included if it's a top or left edge,
+/// so triangles that share an edge never overlap or leave a gap between them.
+pub(crate) fn tri_pixels(
+    points: [Vec2<f32>; 3],
+    size: Vec2<i32>,
+    mut f: impl FnMut(Vec2<i32>, [f32; 3]),
+) {
+    let [a, b, c] = points;
+    let cross = |o: Vec2<f32>, p: Vec2<f32>, q: Vec2<f32>| {
+        (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x)
+    };
+    let area = cross(a, b, c);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    // flipping the edges of an anti-clockwise triangle keeps the inside on the positive side of every edge,
+    // while keeping the weights in the same order as the points
+    let sign = area.signum();
+
+    // the edge opposite each point, and if it's a top or left edge
+    let edges = [(b, c), (c, a), (a, b)].map(|(from, to)| {
+        let dir = (to - from) * sign;
+        (from, to, (dir.y == 0.0 && dir.x > 0.0) || dir.y < 0.0)
+    });
+
+    let x0 = (a.x.min(b.x).min(c.x).floor() as i32).max(0);
+    let y0 = (a.y.min(b.y).min(c.y).floor() as i32).max(0);
+    let x1 = (a.x.max(b.x).max(c.x).ceil() as i32).min(size.x - 1);
+    let y1 = (a.y.max(b.y).max(c.y).ceil() as i32).min(size.y - 1);
+
+    let inv_area = 1.0 / area;
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = edges.map(|(from, to, _)| cross(from, to, p) * inv_area);
+            let inside = edges
+                .iter()
+                .zip(weights)
+                .all(|((_, _, top_left), w)| w > 0.0 || (w == 0.0 && *top_left));
+            if inside {
+                f(Vec2::new(x, y), weights);
+            }
+        }
+    }
+}
+
 pub fn get_line_x(mut from: Vec2<i32>, to: Vec2<i32>) -> Vec<i32> {
     let dist_x = (to.x - from.x).abs();
     let dist_y = (to.y - from.y).abs();
@@ -174,12 +174,28 @@ pub trait VecMath {
     /// The dot product of this and some other vector.
     fn dot(self, other: Self) -> f64;
 
+    /// What the cross product of two of these vectors is.
+    type Cross;
+
     /// The cross product of this and some other vector.
-    fn cross(self, other: Self) -> f64;
+    fn cross(self, other: Self) -> Self::Cross;
 
     /// Scales down this vector to have a length of 1
     fn norm(self) -> Self;
 
+    /// Like `norm`, but a vector with no length is returned unchanged (rather than as NaNs).
+    #[inline(always)]
+    fn norm_or_zero(self) -> Self
+    where
+        Self: Sized,
+    {
+        if self.len_sq() == 0.0 {
+            self
+        } else {
+            self.norm()
+        }
+    }
+
     /// The length of this vector, assuming an origin of (0, 0).
     #[inline(always)]
     fn len(&self) -> f64 {
@@ -196,6 +212,9 @@ pub trait VecMath {
 }
 
 impl<T: ToFromF64 + Copy> VecMath for Vec2<T> {
+    /// The z of the cross product of the vectors in 3D, which is positive if `other` is clockwise from this (with y pointing down).
+    type Cross = f64;
+
     #[inline(always)]
     fn len_sq(&self) -> f64 {
         self.x.to_f64() * self.x.to_f64() + self.y.to_f64() * self.y.to_f64()
@@ -221,6 +240,9 @@ impl<T: ToFromF64 + Copy> VecMath for Vec2<T> {
     }
 }
 impl<T: ToFromF64 + Copy> VecMath for Vec3<T> {
+    /// The vector perpendicular to both vectors, following the right-hand rule.
+    type Cross = Self;
+
     #[inline(always)]
     fn len_sq(&self) -> f64 {
         self.x.to_f64() * self.x.to_f64()
@@ -236,16 +258,12 @@ impl<T: ToFromF64 + Copy> VecMath for Vec3<T> {
     }
 
     #[inline(always)]
-    fn cross(self, _other: Self) -> f64 {
-        // Vec3::new(
-        // 	self.y.to_f64() * other.z.to_f64() -
-        // 	self.z.to_f64() * other.y.to_f64(),
-        // 	self.z.to_f64() * other.x.to_f64() -
-        // 	self.x.to_f64() * other.z.to_f64(),
-        // 	self.x.to_f64() * other.y.to_f64() -
-        // 	self.y.to_f64() * other.x.to_f64(),
-        // )
-        todo!()
+    fn cross(self, other: Self) -> Self {
+        Self::new(
+            T::from_f64(self.y.to_f64() * other.z.to_f64() - self.z.to_f64() * other.y.to_f64()),
+            T::from_f64(self.z.to_f64() * other.x.to_f64() - self.x.to_f64() * other.z.to_f64()),
+            T::from_f64(self.x.to_f64() * other.y.to_f64() - self.y.to_f64() * other.x.to_f64()),
+        )
     }
 
     #[inline(always)]
@@ -299,6 +317,9 @@ impl<T: Copy + ToFromF64> Vec3<T> {
     }
 }
 impl<T: ToFromF64 + Copy> VecMath for Vec4<T> {
+    /// The cross product of the xyz parts, as a direction (with a w of 0).
+    type Cross = Self;
+
     #[inline(always)]
     fn len_sq(&self) -> f64 {
         self.x.to_f64() * self.x.to_f64()
@@ -315,10 +336,11 @@ impl<T: ToFromF64 + Copy> VecMath for Vec4<T> {
             + self.w.to_f64() * other.w.to_f64()
     }
 
-    /// Not implemented for Vec4
     #[inline(always)]
-    fn cross(self, _other: Self) -> f64 {
-        unimplemented!()
+    fn cross(self, other: Self) -> Self {
+        let xyz = |v: Self| Vec3::new(v.x, v.y, v.z);
+        let c = xyz(self).cross(xyz(other));
+        Self::new(c.x, c.y, c.z, T::from_f64(0.0))
     }
 
     #[inline(always)]
@@ -332,3 +354,45 @@ impl<T: ToFromF64 + Copy> VecMath for Vec4<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_products() {
+        let (x, y, z) = (
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        assert_eq!(x.cross(y), z);
+        assert_eq!(y.cross(z), x);
+        assert_eq!(y.cross(x), Vec3::new(0.0, 0.0, -1.0));
+        // the cross product is perpendicular to both vectors
+        let (a, b) = (Vec3::new(2, -3, 5), Vec3::new(-1, 4, 2));
+        let c = a.cross(b);
+        assert_eq!((c.dot(a), c.dot(b)), (0.0, 0.0));
+
+        assert_eq!(Vec2::new(1, 0).cross(Vec2::new(0, 1)), 1.0);
+        // a Vec4's w is ignored, and its cross product is a direction
+        assert_eq!(
+            Vec4::new(1.0, 0.0, 0.0, 1.0).cross(Vec4::new(0.0, 1.0, 0.0, 5.0)),
+            Vec4::new(0.0, 0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn normalizing_zero_vectors() {
+        assert_eq!(
+            Vec3::new(0.0, 3.0, 4.0).norm_or_zero(),
+            Vec3::new(0.0, 0.6, 0.8)
+        );
+        assert_eq!(Vec2::new(0.0f32, 0.0).norm_or_zero(), Vec2::new(0.0, 0.0));
+        assert_eq!(
+            Vec3::new(0.0, 0.0, 0.0).norm_or_zero(),
+            Vec3::new(0.0, 0.0, 0.0)
+        );
+        assert!(Vec2::new(0.0f32, 0.0).norm().x.is_nan());
+    }
+}
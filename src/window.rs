@@ -1,3 +1,5 @@
+use crate::depth::DepthBuffer;
+use crate::glyph_cache::GlyphCache;
 use crate::graphics::Graphics;
 use crate::input::{InputEvent, InputState};
 use crate::pixel_buf::PixBufMutView;
@@ -116,6 +118,8 @@ pub struct CanvasConfig {
     pub max_frames: f32,
     pub max_buffer_resizes: f32,
     pub fullscreen: bool,
+    /// If `render` is given a depth buffer the size of the window, which is cleared every frame.
+    pub depth_buffer: bool,
 }
 impl CanvasConfig {
     pub fn new() -> Self {
@@ -125,6 +129,7 @@ impl CanvasConfig {
             max_frames: 60.0,
             max_buffer_resizes: 60.0,
             fullscreen: false,
+            depth_buffer: false,
         }
     }
 
@@ -148,6 +153,10 @@ impl CanvasConfig {
         self.fullscreen = fs;
         self
     }
+    pub fn with_depth_buffer(mut self, depth_buffer: bool) -> Self {
+        self.depth_buffer = depth_buffer;
+        self
+    }
 }
 
 pub fn run_canvas_app(mut app: impl CanvasApp + 'static, config: CanvasConfig) -> ! {
@@ -157,6 +166,7 @@ pub fn run_canvas_app(mut app: impl CanvasApp + 'static, config: CanvasConfig) -
         max_frames,
         max_buffer_resizes,
         fullscreen,
+        depth_buffer,
     } = config;
     let fullscreen = if fullscreen {
         Some(Fullscreen::Borderless(None))
@@ -195,6 +205,10 @@ pub fn run_canvas_app(mut app: impl CanvasApp + 'static, config: CanvasConfig) -
 
     let mut frames_this_second = 0;
 
+    // kept between frames, so text drawn every frame is only rasterized once
+    let mut glyph_cache = GlyphCache::new();
+    let mut depth_buffer = depth_buffer.then(|| DepthBuffer::new(size));
+
     let mut last_buffer_resize = Instant::now();
     let mut last_stat_update = Instant::now();
     let mut last_frame = Instant::now();
@@ -236,6 +250,15 @@ pub fn run_canvas_app(mut app: impl CanvasApp + 'static, config: CanvasConfig) -
                         size: buffer_size,
                     }
                     .create_graphics();
+                    graphics.set_glyph_cache(&mut glyph_cache);
+                    if let Some(depth_buffer) = &mut depth_buffer {
+                        if depth_buffer.size() == buffer_size {
+                            depth_buffer.clear(DepthBuffer::FAR);
+                        } else {
+                            depth_buffer.resize(buffer_size);
+                        }
+                        graphics.set_depth_buffer(depth_buffer);
+                    }
                     app.render(&mut graphics, &mut window);
 
                     pixels.render().unwrap();